@@ -0,0 +1,28 @@
+//! `cargo +nightly fuzz run envelope` from `src-tauri/`.
+
+#![no_main]
+
+use app_lib::crypto::envelope::{self, Envelope, HEADER_LEN};
+use app_lib::models::types::KeySource;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    match envelope::parse(data) {
+        Ok(Envelope::V1 {
+            aad,
+            nonce,
+            ciphertext,
+            ..
+        }) => {
+            assert_eq!(aad.len(), HEADER_LEN);
+            assert_eq!(nonce.len(), 12);
+            assert_eq!(aad.len() + ciphertext.len(), data.len());
+        }
+        Ok(Envelope::Legacy { nonce, ciphertext }) => {
+            assert_eq!(nonce.len() + ciphertext.len(), data.len());
+        }
+        Err(_) => {}
+    }
+    // Nobody can forge a file for a key they don't have.
+    assert!(envelope::open(data, &[0x42; 32], KeySource::Keyfile).is_err());
+});
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+type Listener = Box<dyn Fn() + Send + Sync>;
+
+/// Notices when the server stops accepting the stored API token, e.g. after
+/// it was revoked. Shared by every client built from the stored
+/// credentials: the first 401 marks the token rejected and calls the
+/// listener once; it stays rejected until new credentials are saved.
+#[derive(Default)]
+pub struct AuthObserver {
+    rejected: AtomicBool,
+    listener: Mutex<Option<Listener>>,
+}
+
+impl AuthObserver {
+    pub fn on_rejected(&self, listener: impl Fn() + Send + Sync + 'static) {
+        if let Ok(mut current) = self.listener.lock() {
+            *current = Some(Box::new(listener));
+        }
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.rejected.load(Ordering::SeqCst)
+    }
+
+    pub fn reject(&self) {
+        if self.rejected.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(listener) = self.listener.lock() {
+            if let Some(listener) = listener.as_ref() {
+                listener();
+            }
+        }
+    }
+
+    pub fn reset(&self) {
+        self.rejected.store(false, Ordering::SeqCst);
+    }
+}
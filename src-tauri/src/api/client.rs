@@ -1,10 +1,21 @@
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::auth::AuthObserver;
+use crate::api::error::ApiError;
+use crate::api::pinning;
+use crate::api::retry::{self, RequestPolicy};
+use crate::crypto::secret::SecretString;
 use crate::models::types::*;
 
 pub struct TwoFAuthClient<'a> {
     client: &'a Client,
     base_url: String,
-    token: String,
+    token: SecretString,
+    policy: RequestPolicy,
+    auth: Option<Arc<AuthObserver>>,
 }
 
 impl<'a> TwoFAuthClient<'a> {
@@ -13,61 +24,177 @@ impl<'a> TwoFAuthClient<'a> {
         Self {
             client,
             base_url,
-            token: token.to_string(),
+            token: token.into(),
+            policy: RequestPolicy::default(),
+            auth: None,
         }
     }
 
+    pub fn with_policy(mut self, policy: RequestPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Reports a rejected token to `auth`.
+    pub fn with_auth_observer(mut self, auth: Arc<AuthObserver>) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
     fn url(&self, path: &str) -> String {
         format!("{}{}", self.base_url, path)
     }
 
-    pub async fn verify_user(&self) -> Result<UserInfo, String> {
-        self.client
-            .get(self.url("/api/v1/user"))
-            .bearer_auth(&self.token)
-            .send()
-            .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json::<UserInfo>()
-            .await
-            .map_err(|e| format!("parse error: {e}"))
+    /// Sends a request with the bearer token. GET and DELETE are retried on
+    /// connection failures, timeouts, 429 and 502-504 with backoff (or the
+    /// server's `Retry-After`); anything else is sent exactly once.
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let mut request = request
+            .bearer_auth(self.token.expose())
+            .timeout(self.policy.timeout)
+            .build()?;
+        let idempotent = retry::is_idempotent(request.method());
+        let mut attempt = 0;
+
+        loop {
+            let next = if idempotent && attempt < self.policy.max_retries {
+                request.try_clone()
+            } else {
+                None
+            };
+            let result = self.client.execute(request).await;
+
+            let delay = match &result {
+                Ok(resp) if retry::is_retryable_status(resp.status()) => {
+                    match retry::retry_after(resp.headers()) {
+                        // Not worth blocking the UI for; surface the rate limit instead.
+                        Some(wait) if wait > self.policy.max_delay => None,
+                        Some(wait) => Some(wait),
+                        None => Some(self.policy.backoff(attempt)),
+                    }
+                }
+                Err(e) if (e.is_connect() || e.is_timeout()) && !pinning::is_pin_mismatch(e) => {
+                    Some(self.policy.backoff(attempt))
+                }
+                _ => None,
+            };
+
+            match (next, delay) {
+                (Some(next), Some(delay)) => {
+                    log::warn!(
+                        "{} {} failed (attempt {}), retrying in {:?}",
+                        next.method(),
+                        next.url().path(),
+                        attempt + 1,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    attempt += 1;
+                }
+                _ => {
+                    let result = check_status(result?).await;
+                    if let (Err(ApiError::Unauthorized { .. }), Some(auth)) = (&result, &self.auth) {
+                        auth.reject();
+                    }
+                    return result;
+                }
+            }
+        }
     }
 
-    pub async fn list_accounts(&self) -> Result<Vec<Account>, String> {
-        self.client
-            .get(self.url("/api/v1/twofaccounts"))
-            .bearer_auth(&self.token)
-            .query(&[("withOtp", "true")])
-            .send()
+    async fn send_json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ApiError> {
+        self.send(request)
+            .await?
+            .json::<T>()
             .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json::<Vec<Account>>()
+            .map_err(|e| ApiError::Parse(e.to_string()))
+    }
+
+    pub async fn verify_user(&self) -> Result<UserInfo, ApiError> {
+        self.send_json(self.client.get(self.url("/api/v1/user")))
             .await
-            .map_err(|e| format!("parse error: {e}"))
     }
 
-    pub async fn get_otp(&self, account_id: u32) -> Result<OtpValue, String> {
-        self.client
-            .get(self.url(&format!("/api/v1/twofaccounts/{}/otp", account_id)))
-            .bearer_auth(&self.token)
-            .send()
+    pub async fn list_accounts(&self) -> Result<Vec<Account>, ApiError> {
+        self.send_json(
+            self.client
+                .get(self.url("/api/v1/twofaccounts"))
+                .query(&[("withOtp", "true")]),
+        )
+        .await
+    }
+
+    pub async fn list_groups(&self) -> Result<Vec<Group>, ApiError> {
+        self.send_json(self.client.get(self.url("/api/v1/groups")))
             .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json::<OtpValue>()
+            .map_err(|e| e.unsupported("groups"))
+    }
+
+    /// Finds out which optional endpoints the server has, with reads only.
+    /// Batch delete can't be probed harmlessly, so `delete_many` learns about
+    /// it from its first call instead.
+    pub async fn probe_capabilities(&self) -> Result<ServerCapabilities, ApiError> {
+        Ok(ServerCapabilities {
+            groups: self.probe(self.client.get(self.url("/api/v1/groups"))).await?,
+            export: self
+                .probe(
+                    self.client
+                        .get(self.url("/api/v1/twofaccounts/export"))
+                        .query(&[("ids", "0")]),
+                )
+                .await?,
+            preferences: self
+                .probe(self.client.get(self.url("/api/v1/user/preferences")))
+                .await?,
+        })
+    }
+
+    /// Whether the endpoint exists: a validation error counts, since only a
+    /// route the server knows gets that far.
+    async fn probe(&self, request: RequestBuilder) -> Result<bool, ApiError> {
+        match self.send(request).await {
+            Ok(_) => Ok(true),
+            Err(ApiError::Status { status, .. }) if status == StatusCode::UNPROCESSABLE_ENTITY => {
+                Ok(true)
+            }
+            Err(e) if e.is_unsupported() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn get_preferences(&self) -> Result<Vec<UserPreference>, ApiError> {
+        self.send_json(self.client.get(self.url("/api/v1/user/preferences")))
             .await
-            .map_err(|e| format!("parse error: {e}"))
+            .map_err(|e| e.unsupported("preferences"))
+    }
+
+    pub async fn set_preference(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<UserPreference, ApiError> {
+        self.send_json(
+            self.client
+                .put(self.url(&format!("/api/v1/user/preferences/{}", key)))
+                .json(&serde_json::json!({ "value": value })),
+        )
+        .await
+        .map_err(|e| e.unsupported("preferences"))
+    }
+
+    pub async fn get_otp(&self, account_id: u32) -> Result<OtpValue, ApiError> {
+        self.send_json(
+            self.client
+                .get(self.url(&format!("/api/v1/twofaccounts/{}/otp", account_id))),
+        )
+        .await
     }
 
     pub async fn create_account(
         &self,
         payload: &CreateAccountPayload,
-    ) -> Result<Account, String> {
+    ) -> Result<Account, ApiError> {
         let mut body = serde_json::Map::new();
 
         if let Some(ref uri) = payload.uri {
@@ -99,69 +226,174 @@ impl<'a> TwoFAuthClient<'a> {
             }
         }
 
-        self.client
-            .post(self.url("/api/v1/twofaccounts"))
-            .bearer_auth(&self.token)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json::<Account>()
+        self.send_json(self.client.post(self.url("/api/v1/twofaccounts")).json(&body))
             .await
-            .map_err(|e| format!("parse error: {e}"))
     }
 
-    pub async fn preview_uri(&self, uri: &str) -> Result<AccountPreview, String> {
-        self.client
-            .post(self.url("/api/v1/twofaccounts/preview"))
-            .bearer_auth(&self.token)
-            .json(&serde_json::json!({ "uri": uri }))
-            .send()
-            .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json::<AccountPreview>()
+    pub async fn preview_uri(&self, uri: &str) -> Result<AccountPreview, ApiError> {
+        self.send_json(
+            self.client
+                .post(self.url("/api/v1/twofaccounts/preview"))
+                .json(&serde_json::json!({ "uri": uri })),
+        )
+        .await
+    }
+
+    pub async fn delete_account(&self, account_id: u32) -> Result<(), ApiError> {
+        self.send(
+            self.client
+                .delete(self.url(&format!("/api/v1/twofaccounts/{}", account_id))),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes several accounts in one request. Servers without the batch
+    /// endpoint answer 404 or 405, see `ApiError::is_unsupported`.
+    pub async fn delete_accounts(&self, ids: &[u32]) -> Result<(), ApiError> {
+        self.send(
+            self.client
+                .delete(self.url("/api/v1/twofaccounts"))
+                .query(&[("ids", join_ids(ids))]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn assign_to_group(&self, group_id: u32, ids: &[u32]) -> Result<(), ApiError> {
+        self.send(
+            self.client
+                .post(self.url(&format!("/api/v1/groups/{}/assign", group_id)))
+                .json(&serde_json::json!({ "ids": ids })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Moves accounts out of their groups.
+    pub async fn withdraw_from_groups(&self, ids: &[u32]) -> Result<(), ApiError> {
+        self.send(
+            self.client
+                .patch(self.url("/api/v1/twofaccounts/withdraw"))
+                .query(&[("ids", join_ids(ids))]),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Saves the account order; `ids` lists every account, first to last.
+    pub async fn reorder_accounts(&self, ids: &[u32]) -> Result<(), ApiError> {
+        self.send(
+            self.client
+                .post(self.url("/api/v1/twofaccounts/reorder"))
+                .json(&serde_json::json!({ "orderedIds": ids })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Fetches accounts with their secrets through 2FAuth's migration export.
+    pub async fn export_accounts(&self, ids: &[u32]) -> Result<Vec<ExportedAccount>, ApiError> {
+        let resp: ExportResponse = self
+            .send_json(
+                self.client
+                    .get(self.url("/api/v1/twofaccounts/export"))
+                    .query(&[("ids", join_ids(ids))]),
+            )
             .await
-            .map_err(|e| format!("parse error: {e}"))
+            .map_err(|e| e.unsupported("exporting accounts"))?;
+        Ok(resp.data)
+    }
+
+    /// Replaces an account's fields; 2FAuth requires the full set, secret
+    /// included, even to change one.
+    pub async fn update_account(
+        &self,
+        account_id: u32,
+        account: &ExportedAccount,
+    ) -> Result<Account, ApiError> {
+        self.send_json(
+            self.client
+                .put(self.url(&format!("/api/v1/twofaccounts/{}", account_id)))
+                .json(account),
+        )
+        .await
+    }
+
+    /// Icons are public files in the server's storage, outside the API.
+    pub async fn download_icon(&self, filename: &str) -> Result<Vec<u8>, ApiError> {
+        let resp = self
+            .send(self.client.get(self.url(&format!("/storage/icons/{}", filename))))
+            .await?;
+        Ok(resp.bytes().await?.to_vec())
     }
 
-    pub async fn delete_account(&self, account_id: u32) -> Result<(), String> {
-        self.client
-            .delete(self.url(&format!("/api/v1/twofaccounts/{}", account_id)))
-            .bearer_auth(&self.token)
-            .send()
+    pub async fn upload_icon(
+        &self,
+        image_bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+    ) -> Result<IconFile, ApiError> {
+        let part = reqwest::multipart::Part::bytes(image_bytes)
+            .file_name(file_name.to_string())
+            .mime_str(mime)?;
+        let form = reqwest::multipart::Form::new().part("icon", part);
+        self.send_json(self.client.post(self.url("/api/v1/icons")).multipart(form))
             .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?;
-        Ok(())
     }
 
-    pub async fn decode_qr(&self, image_bytes: Vec<u8>) -> Result<String, String> {
+    /// Asks the server to fetch the official icon for `service` from its
+    /// icon collection; 404 means there is none.
+    pub async fn fetch_official_icon(&self, service: &str) -> Result<IconFile, ApiError> {
+        self.send_json(
+            self.client
+                .post(self.url("/api/v1/icons/default"))
+                .json(&serde_json::json!({ "service": service })),
+        )
+        .await
+    }
+
+    pub async fn decode_qr(&self, image_bytes: Vec<u8>) -> Result<String, ApiError> {
         let part = reqwest::multipart::Part::bytes(image_bytes)
             .file_name("qrcode.png")
-            .mime_str("image/png")
-            .map_err(|e| format!("mime error: {e}"))?;
+            .mime_str("image/png")?;
 
         let form = reqwest::multipart::Form::new().part("qrcode", part);
 
         let resp: QrDecodeResponse = self
-            .client
-            .post(self.url("/api/v1/qrcode/decode"))
-            .bearer_auth(&self.token)
-            .multipart(form)
-            .send()
-            .await
-            .map_err(|e| format!("request failed: {e}"))?
-            .error_for_status()
-            .map_err(|e| format!("server error: {e}"))?
-            .json()
-            .await
-            .map_err(|e| format!("parse error: {e}"))?;
+            .send_json(self.client.post(self.url("/api/v1/qrcode/decode")).multipart(form))
+            .await?;
 
         Ok(resp.data)
     }
 }
+
+fn join_ids(ids: &[u32]) -> String {
+    ids.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Turns non-2xx responses into errors, keeping the `message` 2FAuth puts in
+/// its JSON error bodies.
+async fn check_status(resp: Response) -> Result<Response, ApiError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        // Laravel's throttle middleware always sends Retry-After; a minute is its window.
+        let wait = retry::retry_after(resp.headers()).unwrap_or(Duration::from_secs(60));
+        return Err(ApiError::RateLimited(wait));
+    }
+    let message = resp
+        .json::<ApiErrorBody>()
+        .await
+        .ok()
+        .map(|body| body.message);
+    if status == StatusCode::UNAUTHORIZED {
+        return Err(ApiError::Unauthorized { message });
+    }
+    Err(ApiError::Status { status, message })
+}
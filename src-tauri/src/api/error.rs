@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::api::pinning::{self, PIN_MISMATCH};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{PIN_MISMATCH}: the server presented a different certificate")]
+    PinMismatch,
+    #[error("request timed out")]
+    Timeout,
+    #[error("request failed: {0}")]
+    Request(String),
+    #[error("rate limited by server, retry in {}s", .0.as_secs().max(1))]
+    RateLimited(Duration),
+    #[error("the server rejected the API token{}, it may have been revoked or expired", detail(.message))]
+    Unauthorized { message: Option<String> },
+    #[error("server error: {status}{}", detail(.message))]
+    Status {
+        status: StatusCode,
+        message: Option<String>,
+    },
+    #[error("parse error: {0}")]
+    Parse(String),
+    /// A feature the server predates, named for the message.
+    #[error("server too old: {0} needs a newer version of 2FAuth")]
+    Unsupported(&'static str),
+}
+
+fn detail(message: &Option<String>) -> String {
+    message
+        .as_deref()
+        .map(|m| format!(" ({m})"))
+        .unwrap_or_default()
+}
+
+impl ApiError {
+    /// The server doesn't have the endpoint, usually because it predates it.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            ApiError::Status { status, .. }
+                if *status == StatusCode::NOT_FOUND || *status == StatusCode::METHOD_NOT_ALLOWED
+        ) || matches!(self, ApiError::Unsupported(_))
+    }
+
+    /// The server answered and turned the request down, so it had no
+    /// effect. After a timeout, a dropped connection or a server error it
+    /// may or may not have been applied.
+    pub fn is_refusal(&self) -> bool {
+        match self {
+            ApiError::PinMismatch
+            | ApiError::RateLimited(_)
+            | ApiError::Unauthorized { .. }
+            | ApiError::Unsupported(_) => true,
+            ApiError::Status { status, .. } => status.is_client_error(),
+            ApiError::Timeout | ApiError::Request(_) | ApiError::Parse(_) => false,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ApiError::Status { status, .. } if *status == StatusCode::NOT_FOUND)
+    }
+
+    /// Names `feature` if the server doesn't have its endpoint. Only for
+    /// endpoints where a 404 can't mean a missing record.
+    pub fn unsupported(self, feature: &'static str) -> Self {
+        if self.is_unsupported() {
+            ApiError::Unsupported(feature)
+        } else {
+            self
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if pinning::is_pin_mismatch(&e) {
+            ApiError::PinMismatch
+        } else if e.is_timeout() {
+            ApiError::Timeout
+        } else if e.is_decode() {
+            ApiError::Parse(e.to_string())
+        } else {
+            ApiError::Request(e.to_string())
+        }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(e: ApiError) -> Self {
+        e.to_string()
+    }
+}
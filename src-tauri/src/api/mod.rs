@@ -1 +1,5 @@
+pub mod auth;
 pub mod client;
+pub mod error;
+pub mod pinning;
+pub mod retry;
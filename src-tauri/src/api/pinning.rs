@@ -0,0 +1,167 @@
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use reqwest::Client;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+/// Prefix of every error caused by the server presenting a certificate whose
+/// public key does not match the pinned fingerprint. The webview matches on it
+/// to offer re-approval.
+pub const PIN_MISMATCH: &str = "certificate pin mismatch";
+
+#[derive(Debug, thiserror::Error)]
+#[error("{PIN_MISMATCH}")]
+struct PinMismatch;
+
+/// Records the fingerprint of the last leaf certificate that passed chain
+/// verification during a handshake, pinned or not, so a trust-on-first-use
+/// pin can be captured and a mismatching one can be shown to the user.
+/// A certificate the platform doesn't trust is never recorded.
+#[derive(Debug, Default)]
+pub struct PinObserver {
+    last_seen: Mutex<Option<String>>,
+}
+
+impl PinObserver {
+    pub fn last_seen(&self) -> Option<String> {
+        self.last_seen.lock().ok().and_then(|seen| seen.clone())
+    }
+
+    fn record(&self, fingerprint: &str) {
+        if let Ok(mut seen) = self.last_seen.lock() {
+            *seen = Some(fingerprint.to_string());
+        }
+    }
+}
+
+/// SHA-256 of the certificate's SubjectPublicKeyInfo, written as
+/// `sha256/<base64>`.
+pub fn spki_fingerprint(cert_der: &[u8]) -> Result<String, String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert_der)
+        .map_err(|e| format!("parse certificate: {e}"))?;
+    let digest = Sha256::digest(cert.tbs_certificate.subject_pki.raw);
+    Ok(format!("sha256/{}", B64.encode(digest)))
+}
+
+/// Walks an error chain looking for a pin mismatch raised by the verifier.
+pub fn is_pin_mismatch(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(e) = current {
+        if e.is::<PinMismatch>() {
+            return true;
+        }
+        if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) =
+            e.downcast_ref::<rustls::Error>()
+        {
+            return other.0.is::<PinMismatch>();
+        }
+        // io::Error hides its payload from `source()`, so step into it explicitly.
+        if let Some(inner) = e.downcast_ref::<std::io::Error>().and_then(|io| io.get_ref()) {
+            current = Some(inner);
+            continue;
+        }
+        current = e.source();
+    }
+    false
+}
+
+/// Verifies the chain against the platform trust store as usual, and on top of
+/// that rejects any leaf whose SPKI fingerprint differs from the pin.
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    pin: Option<String>,
+    observer: Arc<PinObserver>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = spki_fingerprint(end_entity.as_ref())
+            .map_err(|_| rustls::Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        self.observer.record(&fingerprint);
+
+        if let Some(ref pin) = self.pin {
+            if *pin != fingerprint {
+                return Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                    OtherError(Arc::new(PinMismatch)),
+                )));
+            }
+        }
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// The verifier `build_client` installs: `inner` checks the chain, then the
+/// leaf is reported to `observer` and, when `pin` is set, refused unless its
+/// key is the pinned one.
+pub fn verifier(
+    inner: Arc<dyn ServerCertVerifier>,
+    pin: Option<&str>,
+    observer: Arc<PinObserver>,
+) -> Arc<dyn ServerCertVerifier> {
+    Arc::new(PinningVerifier {
+        inner,
+        pin: pin.map(str::to_string),
+        observer,
+    })
+}
+
+/// Builds an HTTP client whose TLS handshakes report to `observer` and, when
+/// `pin` is set, refuse any server key other than the pinned one.
+pub fn build_client(pin: Option<&str>, observer: Arc<PinObserver>) -> Result<Client, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let platform = rustls_platform_verifier::Verifier::new(provider.clone())
+        .map_err(|e| format!("tls verifier: {e}"))?;
+    let verifier = verifier(Arc::new(platform), pin, observer);
+
+    let tls = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("tls config: {e}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+
+    Client::builder()
+        .use_preconfigured_tls(tls)
+        .build()
+        .map_err(|e| format!("http client: {e}"))
+}
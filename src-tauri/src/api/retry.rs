@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+
+use crate::models::types::Settings;
+
+/// How the request layer times out and retries calls to the 2FAuth server.
+#[derive(Debug, Clone)]
+pub struct RequestPolicy {
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(15),
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+/// The most `Settings` may ask for.
+pub const MAX_TIMEOUT_SECS: u64 = 300;
+pub const MAX_RETRIES: u32 = 10;
+
+/// `update_settings` rejects unusable values, but `settings.json` can be
+/// edited by hand, so they are clamped here too.
+impl From<&Settings> for RequestPolicy {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            timeout: Duration::from_secs(settings.request_timeout_secs.clamp(1, MAX_TIMEOUT_SECS)),
+            max_retries: settings.max_retries.min(MAX_RETRIES),
+            ..Self::default()
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// Full-jitter exponential backoff: a random delay between zero and
+    /// `base_delay * 2^attempt`, capped at `max_delay`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let millis = ceiling.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+}
+
+/// Only methods that can be repeated without side effects are retried; a POST
+/// that timed out may still have created the account.
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::DELETE)
+}
+
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Reads `Retry-After` as a number of seconds. 2FAuth (Laravel) never sends
+/// the HTTP-date form, so that is treated as absent.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
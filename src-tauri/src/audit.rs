@@ -0,0 +1,232 @@
+//! Append-only audit log of sensitive operations, one JSON entry per line
+//! in `audit.log`. Each entry carries the SHA-256 of the previous one and
+//! its own hash over all its other fields, so a line edited or removed by
+//! hand, or lost to a bad write, breaks the chain from there on. The hashes
+//! aren't keyed: whoever can write the file can also rewrite the chain to
+//! match, so this catches accidents and careless edits, not an attacker.
+//! Past `MAX_LOG_BYTES` the log is rotated to `audit.log.1` ..
+//! `audit.log.N`; the chain continues across files and the oldest file's
+//! first link is taken on trust once older ones are gone.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs4::fs_std::FileExt;
+use sha2::{Digest, Sha256};
+
+use crate::models::types::{AuditAction, AuditEntry, AuditFilter, AuditSource, AuditVerification};
+
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+const ROTATED_FILES: usize = 5;
+const TAIL_BYTES: u64 = 64 * 1024;
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+pub struct Record<'a> {
+    pub action: AuditAction,
+    pub account_id: Option<u32>,
+    pub profile: Option<&'a str>,
+    pub source: AuditSource,
+    pub detail: Option<String>,
+}
+
+fn log_path(dir: &Path, generation: usize) -> PathBuf {
+    match generation {
+        0 => dir.join("audit.log"),
+        n => dir.join(format!("audit.log.{n}")),
+    }
+}
+
+fn hash(entry: &AuditEntry) -> Result<String, String> {
+    let unhashed = AuditEntry {
+        hash: String::new(),
+        ..entry.clone()
+    };
+    let bytes = serde_json::to_vec(&unhashed).map_err(|e| format!("serialize: {e}"))?;
+    Ok(hex::encode(Sha256::digest(bytes)))
+}
+
+fn parse_lines(data: &str) -> Vec<Result<AuditEntry, String>> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).map_err(|e| format!("parse audit entry: {e}")))
+        .collect()
+}
+
+/// Reads only the end of a file; entries are far shorter than this.
+fn tail(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES))).ok()?;
+    let mut data = Vec::new();
+    file.read_to_end(&mut data).ok()?;
+    Some(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// The last entry that parses. Lines after it, such as one torn by a crash
+/// mid-write, are left for `verify` to report; new entries chain from it.
+fn last_entry(dir: &Path) -> Option<AuditEntry> {
+    for generation in 0..=ROTATED_FILES {
+        let Some(data) = tail(&log_path(dir, generation)) else {
+            continue;
+        };
+        let last = data
+            .lines()
+            .rev()
+            .filter(|l| !l.trim().is_empty())
+            .find_map(|l| serde_json::from_str(l).ok());
+        if last.is_some() {
+            return last;
+        }
+    }
+    None
+}
+
+/// Whether the file ends mid-line, so an append must start a line first.
+fn ends_mid_line(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n'
+}
+
+/// Holds `audit.lock` exclusively until dropped, so the app, the CLI and
+/// the native host append one at a time and each links to the entry
+/// before it, instead of two entries claiming the same predecessor.
+fn lock(dir: &Path) -> Result<File, String> {
+    let mut options = OpenOptions::new();
+    options.create(true).truncate(false).write(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options
+        .open(dir.join("audit.lock"))
+        .map_err(|e| format!("open audit lock: {e}"))?;
+    FileExt::lock_exclusive(&file).map_err(|e| format!("lock audit log: {e}"))?;
+    Ok(file)
+}
+
+fn rotate(dir: &Path) -> Result<(), String> {
+    let _ = fs::remove_file(log_path(dir, ROTATED_FILES));
+    for generation in (0..ROTATED_FILES).rev() {
+        let from = log_path(dir, generation);
+        if from.exists() {
+            fs::rename(&from, log_path(dir, generation + 1))
+                .map_err(|e| format!("rotate audit log: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn append(dir: &Path, record: Record) -> Result<AuditEntry, String> {
+    let _lock = lock(dir)?;
+
+    let previous = last_entry(dir);
+    let mut entry = AuditEntry {
+        seq: previous.as_ref().map_or(0, |p| p.seq + 1),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        action: record.action,
+        account_id: record.account_id,
+        profile: record.profile.map(str::to_string),
+        source: record.source,
+        detail: record.detail,
+        prev_hash: previous.map_or_else(|| GENESIS.to_string(), |p| p.hash),
+        hash: String::new(),
+    };
+    entry.hash = hash(&entry)?;
+
+    let path = log_path(dir, 0);
+    if fs::metadata(&path).is_ok_and(|m| m.len() >= MAX_LOG_BYTES) {
+        rotate(dir)?;
+    }
+    let mut line = if ends_mid_line(&path) {
+        "\n".to_string()
+    } else {
+        String::new()
+    };
+    line.push_str(&serde_json::to_string(&entry).map_err(|e| format!("serialize: {e}"))?);
+    line.push('\n');
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("open audit log: {e}"))?;
+    file.write_all(line.as_bytes())
+        .map_err(|e| format!("write audit log: {e}"))?;
+    Ok(entry)
+}
+
+/// Every entry still on disk, oldest first. A line that doesn't parse is
+/// skipped with a warning naming it; `verify` tells where the chain breaks.
+pub fn read_all(dir: &Path) -> Result<Vec<AuditEntry>, String> {
+    let mut entries = Vec::new();
+    for generation in (0..=ROTATED_FILES).rev() {
+        let path = log_path(dir, generation);
+        if let Ok(data) = fs::read_to_string(&path) {
+            for (line, entry) in parse_lines(&data).into_iter().enumerate() {
+                match entry {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => log::warn!("{} entry {}: {e}; skipped", path.display(), line + 1),
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Matching entries, newest first.
+pub fn query(dir: &Path, filter: &AuditFilter) -> Result<Vec<AuditEntry>, String> {
+    let mut entries = read_all(dir)?;
+    entries.retain(|e| {
+        filter.action.map_or(true, |a| e.action == a)
+            && filter.account_id.map_or(true, |id| e.account_id == Some(id))
+            && filter.source.map_or(true, |s| e.source == s)
+            && filter.since.map_or(true, |t| e.timestamp >= t)
+            && filter.until.map_or(true, |t| e.timestamp <= t)
+    });
+    entries.reverse();
+    if let Some(limit) = filter.limit {
+        entries.truncate(limit);
+    }
+    Ok(entries)
+}
+
+pub fn verify(dir: &Path) -> Result<AuditVerification, String> {
+    let mut entries = 0;
+    let mut previous: Option<AuditEntry> = None;
+    for generation in (0..=ROTATED_FILES).rev() {
+        let Ok(data) = fs::read_to_string(log_path(dir, generation)) else {
+            continue;
+        };
+        for entry in parse_lines(&data) {
+            let next_seq = previous.as_ref().map_or(0, |p| p.seq + 1);
+            let Ok(entry) = entry else {
+                return Ok(AuditVerification {
+                    entries,
+                    broken_at: Some(next_seq),
+                });
+            };
+            let linked = previous.as_ref().map_or(true, |p| {
+                p.hash == entry.prev_hash && entry.seq == next_seq
+            });
+            if !linked || hash(&entry)? != entry.hash {
+                return Ok(AuditVerification {
+                    entries,
+                    broken_at: Some(entry.seq),
+                });
+            }
+            previous = Some(entry);
+            entries += 1;
+        }
+    }
+    Ok(AuditVerification {
+        entries,
+        broken_at: None,
+    })
+}
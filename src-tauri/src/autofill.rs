@@ -0,0 +1,86 @@
+//! Matching the page a browser extension is on to the accounts that log in
+//! there.
+
+use reqwest::Url;
+
+use crate::models::types::{Account, UrlPattern};
+
+/// The lowercased host of an http(s) `url`, without a leading `www.`.
+pub fn host_of(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?.to_ascii_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+/// `pattern` is a host (`github.com`, which also covers its subdomains) or a
+/// wildcard host (`*.corp.example`, which covers only subdomains). A scheme
+/// or path in the pattern is ignored.
+pub fn matches_pattern(host: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let pattern = pattern.split_once("://").map_or(pattern.as_str(), |(_, rest)| rest);
+    let pattern = pattern.split('/').next().unwrap_or_default();
+    let pattern = pattern.strip_prefix("www.").unwrap_or(pattern);
+
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host.ends_with(&format!(".{suffix}")),
+        None => !pattern.is_empty() && (host == pattern || host.ends_with(&format!(".{pattern}"))),
+    }
+}
+
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Whether the account's service (the issuer in its otpauth URI) names this
+/// host. Only the registrable domain counts, since anyone can pick the
+/// labels in front of it or register a lookalike under another suffix: a
+/// service that looks like a domain must be that domain or a subdomain of
+/// it; otherwise it must equal the domain's name, ignoring case and
+/// punctuation. So "GitHub" matches `github.com` and "Proton Mail" matches
+/// `account.protonmail.com`, but neither matches `github.com.evil.io`.
+fn service_matches(host: &str, service: &str) -> bool {
+    let Some(domain) = psl::domain_str(host) else {
+        return false;
+    };
+    let service = service.trim();
+    if service.contains('.') && !service.contains(' ') {
+        let service = service.to_ascii_lowercase();
+        let service = service.strip_prefix("www.").unwrap_or(&service);
+        // A bare public suffix such as `co.uk` would cover every site on it.
+        return psl::domain_str(service).is_some()
+            && (host == service || host.ends_with(&format!(".{service}")));
+    }
+    let service = normalize(service);
+    let name = domain.split('.').next().unwrap_or_default();
+    !service.is_empty() && normalize(name) == service
+}
+
+/// Accounts for `host`. User-defined patterns win: if any pattern matches,
+/// only the accounts they point at are returned.
+pub fn match_accounts<'a>(
+    accounts: &'a [Account],
+    host: &str,
+    patterns: &[UrlPattern],
+) -> Vec<&'a Account> {
+    let pinned: Vec<_> = accounts
+        .iter()
+        .filter(|a| {
+            patterns
+                .iter()
+                .any(|p| p.account_id == a.id && matches_pattern(host, &p.pattern))
+        })
+        .collect();
+    if !pinned.is_empty() {
+        return pinned;
+    }
+    accounts
+        .iter()
+        .filter(|a| a.service.as_deref().is_some_and(|s| service_matches(host, s)))
+        .collect()
+}
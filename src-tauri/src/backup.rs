@@ -0,0 +1,203 @@
+//! Password-encrypted backups of every account, secrets included.
+//!
+//! File layout (all integers little-endian):
+//!
+//! | offset | size | field                                  |
+//! |--------|------|----------------------------------------|
+//! | 0      | 8    | magic `2FABAKUP`                       |
+//! | 8      | 1    | format version, currently `1`          |
+//! | 9      | 4    | Argon2id memory cost in KiB            |
+//! | 13     | 4    | Argon2id iterations                    |
+//! | 17     | 4    | Argon2id parallelism                   |
+//! | 21     | 16   | Argon2id salt                          |
+//! | 37     | 12   | AES-256-GCM nonce                      |
+//! | 49     | ..   | AES-256-GCM ciphertext followed by tag |
+//!
+//! The key is Argon2id(password, salt) with the stored parameters. The first
+//! 49 bytes are passed as associated data, so tampering with the version or
+//! KDF parameters fails decryption just like tampering with the ciphertext.
+//! The plaintext is a JSON `BackupPayload`.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::crypto::secret::{Secret, Zeroizing};
+use crate::import::{ImportEntry, Importer, OtpFields};
+use crate::models::types::ExportedAccount;
+
+const MAGIC: &[u8; 8] = b"2FABAKUP";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 49;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+const M_COST: u32 = 64 * 1024;
+const T_COST: u32 = 3;
+const P_COST: u32 = 1;
+/// Refuse to spend more than 1 GiB, or minutes of CPU, deriving a key for a
+/// file we didn't write.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 16;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Whether Argon2 parameters read from a file are within what we are
+/// willing to spend on them.
+pub(crate) fn affordable(m: u32, t: u32, p: u32) -> bool {
+    m <= MAX_M_COST && t <= MAX_T_COST && p <= MAX_P_COST
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupPayload {
+    pub created_at: u64,
+    pub server_url: String,
+    pub accounts: Vec<ExportedAccount>,
+}
+
+pub(crate) fn derive_key(
+    password: &str,
+    salt: &[u8],
+    m: u32,
+    t: u32,
+    p: u32,
+) -> Result<Secret<[u8; 32]>, String> {
+    let params = Params::new(m, t, p, Some(32)).map_err(|e| format!("argon2 params: {e}"))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| format!("argon2: {e}"))?;
+    Ok(Secret::new(*key))
+}
+
+/// Checks a password for a new backup, so a bad one is refused before
+/// anything is fetched from the server.
+pub fn check_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(format!(
+            "backup password must be at least {MIN_PASSWORD_LEN} characters"
+        ));
+    }
+    Ok(())
+}
+
+pub fn seal(payload: &BackupPayload, password: &str) -> Result<Vec<u8>, String> {
+    check_password(password)?;
+    let plaintext =
+        Zeroizing::new(serde_json::to_vec(payload).map_err(|e| format!("serialize: {e}"))?);
+
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&M_COST.to_le_bytes());
+    out.extend_from_slice(&T_COST.to_le_bytes());
+    out.extend_from_slice(&P_COST.to_le_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let key = derive_key(password, &salt, M_COST, T_COST, P_COST)?;
+    let cipher =
+        Aes256Gcm::new_from_slice(key.expose()).map_err(|e| format!("cipher init: {e}"))?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &plaintext,
+                aad: &out,
+            },
+        )
+        .map_err(|e| format!("encrypt: {e}"))?;
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+pub fn is_backup(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn open(data: &[u8], password: &str) -> Result<BackupPayload, String> {
+    if !is_backup(data) || data.len() < HEADER_LEN {
+        return Err("not a 2FA Auth backup".into());
+    }
+    if data[8] != VERSION {
+        return Err(format!("unsupported backup version {}", data[8]));
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let u32_at = |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]);
+    let (m, t, p) = (u32_at(9), u32_at(13), u32_at(17));
+    if !affordable(m, t, p) {
+        return Err("backup key derivation parameters are too large".into());
+    }
+    let salt = &header[21..21 + SALT_LEN];
+    let nonce = &header[37..37 + NONCE_LEN];
+
+    let key = derive_key(password, salt, m, t, p)?;
+    let cipher =
+        Aes256Gcm::new_from_slice(key.expose()).map_err(|e| format!("cipher init: {e}"))?;
+    let plaintext = Zeroizing::new(
+        cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| "wrong password or corrupt backup".to_string())?,
+    );
+    serde_json::from_slice(&plaintext).map_err(|e| format!("parse backup: {e}"))
+}
+
+pub fn entries(payload: BackupPayload) -> Vec<ImportEntry> {
+    payload.accounts.into_iter().map(entry).collect()
+}
+
+/// An exported account as something that can be created again.
+pub fn entry(a: ExportedAccount) -> ImportEntry {
+    match a.secret {
+        Some(secret) => OtpFields {
+            service: a.service,
+            account: a.account,
+            kind: a.otp_type,
+            secret,
+            algorithm: a.algorithm,
+            digits: a.digits,
+            period: a.period,
+            counter: a.counter,
+        }
+        .into(),
+        None => ImportEntry::unsupported(
+            a.service.as_deref(),
+            a.account.as_deref(),
+            &a.otp_type,
+            "backup entry has no secret".into(),
+        ),
+    }
+}
+
+/// Lets backups go through the regular import preview as well.
+pub struct Backup;
+
+impl Importer for Backup {
+    fn name(&self) -> &'static str {
+        "2fa-auth-backup"
+    }
+
+    fn detect(&self, data: &[u8], _json: Option<&Value>) -> bool {
+        is_backup(data)
+    }
+
+    fn parse(&self, data: &[u8], password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+        let password = password.ok_or("backups are encrypted, a password is required")?;
+        Ok(entries(open(data, password)?))
+    }
+}
@@ -0,0 +1,359 @@
+//! `2fa`: headless companion to the desktop app for terminals and scripts.
+//! It reads the credentials and settings the app stored, so the app must
+//! have been set up once on this machine.
+
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+
+use app_lib::api::client::TwoFAuthClient;
+use app_lib::api::error::ApiError;
+use app_lib::api::pinning::{self, PinObserver};
+use app_lib::api::retry::RequestPolicy;
+use app_lib::keystore::password;
+use app_lib::{audit, import};
+use app_lib::models::types::*;
+use app_lib::search::{self, Match};
+use app_lib::service::{AccountService, FileStorage};
+use app_lib::{qr, store};
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_NOT_CONFIGURED: u8 = 3;
+const EXIT_NO_MATCH: u8 = 4;
+
+/// Where scripts put the master password when the app uses one.
+const PASSWORD_ENV: &str = "TWOFA_MASTER_PASSWORD";
+
+#[derive(Parser)]
+#[command(
+    name = "2fa",
+    version,
+    about = "Command-line companion to 2FA Auth",
+    after_help = "Exit codes: 0 success, 1 error, 2 usage, 3 not configured, 4 no single matching account"
+)]
+struct Cli {
+    /// Print JSON instead of plain text
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List all accounts
+    List,
+    /// Print the current code of the one account matching QUERY
+    Get { query: String },
+    /// Add an account from an otpauth:// URI
+    Add { uri: String },
+    /// Delete an account by id
+    Delete { id: u32 },
+    /// Decode a QR code image and print the URI it holds
+    Scan {
+        image: PathBuf,
+        /// Also add the decoded account
+        #[arg(long)]
+        add: bool,
+    },
+    /// Import an Aegis, 2FAS, andOTP, Bitwarden or 2FA Auth backup file
+    Import {
+        file: PathBuf,
+        /// Only show what would be imported
+        #[arg(long)]
+        dry_run: bool,
+        /// Read the file's password from the first line of stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+}
+
+struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: u8, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::new(EXIT_ERROR, message)
+    }
+}
+
+impl From<ApiError> for Failure {
+    fn from(e: ApiError) -> Self {
+        Self::new(EXIT_ERROR, e.to_string())
+    }
+}
+
+struct Session {
+    dir: PathBuf,
+    creds: Credentials,
+    http: reqwest::Client,
+    policy: RequestPolicy,
+}
+
+impl Session {
+    fn load() -> Result<Self, Failure> {
+        let dir = store::data_dir()?;
+        store::restrict_permissions(&dir)?;
+        if password::is_locked(&dir)? {
+            let master = std::env::var(PASSWORD_ENV).map_err(|_| {
+                Failure::new(
+                    EXIT_NOT_CONFIGURED,
+                    format!("the vault is locked, set {PASSWORD_ENV} to the master password"),
+                )
+            })?;
+            password::unlock(&dir, &master)?;
+        }
+        let creds = store::read_credentials(&dir)?.ok_or_else(|| {
+            Failure::new(
+                EXIT_NOT_CONFIGURED,
+                "not configured, connect to your server in the desktop app first",
+            )
+        })?;
+        let settings = store::read_settings(&dir)?;
+        let http = pinning::build_client(
+            creds.cert_pin.as_deref(),
+            Arc::new(PinObserver::default()),
+        )?;
+        Ok(Self {
+            dir,
+            creds,
+            http,
+            policy: RequestPolicy::from(&settings),
+        })
+    }
+
+    fn client(&self) -> TwoFAuthClient<'_> {
+        TwoFAuthClient::new(&self.http, &self.creds.server_url, self.creds.api_token.expose())
+            .with_policy(self.policy.clone())
+    }
+
+    fn accounts(&self) -> AccountService<TwoFAuthClient<'_>, FileStorage> {
+        AccountService::new(
+            self.client(),
+            FileStorage::new(self.dir.clone()),
+            &self.creds.server_url,
+        )
+        .with_capabilities(self.creds.capabilities)
+    }
+
+    /// Records the operation in the app's audit log; a failure to write is
+    /// reported but doesn't fail the command that already succeeded.
+    fn audit(&self, action: AuditAction, account_id: Option<u32>, detail: Option<String>) {
+        let record = audit::Record {
+            action,
+            account_id,
+            profile: Some(&self.creds.server_url),
+            source: AuditSource::Cli,
+            detail,
+        };
+        if let Err(e) = audit::append(&self.dir, record) {
+            eprintln!("2fa: audit log: {e}");
+        }
+    }
+}
+
+fn print<T: Serialize>(json: bool, value: &T, plain: impl FnOnce(&T) -> String) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+    } else {
+        println!("{}", plain(value));
+    }
+}
+
+fn describe(service: Option<&str>, account: Option<&str>) -> String {
+    match (service, account) {
+        (Some(s), Some(a)) => format!("{s} ({a})"),
+        (Some(s), None) => s.to_string(),
+        (None, Some(a)) => a.to_string(),
+        (None, None) => "-".to_string(),
+    }
+}
+
+fn find_account<'a>(accounts: &'a [Account], query: &str) -> Result<&'a Account, Failure> {
+    match search::resolve(accounts, query) {
+        Match::One(account) => Ok(account),
+        other => Err(Failure::new(
+            EXIT_NO_MATCH,
+            search::describe_failure(query, &other),
+        )),
+    }
+}
+
+fn read_password_stdin() -> Result<String, Failure> {
+    let mut line = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("read password: {e}"))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+async fn run(cli: &Cli) -> Result<(), Failure> {
+    match cli.command {
+        Command::List => {
+            let session = Session::load()?;
+            let accounts = session.accounts().list().await?;
+            print(cli.json, &accounts, |accounts| {
+                accounts
+                    .iter()
+                    .map(|a| format!("{:>5}  {}", a.id, search::label(a)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Get { ref query } => {
+            let session = Session::load()?;
+            let service = session.accounts();
+            let accounts = service.list().await?;
+            let account = find_account(&accounts, query)?;
+            let otp = service.otp(account.id).await?;
+            session.audit(AuditAction::CodeRequested, Some(account.id), None);
+            print(
+                cli.json,
+                &json!({
+                    "id": account.id,
+                    "service": account.service,
+                    "account": account.account,
+                    "otp": otp,
+                }),
+                |_| otp.password.clone(),
+            );
+        }
+        Command::Add { ref uri } => {
+            let session = Session::load()?;
+            let account = session.accounts().create(&uri_payload(uri)).await?;
+            session.audit(AuditAction::AccountCreated, Some(account.id), account.service.clone());
+            print(cli.json, &account, |a| format!("added {} [{}]", search::label(a), a.id));
+        }
+        Command::Delete { id } => {
+            let session = Session::load()?;
+            session.accounts().delete(id).await?;
+            session.audit(AuditAction::AccountDeleted, Some(id), None);
+            print(cli.json, &json!({ "deleted": id }), |_| format!("deleted {id}"));
+        }
+        Command::Scan { ref image, add } => {
+            let data = std::fs::read(image).map_err(|e| format!("read {}: {e}", image.display()))?;
+            let uri = qr::decode(&data)?;
+            if add {
+                let session = Session::load()?;
+                let account = session.accounts().create(&uri_payload(&uri)).await?;
+                session.audit(AuditAction::AccountCreated, Some(account.id), account.service.clone());
+                print(cli.json, &account, |a| format!("added {} [{}]", search::label(a), a.id));
+            } else {
+                print(cli.json, &json!({ "uri": uri }), |_| uri.clone());
+            }
+        }
+        Command::Import {
+            ref file,
+            dry_run,
+            password_stdin,
+        } => {
+            let data = std::fs::read(file).map_err(|e| format!("read {}: {e}", file.display()))?;
+            let password = if password_stdin {
+                Some(read_password_stdin()?)
+            } else {
+                None
+            };
+            let importer = import::detect(&data)?;
+            let entries = importer.parse(&data, password.as_deref())?;
+
+            let session = Session::load()?;
+            let service = session.accounts();
+            let preview = service.preview_import(importer.name(), &entries).await?;
+
+            if dry_run {
+                print(cli.json, &preview, |p| {
+                    p.candidates
+                        .iter()
+                        .map(|c| {
+                            format!(
+                                "{:>4}  {:<11}  {}{}",
+                                c.index,
+                                format!("{:?}", c.status).to_lowercase(),
+                                describe(c.service.as_deref(), c.account.as_deref()),
+                                c.reason.as_deref().map(|r| format!(" ({r})")).unwrap_or_default()
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                });
+                return Ok(());
+            }
+
+            let ready = preview
+                .candidates
+                .iter()
+                .filter(|c| c.status == ImportStatus::Ready)
+                .map(|c| c.index);
+            let results = service.create_entries(&entries, ready).await;
+            for account in results.iter().filter_map(|r| r.account.as_ref()) {
+                session.audit(AuditAction::AccountCreated, Some(account.id), account.service.clone());
+            }
+            let failed = results.iter().filter(|r| r.error.is_some()).count();
+            session.audit(
+                AuditAction::AccountsImported,
+                None,
+                Some(format!("{} of {} accounts", results.len() - failed, results.len())),
+            );
+            print(cli.json, &results, |results| {
+                format!(
+                    "imported {} of {} entries ({} skipped, {} failed)",
+                    results.len() - failed,
+                    entries.len(),
+                    entries.len() - results.len(),
+                    failed
+                )
+            });
+            if failed > 0 {
+                return Err(Failure::new(EXIT_ERROR, format!("{failed} entries failed to import")));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn uri_payload(uri: &str) -> CreateAccountPayload {
+    CreateAccountPayload {
+        uri: Some(uri.to_string()),
+        service: None,
+        account: None,
+        secret: None,
+        otp_type: None,
+        digits: None,
+        period: None,
+        counter: None,
+        algorithm: None,
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(&cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            if cli.json {
+                eprintln!("{}", json!({ "error": failure.message }));
+            } else {
+                eprintln!("2fa: {}", failure.message);
+            }
+            ExitCode::from(failure.code)
+        }
+    }
+}
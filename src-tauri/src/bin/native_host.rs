@@ -0,0 +1,113 @@
+//! `2fa-native-host`: Chrome/Firefox native messaging host for the autofill
+//! extension. The browser starts it and exchanges JSON messages over stdio,
+//! each prefixed with its length as a native-endian u32:
+//!
+//! ```text
+//! -> {"type":"ping"}
+//! <- {"ok":true,"result":"pong"}
+//! -> {"type":"get_code","url":"https://github.com/login"}
+//! <- {"ok":true,"result":{"id":3,"service":"GitHub","account":"me","otp":{...}}}
+//! <- {"ok":false,"error":"request denied"}
+//! ```
+//!
+//! Codes come from the running desktop app over its IPC socket, which asks
+//! the user to confirm each request in the tray, so the host never sees the
+//! API token. The socket has to be enabled in the app's settings.
+
+use std::io::{self, Read, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Chrome refuses messages from a host larger than 1 MiB; nothing the
+/// extension sends comes near that either.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Ping,
+    GetCode { url: String },
+}
+
+fn read_message(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long"));
+    }
+    let mut message = vec![0u8; len];
+    input.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    output.write_all(&(body.len() as u32).to_ne_bytes())?;
+    output.write_all(body.as_bytes())?;
+    output.flush()
+}
+
+/// Chrome passes the calling extension's origin as the first argument;
+/// Firefox passes the manifest path first and the extension id second.
+fn caller_origin() -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    args.iter()
+        .find(|a| a.starts_with("chrome-extension://"))
+        .or_else(|| args.get(1))
+        .cloned()
+}
+
+#[cfg(unix)]
+fn ask_app(request: &Value) -> Result<Value, String> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let path = app_lib::store::socket_path(&app_lib::store::data_dir()?);
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|e| format!("2FA Auth is not running or IPC is disabled: {e}"))?;
+    let mut line = request.to_string();
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| format!("write: {e}"))?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .map_err(|e| format!("read: {e}"))?;
+    serde_json::from_str(&response).map_err(|e| format!("bad response from app: {e}"))
+}
+
+#[cfg(not(unix))]
+fn ask_app(_request: &Value) -> Result<Value, String> {
+    Err("browser autofill is not supported on this platform yet".into())
+}
+
+fn handle(message: &[u8], origin: Option<&str>) -> Value {
+    let result = match serde_json::from_slice::<Message>(message) {
+        Ok(Message::Ping) => return json!({ "ok": true, "result": "pong" }),
+        Ok(Message::GetCode { url }) => ask_app(&json!({
+            "method": "autofill",
+            "url": url,
+            "origin": origin,
+        })),
+        Err(e) => Err(format!("bad message: {e}")),
+    };
+    result.unwrap_or_else(|error| json!({ "ok": false, "error": error }))
+}
+
+fn main() -> io::Result<()> {
+    let origin = caller_origin();
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        write_message(&mut stdout, &handle(&message, origin.as_deref()))?;
+    }
+    Ok(())
+}
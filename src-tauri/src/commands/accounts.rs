@@ -1,8 +1,13 @@
-use crate::api::client::TwoFAuthClient;
+use std::sync::Arc;
+use tauri::AppHandle;
+
+use super::{account_service, audit, data_dir};
 use crate::models::types::*;
 use crate::state::AppState;
+use crate::store;
+use crate::tray;
 
-fn get_creds(state: &AppState) -> Result<Credentials, String> {
+pub(crate) fn get_creds(state: &AppState) -> Result<Arc<Credentials>, String> {
     state
         .credentials
         .lock()
@@ -11,41 +16,194 @@ fn get_creds(state: &AppState) -> Result<Credentials, String> {
         .ok_or_else(|| "not configured".to_string())
 }
 
+/// A cached account as it can be shown once its code is out of date.
+pub(crate) fn without_otp(account: &Account) -> Account {
+    Account {
+        otp: None,
+        ..account.clone()
+    }
+}
+
 #[tauri::command]
 pub async fn fetch_accounts(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<Account>, String> {
-    let creds = get_creds(&state)?;
-    let client = TwoFAuthClient::new(&state.http_client, &creds.server_url, &creds.api_token);
-    client.list_accounts().await
+    // Keep showing what was fetched last until the user signs in again,
+    // without the codes, which are long out of date by then.
+    if state.auth.is_rejected() {
+        let cached = state.accounts.lock().map_err(|e| format!("lock: {e}"))?;
+        return Ok(cached.iter().flatten().map(without_otp).collect());
+    }
+    let http = state.http_client()?;
+    let accounts = account_service(&app, &state, &http)?.list().await?;
+    *state.accounts.lock().map_err(|e| format!("lock: {e}"))? = Some(accounts.clone());
+    *state.group_names.lock().map_err(|e| format!("lock: {e}"))? = None;
+    tray::refresh_menu(&app);
+    Ok(accounts)
 }
 
 #[tauri::command]
 pub async fn create_account(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     payload: CreateAccountPayload,
 ) -> Result<Account, String> {
-    let creds = get_creds(&state)?;
-    let client = TwoFAuthClient::new(&state.http_client, &creds.server_url, &creds.api_token);
-    client.create_account(&payload).await
+    let http = state.http_client()?;
+    let account = account_service(&app, &state, &http)?.create(&payload).await?;
+    audit::record(
+        &app,
+        AuditAction::AccountCreated,
+        Some(account.id),
+        AuditSource::App,
+        account.service.clone(),
+    );
+    Ok(account)
 }
 
 #[tauri::command]
 pub async fn preview_account(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     uri: String,
 ) -> Result<AccountPreview, String> {
-    let creds = get_creds(&state)?;
-    let client = TwoFAuthClient::new(&state.http_client, &creds.server_url, &creds.api_token);
-    client.preview_uri(&uri).await
+    let http = state.http_client()?;
+    account_service(&app, &state, &http)?.preview(&uri).await
 }
 
+/// Deletes on the server, keeping a copy in the trash for `undo_delete`, or
+/// with `for_good` without one, for servers that can't export it.
 #[tauri::command]
 pub async fn delete_account(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     account_id: u32,
+    for_good: Option<bool>,
 ) -> Result<(), String> {
-    let creds = get_creds(&state)?;
-    let client = TwoFAuthClient::new(&state.http_client, &creds.server_url, &creds.api_token);
-    client.delete_account(account_id).await
+    let http = state.http_client()?;
+    let service = account_service(&app, &state, &http)?;
+    if for_good.unwrap_or(false) {
+        service.delete_for_good(account_id).await?;
+        audit::record(
+            &app,
+            AuditAction::AccountDeleted,
+            Some(account_id),
+            AuditSource::App,
+            Some("not kept in the trash".into()),
+        );
+    } else {
+        service.delete(account_id).await?;
+        audit::record(&app, AuditAction::AccountDeleted, Some(account_id), AuditSource::App, None);
+    }
+    Ok(())
+}
+
+/// Deletes several accounts, each kept in the trash like `delete_account`.
+/// Accounts that fail don't stop the others.
+#[tauri::command]
+pub async fn delete_accounts(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_ids: Vec<u32>,
+) -> Result<Vec<BatchResult>, String> {
+    let http = state.http_client()?;
+    let results = account_service(&app, &state, &http)?
+        .delete_many(&account_ids)
+        .await?;
+    for result in results.iter().filter(|r| r.error.is_none()) {
+        audit::record(
+            &app,
+            AuditAction::AccountDeleted,
+            Some(result.account_id),
+            AuditSource::App,
+            Some("batch".into()),
+        );
+    }
+    Ok(results)
+}
+
+/// Moves accounts into a group, or out of any group when `group_id` is
+/// `None`. The server does this in one request, so they all succeed or all
+/// fail.
+#[tauri::command]
+pub async fn move_accounts_to_group(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_ids: Vec<u32>,
+    group_id: Option<u32>,
+) -> Result<Vec<BatchResult>, String> {
+    let http = state.http_client()?;
+    let results = account_service(&app, &state, &http)?
+        .move_to_group(&account_ids, group_id)
+        .await?;
+
+    let moved: Vec<u32> = results
+        .iter()
+        .filter(|r| r.error.is_none())
+        .map(|r| r.account_id)
+        .collect();
+    let mut cached = state.accounts.lock().map_err(|e| format!("lock: {e}"))?;
+    for account in cached.iter_mut().flatten().filter(|a| moved.contains(&a.id)) {
+        account.group_id = group_id;
+    }
+    drop(cached);
+    let detail = match group_id {
+        Some(id) => format!("moved to group {id}"),
+        None => "removed from group".to_string(),
+    };
+    for id in moved {
+        audit::record(
+            &app,
+            AuditAction::AccountUpdated,
+            Some(id),
+            AuditSource::App,
+            Some(detail.clone()),
+        );
+    }
+    Ok(results)
+}
+
+/// Saves a new account order on the server after the user dragged an
+/// account; `account_ids` lists every account in its new position.
+#[tauri::command]
+pub async fn reorder_accounts(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_ids: Vec<u32>,
+) -> Result<(), String> {
+    let http = state.http_client()?;
+    account_service(&app, &state, &http)?
+        .reorder(&account_ids)
+        .await?;
+
+    let mut cached = state.accounts.lock().map_err(|e| format!("lock: {e}"))?;
+    if let Some(cached) = cached.as_mut() {
+        cached.sort_by_key(|a| {
+            account_ids
+                .iter()
+                .position(|id| *id == a.id)
+                .unwrap_or(usize::MAX)
+        });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_pinned(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_id: u32,
+    pinned: bool,
+) -> Result<Vec<u32>, String> {
+    let settings = {
+        let mut settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+        settings.pinned.retain(|id| *id != account_id);
+        if pinned {
+            settings.pinned.push(account_id);
+        }
+        settings.clone()
+    };
+    store::write_settings(&data_dir(&app)?, &settings)?;
+    tray::refresh_menu(&app);
+    Ok(settings.pinned)
 }
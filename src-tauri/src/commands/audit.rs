@@ -0,0 +1,51 @@
+use tauri::{AppHandle, Manager};
+
+use super::data_dir;
+use crate::audit::{self, Record};
+use crate::models::types::*;
+use crate::state::AppState;
+
+/// Appends to the audit log. A failure to write is logged rather than
+/// failing the operation that was already carried out.
+pub(crate) fn record(
+    app: &AppHandle,
+    action: AuditAction,
+    account_id: Option<u32>,
+    source: AuditSource,
+    detail: Option<String>,
+) {
+    let state = app.state::<AppState>();
+    let profile = state
+        .credentials
+        .lock()
+        .ok()
+        .and_then(|c| c.as_ref().map(|c| c.server_url.clone()));
+    let result = data_dir(app).and_then(|dir| {
+        audit::append(
+            &dir,
+            Record {
+                action,
+                account_id,
+                profile: profile.as_deref(),
+                source,
+                detail,
+            },
+        )
+    });
+    if let Err(e) = result {
+        log::error!("audit: {e}");
+    }
+}
+
+#[tauri::command]
+pub async fn query_audit_log(
+    app: AppHandle,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+    audit::query(&data_dir(&app)?, &filter.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn verify_audit_log(app: AppHandle) -> Result<AuditVerification, String> {
+    audit::verify(&data_dir(&app)?)
+}
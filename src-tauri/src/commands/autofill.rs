@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::models::types::{AutofillCandidate, AutofillRequest};
+use crate::state::{AppState, PendingAutofill};
+use crate::tray;
+
+/// Requests nobody answers are denied after this.
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Shows the request in the tray popup and waits for the user to pick one of
+/// the candidates. `None` means denied or timed out.
+pub(crate) async fn confirm(
+    app: &AppHandle,
+    origin: Option<String>,
+    host: String,
+    candidates: Vec<AutofillCandidate>,
+) -> Result<Option<u32>, String> {
+    let request = AutofillRequest {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        origin,
+        host,
+        candidates,
+    };
+    let (reply, answer) = oneshot::channel();
+    let state = app.state::<AppState>();
+    state
+        .pending_autofill
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .insert(
+            request.id,
+            PendingAutofill {
+                request: request.clone(),
+                reply,
+            },
+        );
+
+    app.emit_to("tray-popup", "autofill-request", &request)
+        .map_err(|e| format!("emit: {e}"))?;
+    tray::show_tray_popup(app);
+
+    let choice = tokio::time::timeout(CONFIRM_TIMEOUT, answer)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .flatten();
+    state
+        .pending_autofill
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .remove(&request.id);
+    let _ = app.emit_to("tray-popup", "autofill-resolved", request.id);
+
+    Ok(choice.filter(|id| request.candidates.iter().any(|c| c.id == *id)))
+}
+
+#[tauri::command]
+pub async fn pending_autofill_requests(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AutofillRequest>, String> {
+    let pending = state
+        .pending_autofill
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?;
+    let mut requests: Vec<_> = pending.values().map(|p| p.request.clone()).collect();
+    requests.sort_by_key(|r| r.id);
+    Ok(requests)
+}
+
+#[tauri::command]
+pub async fn respond_autofill(
+    state: tauri::State<'_, AppState>,
+    request_id: u64,
+    account_id: Option<u32>,
+) -> Result<(), String> {
+    let pending = state
+        .pending_autofill
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .remove(&request_id)
+        .ok_or("autofill request expired")?;
+    let _ = pending.reply.send(account_id);
+    Ok(())
+}
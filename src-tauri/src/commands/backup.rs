@@ -0,0 +1,78 @@
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use super::accounts::get_creds;
+use super::import::audit_created;
+use super::{account_service, audit};
+use crate::backup::{self, BackupPayload};
+use crate::models::types::*;
+use crate::service;
+use crate::state::AppState;
+use crate::store;
+
+/// Pulls every account with its secret and writes an encrypted backup to a
+/// file the user picks. Returns the path written, or `None` if the save
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn export_backup(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    password: String,
+) -> Result<Option<String>, String> {
+    backup::check_password(&password)?;
+    let creds = get_creds(&state)?;
+    let http = state.http_client()?;
+    let accounts = account_service(&app, &state, &http)?.export_all().await?;
+    let created_at = service::now();
+    let count = accounts.len();
+    let sealed = backup::seal(
+        &BackupPayload {
+            created_at,
+            server_url: creds.server_url.clone(),
+            accounts,
+        },
+        &password,
+    )?;
+
+    let (tx, rx) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Save Backup")
+        .set_file_name("2fa-auth-backup.2fabak")
+        .add_filter("2FA Auth backup", &["2fabak"])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let Some(path) = rx.await.map_err(|_| "backup path: the dialog went away".to_string())? else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| format!("backup path: {e}"))?;
+    store::write_atomic(&path, &sealed)?;
+    audit::record(
+        &app,
+        AuditAction::BackupExported,
+        None,
+        AuditSource::App,
+        Some(format!("{count} accounts to {}", path.display())),
+    );
+    Ok(Some(path.display().to_string()))
+}
+
+/// Re-creates every account from a backup on the configured server, which
+/// need not be the one it was taken from. Accounts that already exist there
+/// are skipped and reported as such.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    data: Vec<u8>,
+    password: String,
+) -> Result<Vec<ImportResult>, String> {
+    let entries = backup::entries(backup::open(&data, &password)?);
+
+    let http = state.http_client()?;
+    let results = account_service(&app, &state, &http)?.restore(&entries).await?;
+    audit_created(&app, AuditAction::BackupRestored, &results);
+    Ok(results)
+}
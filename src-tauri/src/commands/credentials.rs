@@ -1,70 +1,63 @@
-use base64::{engine::general_purpose::STANDARD as B64, Engine};
-use std::fs;
-use tauri::{AppHandle, Manager};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
 
+use super::accounts::get_creds;
+use super::{audit, credential_service, data_dir};
 use crate::api::client::TwoFAuthClient;
-use crate::crypto::encryption;
-use crate::models::types::{Credentials, UserInfo};
-use crate::state::AppState;
-
-fn credentials_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("app data dir: {e}"))?;
-    fs::create_dir_all(&dir).map_err(|e| format!("create dir: {e}"))?;
-    Ok(dir.join("credentials.enc"))
-}
-
-fn key_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
-    let dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("app data dir: {e}"))?;
-    fs::create_dir_all(&dir).map_err(|e| format!("create dir: {e}"))?;
-    Ok(dir.join("keyfile.key"))
-}
-
-fn load_or_create_key(app: &AppHandle) -> Result<[u8; 32], String> {
-    let path = key_path(app)?;
-    if path.exists() {
-        let encoded = fs::read_to_string(&path).map_err(|e| format!("read key: {e}"))?;
-        let bytes = B64
-            .decode(encoded.trim())
-            .map_err(|e| format!("decode key: {e}"))?;
-        if bytes.len() != 32 {
-            return Err("invalid key length".into());
-        }
-        let mut key = [0u8; 32];
-        key.copy_from_slice(&bytes);
-        Ok(key)
-    } else {
-        let key = encryption::generate_key();
-        let encoded = B64.encode(key);
-        fs::write(&path, encoded).map_err(|e| format!("write key: {e}"))?;
-        Ok(key)
-    }
-}
+use crate::api::pinning::{self, PinObserver};
+use crate::crypto::secret::SecretString;
+use crate::models::types::{
+    AuditAction, AuditSource, CertificatePinStatus, Credentials, CredentialsView, UserInfo,
+};
+use crate::state::{AppState, PendingPin, ProbedServer};
+use crate::{store, tray};
 
 #[tauri::command]
 pub async fn save_credentials(
     app: AppHandle,
     state: tauri::State<'_, AppState>,
     server_url: String,
-    api_token: String,
+    api_token: SecretString,
+    pin_certificate: Option<bool>,
 ) -> Result<(), String> {
+    let server_url = server_url.trim_end_matches('/').to_string();
+    let cert_pin = if pin_certificate.unwrap_or(false) {
+        let pending = state
+            .pending_pin
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?
+            .take();
+        match pending {
+            Some(p) if p.server_url == server_url => Some(p.fingerprint),
+            // Plain HTTP has no certificate to pin.
+            _ if !server_url.starts_with("https://") => None,
+            _ => return Err("test the connection before pinning its certificate".into()),
+        }
+    } else {
+        None
+    };
+
+    let probed = state
+        .probed_server
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .take();
+    let (capabilities, email) = match probed {
+        Some(p) if p.server_url == server_url => (p.capabilities, p.email),
+        _ => (None, None),
+    };
+
     let creds = Credentials {
-        server_url: server_url.trim_end_matches('/').to_string(),
+        server_url,
         api_token,
+        cert_pin,
+        capabilities,
+        email,
     };
-    let json = serde_json::to_vec(&creds).map_err(|e| format!("serialize: {e}"))?;
-    let key = load_or_create_key(&app)?;
-    let encrypted = encryption::encrypt(&json, &key)?;
-    let path = credentials_path(&app)?;
-    fs::write(&path, &encrypted).map_err(|e| format!("write file: {e}"))?;
-
-    let mut lock = state.credentials.lock().map_err(|e| format!("lock: {e}"))?;
-    *lock = Some(creds);
+    update_credentials(&app, &state, creds)?;
+    restore_auth(&app, &state);
+    audit::record(&app, AuditAction::CredentialsSaved, None, AuditSource::App, None);
     Ok(())
 }
 
@@ -73,23 +66,13 @@ pub async fn load_credentials(
     app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<bool, String> {
-    let path = credentials_path(&app)?;
-    if !path.exists() {
-        return Ok(false);
-    }
-    let key_file = key_path(&app)?;
-    if !key_file.exists() {
+    let Some(creds) = credential_service(&app)?.load()? else {
         return Ok(false);
-    }
-
-    let key = load_or_create_key(&app)?;
-    let encrypted = fs::read(&path).map_err(|e| format!("read file: {e}"))?;
-    let decrypted = encryption::decrypt(&encrypted, &key)?;
-    let creds: Credentials =
-        serde_json::from_slice(&decrypted).map_err(|e| format!("parse: {e}"))?;
+    };
+    state.apply_pin(creds.cert_pin.as_deref())?;
 
     let mut lock = state.credentials.lock().map_err(|e| format!("lock: {e}"))?;
-    *lock = Some(creds);
+    *lock = Some(Arc::new(creds));
     Ok(true)
 }
 
@@ -98,37 +81,241 @@ pub async fn clear_credentials(
     app: AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
-    let path = credentials_path(&app)?;
-    if path.exists() {
-        fs::remove_file(&path).map_err(|e| format!("remove file: {e}"))?;
-    }
-    let kp = key_path(&app)?;
-    if kp.exists() {
-        fs::remove_file(&kp).map_err(|e| format!("remove key: {e}"))?;
-    }
+    credential_service(&app)?.clear()?;
+    // Clearing goes back to the keyfile.
+    super::settings::init(&app)?;
+    audit::record(&app, AuditAction::CredentialsCleared, None, AuditSource::App, None);
+    state.apply_pin(None)?;
+    state.auth.reset();
     let mut lock = state.credentials.lock().map_err(|e| format!("lock: {e}"))?;
     *lock = None;
+    drop(lock);
+    state.forget_accounts()?;
+    tray::refresh_menu(&app);
     Ok(())
 }
 
+/// Clears a rejected token once new credentials are in, and tells every
+/// window with `auth-restored`, the counterpart of `auth-required`.
+fn restore_auth(app: &AppHandle, state: &AppState) {
+    state.auth.reset();
+    let _ = app.emit("auth-restored", ());
+}
+
+/// Whether the server rejected the stored token and new credentials are
+/// needed. Becomes true with the `auth-required` event and false with
+/// `auth-restored`.
+#[tauri::command]
+pub async fn is_auth_required(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.auth.is_rejected())
+}
+
 #[tauri::command]
 pub async fn get_credentials(
     state: tauri::State<'_, AppState>,
-) -> Result<Credentials, String> {
+) -> Result<CredentialsView, String> {
     state
         .credentials
         .lock()
         .map_err(|e| format!("lock: {e}"))?
-        .clone()
+        .as_deref()
+        .map(CredentialsView::from)
         .ok_or_else(|| "not configured".to_string())
 }
 
 #[tauri::command]
 pub async fn verify_connection(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     server_url: String,
-    api_token: String,
+    api_token: SecretString,
 ) -> Result<UserInfo, String> {
-    let client = TwoFAuthClient::new(&state.http_client, &server_url, &api_token);
-    client.verify_user().await
+    let server_url = server_url.trim_end_matches('/').to_string();
+    // Re-testing the configured server must still honour its pin.
+    let pin = state
+        .credentials
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .as_ref()
+        .filter(|c| c.server_url == server_url)
+        .and_then(|c| c.cert_pin.clone());
+
+    let observer = Arc::new(PinObserver::default());
+    let http = pinning::build_client(pin.as_deref(), observer.clone())?;
+    let client = TwoFAuthClient::new(&http, &server_url, api_token.expose())
+        .with_policy(state.request_policy()?);
+    let result = credential_service(&app)?.verify(&client).await;
+
+    if let Ok(user) = &result {
+        // A failed probe only means features stay ungated, not that the
+        // connection is bad.
+        let capabilities = client.probe_capabilities().await.ok();
+        let mut probed = state.probed_server.lock().map_err(|e| format!("lock: {e}"))?;
+        *probed = Some(ProbedServer {
+            server_url: server_url.clone(),
+            capabilities,
+            email: user.email.clone(),
+        });
+    }
+
+    // Only a certificate that passed verification can be pinned; a failure,
+    // a pin mismatch included, also drops one approved earlier.
+    let fingerprint = observer.last_seen().filter(|_| result.is_ok());
+    *state.pending_pin.lock().map_err(|e| format!("lock: {e}"))? =
+        fingerprint.map(|fingerprint| PendingPin {
+            server_url,
+            fingerprint,
+        });
+    result
+}
+
+/// Swaps the stored token for `api_token`, which must belong to the same
+/// user on the same server. Works after the server rejected the stored
+/// token, which is when it is needed most.
+#[tauri::command]
+pub async fn replace_api_token(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    api_token: SecretString,
+) -> Result<(), String> {
+    let stored = get_creds(&state)?;
+    let http = state.http_client()?;
+    let policy = state.request_policy()?;
+    // Not `state.client`, which refuses once the token was rejected. This
+    // one is only asked who the user is when the credentials don't say.
+    let current = TwoFAuthClient::new(&http, &stored.server_url, stored.api_token.expose())
+        .with_policy(policy.clone());
+    let replacement = TwoFAuthClient::new(&http, &stored.server_url, api_token.expose())
+        .with_policy(policy);
+    let creds = credential_service(&app)?
+        .replace_token(&stored, api_token, &current, &replacement)
+        .await?;
+
+    let mut lock = state.credentials.lock().map_err(|e| format!("lock: {e}"))?;
+    *lock = Some(Arc::new(creds));
+    drop(lock);
+    restore_auth(&app, &state);
+    audit::record(&app, AuditAction::ApiTokenReplaced, None, AuditSource::App, None);
+    Ok(())
+}
+
+/// Confirms the user still knows the API token before secrets leave the app.
+/// The token must match the stored one and still be accepted by the server.
+#[tauri::command]
+pub async fn reauthenticate(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    api_token: SecretString,
+) -> Result<(), String> {
+    let creds = get_creds(&state)?;
+    let http = state.http_client()?;
+    credential_service(&app)?
+        .reauthenticate(&creds, api_token.expose(), &state.client(&http)?)
+        .await?;
+
+    let mut at = state
+        .reauthenticated_at
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?;
+    *at = Some(Instant::now());
+    drop(at);
+    audit::record(&app, AuditAction::Reauthenticated, None, AuditSource::App, None);
+    Ok(())
+}
+
+/// Connects to the configured server without its pin and reports the
+/// certificate it presents, which becomes the one `approve_certificate_pin`
+/// may trust. No token is sent, and a certificate the platform doesn't trust
+/// is not offered at all.
+#[tauri::command]
+pub async fn get_certificate_pin(
+    state: tauri::State<'_, AppState>,
+) -> Result<CertificatePinStatus, String> {
+    let creds = get_creds(&state)?;
+    let observer = Arc::new(PinObserver::default());
+    let http = pinning::build_client(None, observer.clone())?;
+    // Only the handshake matters; whatever the server answers is ignored.
+    let _ = http.head(&creds.server_url).send().await;
+
+    let presented = observer.last_seen();
+    *state.pending_pin.lock().map_err(|e| format!("lock: {e}"))? =
+        presented.clone().map(|fingerprint| PendingPin {
+            server_url: creds.server_url.clone(),
+            fingerprint,
+        });
+    Ok(CertificatePinStatus {
+        presented,
+        pinned: creds.cert_pin.clone(),
+    })
+}
+
+/// Re-pins the configured server to the certificate `get_certificate_pin`
+/// last saw. The caller passes back the fingerprint the user was shown, so a
+/// certificate that changes again between prompt and approval is not trusted.
+#[tauri::command]
+pub async fn approve_certificate_pin(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    fingerprint: String,
+) -> Result<(), String> {
+    let mut creds = Credentials::clone(&get_creds(&state)?);
+    let pending = state
+        .pending_pin
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .take()
+        .filter(|p| p.server_url == creds.server_url)
+        .ok_or("check the server's certificate again before trusting it")?;
+    if pending.fingerprint != fingerprint {
+        return Err("certificate changed again, please retry".into());
+    }
+    creds.cert_pin = Some(fingerprint.clone());
+    update_credentials(&app, &state, creds)?;
+    audit::record(
+        &app,
+        AuditAction::CertificatePinChanged,
+        None,
+        AuditSource::App,
+        Some(fingerprint),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn clear_certificate_pin(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let mut creds = Credentials::clone(&get_creds(&state)?);
+    creds.cert_pin = None;
+    update_credentials(&app, &state, creds)?;
+    audit::record(
+        &app,
+        AuditAction::CertificatePinChanged,
+        None,
+        AuditSource::App,
+        Some("cleared".into()),
+    );
+    Ok(())
+}
+
+fn update_credentials(
+    app: &AppHandle,
+    state: &AppState,
+    creds: Credentials,
+) -> Result<(), String> {
+    credential_service(app)?.save(&creds)?;
+    state.apply_pin(creds.cert_pin.as_deref())?;
+    {
+        let mut settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+        if settings.claim_for(&creds.server_url) {
+            store::write_settings(&data_dir(app)?, &settings)?;
+        }
+    }
+    let mut lock = state.credentials.lock().map_err(|e| format!("lock: {e}"))?;
+    *lock = Some(Arc::new(creds));
+    drop(lock);
+    state.forget_accounts()?;
+    tray::refresh_menu(app);
+    Ok(())
 }
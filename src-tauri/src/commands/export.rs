@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+use tokio::sync::oneshot;
+
+use super::{account_service, audit};
+use crate::export::{self, migration, otpauth, qr, ExportFormat};
+use crate::models::types::*;
+use crate::state::AppState;
+use crate::store;
+
+/// The files hold secrets, so they are written like vault files: readable
+/// by the owner only and never left half written.
+fn write(dir: &Path, name: &str, bytes: &[u8]) -> Result<String, String> {
+    let path = dir.join(name);
+    store::write_atomic(&path, bytes)?;
+    Ok(path.display().to_string())
+}
+
+fn label(account: &ExportedAccount) -> String {
+    match (account.service.as_deref(), account.account.as_deref()) {
+        (Some(s), Some(a)) => format!("{s} ({a})"),
+        (Some(s), None) => s.to_string(),
+        (None, Some(a)) => a.to_string(),
+        (None, None) => "unnamed account".to_string(),
+    }
+}
+
+/// Writes the selected accounts, secrets included, to a directory the user
+/// picks, for enrolling another device or printing a paper backup. Needs a
+/// recent `reauthenticate`. Returns `None` if the folder picker was cancelled.
+#[tauri::command]
+pub async fn export_accounts(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_ids: Vec<u32>,
+    format: ExportFormat,
+) -> Result<Option<ExportSummary>, String> {
+    state.require_reauth()?;
+    if account_ids.is_empty() {
+        return Err("no accounts selected".into());
+    }
+
+    let http = state.http_client()?;
+    let accounts = account_service(&app, &state, &http)?
+        .export(&account_ids)
+        .await?;
+
+    let (tx, rx) = oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Choose Export Folder")
+        .pick_folder(move |dir| {
+            let _ = tx.send(dir);
+        });
+    let Some(dir) = rx.await.map_err(|_| "export folder: the dialog went away".to_string())? else {
+        return Ok(None);
+    };
+    let dir = dir.into_path().map_err(|e| format!("export folder: {e}"))?;
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    match format {
+        ExportFormat::UriList => {
+            let mut lines = String::new();
+            for account in &accounts {
+                match otpauth::to_uri(account) {
+                    Ok(uri) => {
+                        lines.push_str(&uri);
+                        lines.push('\n');
+                    }
+                    Err(reason) => skipped.push(ExportSkip {
+                        account: label(account),
+                        reason,
+                    }),
+                }
+            }
+            files.push(write(&dir, "otpauth-uris.txt", lines.as_bytes())?);
+        }
+        ExportFormat::QrCodes => {
+            for (index, account) in accounts.iter().enumerate() {
+                match otpauth::to_uri(account).and_then(|uri| qr::render_png(&uri)) {
+                    Ok(png) => {
+                        let stem = export::file_stem(
+                            index,
+                            account.service.as_deref(),
+                            account.account.as_deref(),
+                        );
+                        files.push(write(&dir, &format!("{stem}.png"), &png)?);
+                    }
+                    Err(reason) => skipped.push(ExportSkip {
+                        account: label(account),
+                        reason,
+                    }),
+                }
+            }
+        }
+        ExportFormat::Migration => {
+            let mut parameters = Vec::new();
+            for account in &accounts {
+                match migration::otp_parameters(account) {
+                    Ok(params) => parameters.push(params),
+                    Err(reason) => skipped.push(ExportSkip {
+                        account: label(account),
+                        reason,
+                    }),
+                }
+            }
+            let uris = migration::to_uris(&parameters)?;
+            let total = uris.len();
+            for (index, uri) in uris.iter().enumerate() {
+                let png = qr::render_png(uri)?;
+                let name = format!("google-authenticator-{}-of-{}.png", index + 1, total);
+                files.push(write(&dir, &name, &png)?);
+            }
+        }
+    }
+
+    audit::record(
+        &app,
+        AuditAction::AccountsExported,
+        None,
+        AuditSource::App,
+        Some(format!(
+            "{} accounts as {format:?} to {}",
+            accounts.len() - skipped.len(),
+            dir.display()
+        )),
+    );
+    // The server leaves out ids it doesn't know without saying which.
+    let requested: HashSet<_> = account_ids.iter().collect();
+    let missing = requested.len().saturating_sub(accounts.len());
+    if missing > 0 {
+        skipped.push(ExportSkip {
+            account: format!("{missing} selected accounts"),
+            reason: "not found on the server".into(),
+        });
+    }
+    Ok(Some(ExportSummary {
+        directory: dir.display().to_string(),
+        files,
+        skipped,
+    }))
+}
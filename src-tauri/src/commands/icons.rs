@@ -0,0 +1,63 @@
+use tauri::AppHandle;
+
+use super::{account_service, audit};
+use crate::icons;
+use crate::models::types::*;
+use crate::state::AppState;
+
+/// Uploads an image as an icon and returns its server filename, ready for
+/// `set_account_icon`. The icon cache picks it up from the server when it is
+/// first shown.
+#[tauri::command]
+pub async fn upload_icon(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    data: Vec<u8>,
+    file_name: String,
+) -> Result<String, String> {
+    let http = state.http_client()?;
+    let mime = icons::content_type(&file_name);
+    account_service(&app, &state, &http)?
+        .upload_icon(data, &file_name, mime)
+        .await
+}
+
+/// Has the server fetch the official icon for `service`; `None` if its icon
+/// collection has none.
+#[tauri::command]
+pub async fn fetch_official_icon(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    service: String,
+) -> Result<Option<String>, String> {
+    let http = state.http_client()?;
+    account_service(&app, &state, &http)?
+        .fetch_official_icon(&service)
+        .await
+}
+
+/// Sets or clears an account's icon. The secret the update needs is read
+/// from the server and sent straight back; it never reaches the webview.
+#[tauri::command]
+pub async fn set_account_icon(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_id: u32,
+    icon: Option<String>,
+) -> Result<Account, String> {
+    if icon.as_deref().is_some_and(|i| !icons::is_valid_filename(i)) {
+        return Err("invalid icon name".into());
+    }
+    let http = state.http_client()?;
+    let updated = account_service(&app, &state, &http)?
+        .set_icon(account_id, icon.clone())
+        .await?;
+    audit::record(
+        &app,
+        AuditAction::AccountUpdated,
+        Some(account_id),
+        AuditSource::App,
+        Some(format!("icon: {}", icon.as_deref().unwrap_or("none"))),
+    );
+    Ok(updated)
+}
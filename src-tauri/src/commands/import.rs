@@ -0,0 +1,72 @@
+use tauri::AppHandle;
+
+use super::{account_service, audit};
+use crate::import;
+use crate::models::types::*;
+use crate::state::AppState;
+
+/// Dry run: detects the export format (Aegis, 2FAS, andOTP, Bitwarden),
+/// parses it and reports what would be created. The parsed entries, secrets
+/// included, stay in the backend until `import_accounts` is called with the
+/// indices the user kept.
+#[tauri::command]
+pub async fn preview_import(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    data: Vec<u8>,
+    password: Option<String>,
+) -> Result<ImportPreview, String> {
+    let importer = import::detect(&data)?;
+    let entries = importer.parse(&data, password.as_deref())?;
+
+    let http = state.http_client()?;
+    let preview = account_service(&app, &state, &http)?
+        .preview_import(importer.name(), &entries)
+        .await?;
+    let mut pending = state.pending_import.lock().map_err(|e| format!("lock: {e}"))?;
+    *pending = Some(entries);
+    Ok(preview)
+}
+
+/// Creates the selected entries from the last preview.
+#[tauri::command]
+pub async fn import_accounts(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    indices: Vec<usize>,
+) -> Result<Vec<ImportResult>, String> {
+    let entries = state
+        .pending_import
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .take()
+        .ok_or_else(|| "nothing to import, preview a file first".to_string())?;
+
+    let http = state.http_client()?;
+    let results = account_service(&app, &state, &http)?
+        .create_entries(&entries, indices)
+        .await;
+    audit_created(&app, AuditAction::AccountsImported, &results);
+    Ok(results)
+}
+
+/// One entry for the batch, one per account created.
+pub(crate) fn audit_created(app: &AppHandle, action: AuditAction, results: &[ImportResult]) {
+    let created: Vec<_> = results.iter().filter_map(|r| r.account.as_ref()).collect();
+    audit::record(
+        app,
+        action,
+        None,
+        AuditSource::App,
+        Some(format!("{} of {} accounts", created.len(), results.len())),
+    );
+    for account in created {
+        audit::record(
+            app,
+            AuditAction::AccountCreated,
+            Some(account.id),
+            AuditSource::App,
+            account.service.clone(),
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use tauri::AppHandle;
+
+use super::{audit, credentials, data_dir};
+use crate::crypto::secret::SecretString;
+use crate::keystore::{self, password};
+use crate::models::types::*;
+use crate::state::AppState;
+
+/// Whether the master password has to be entered before the credentials
+/// can be loaded.
+#[tauri::command]
+pub async fn is_vault_locked(app: AppHandle) -> Result<bool, String> {
+    password::is_locked(&data_dir(&app)?)
+}
+
+/// Unlocks the vault with the master password, then loads the credentials
+/// like `load_credentials`.
+#[tauri::command]
+pub async fn unlock_vault(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    password: SecretString,
+) -> Result<bool, String> {
+    password::unlock(&data_dir(&app)?, password.expose())?;
+    credentials::load_credentials(app, state).await
+}
+
+/// Moves the vault key to `source`, re-encrypting the vault under a new key.
+/// `password` is the master password to set when `source` is `password`;
+/// migrating to `password` again changes it. Needs a recent
+/// `reauthenticate`.
+#[tauri::command]
+pub async fn migrate_key_store(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    source: KeySource,
+    password: Option<SecretString>,
+) -> Result<(), String> {
+    state.require_reauth()?;
+    let dir = data_dir(&app)?;
+    let password = password.as_ref().map(|p| p.expose().as_str());
+    let target = keystore::open_with_password(&dir, source, password)?;
+    keystore::migrate(&dir, target.as_ref())?;
+
+    state
+        .settings
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .key_source = source;
+    audit::record(
+        &app,
+        AuditAction::KeyStoreChanged,
+        None,
+        AuditSource::App,
+        Some(format!("{source:?}")),
+    );
+    Ok(())
+}
+
+/// Re-encrypts the vault under a fresh key, kept where the current one is.
+/// `password` is the current master password when the vault uses one.
+/// Needs a recent `reauthenticate`.
+#[tauri::command]
+pub async fn rotate_vault_key(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    password: Option<SecretString>,
+) -> Result<(), String> {
+    state.require_reauth()?;
+    keystore::rotate(
+        &data_dir(&app)?,
+        password.as_ref().map(|p| p.expose().as_str()),
+    )?;
+    audit::record(&app, AuditAction::VaultKeyRotated, None, AuditSource::App, None);
+    Ok(())
+}
@@ -1,5 +1,52 @@
 pub mod accounts;
+pub mod audit;
+pub mod autofill;
+pub mod backup;
 pub mod credentials;
+pub mod export;
+pub mod icons;
+pub mod import;
+pub mod keystore;
 pub mod otp;
 pub mod qrcode;
 pub mod screenshot;
+pub mod search;
+pub mod settings;
+pub mod trash;
+
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::api::client::TwoFAuthClient;
+use crate::service::{AccountService, CredentialService, FileStorage};
+use crate::state::AppState;
+use crate::store;
+
+pub(crate) fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("app data dir: {e}"))?;
+    store::create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// The account service for the configured server, talking to it over `http`
+/// (from `AppState::http_client`).
+pub(crate) fn account_service<'a>(
+    app: &AppHandle,
+    state: &AppState,
+    http: &'a reqwest::Client,
+) -> Result<AccountService<TwoFAuthClient<'a>, FileStorage>, String> {
+    let creds = accounts::get_creds(state)?;
+    Ok(AccountService::new(
+        state.client(http)?,
+        FileStorage::new(data_dir(app)?),
+        &creds.server_url,
+    )
+    .with_capabilities(creds.capabilities))
+}
+
+pub(crate) fn credential_service(app: &AppHandle) -> Result<CredentialService<FileStorage>, String> {
+    Ok(CredentialService::new(FileStorage::new(data_dir(app)?)))
+}
@@ -1,18 +1,34 @@
-use crate::api::client::TwoFAuthClient;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use super::account_service;
 use crate::models::types::*;
 use crate::state::AppState;
 
 #[tauri::command]
 pub async fn fetch_otp(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     account_id: u32,
 ) -> Result<OtpValue, String> {
-    let creds = state
-        .credentials
-        .lock()
-        .map_err(|e| format!("lock: {e}"))?
-        .clone()
-        .ok_or_else(|| "not configured".to_string())?;
-    let client = TwoFAuthClient::new(&state.http_client, &creds.server_url, &creds.api_token);
-    client.get_otp(account_id).await
+    let http = state.http_client()?;
+    account_service(&app, &state, &http)?.otp(account_id).await
+}
+
+/// Copies an account's current code, for the tray menu's pinned accounts.
+pub(crate) async fn copy_code(app: &AppHandle, account_id: u32) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let http = state.http_client()?;
+    let otp = account_service(app, &state, &http)?.otp(account_id).await?;
+    app.clipboard()
+        .write_text(otp.password)
+        .map_err(|e| format!("clipboard: {e}"))?;
+    super::audit::record(
+        app,
+        AuditAction::CodeCopied,
+        Some(account_id),
+        AuditSource::Tray,
+        None,
+    );
+    super::search::note_use(app, account_id)
 }
@@ -62,7 +62,7 @@ pub async fn scan_screen_for_qr(
     #[cfg(target_os = "macos")]
     {
         let png_bytes = capture_macos(x, y, width, height)?;
-        decode_qr_from_png(&png_bytes)
+        crate::qr::decode(&png_bytes)
     }
 
     #[cfg(not(target_os = "macos"))]
@@ -72,25 +72,6 @@ pub async fn scan_screen_for_qr(
     }
 }
 
-fn decode_qr_from_png(png_bytes: &[u8]) -> Result<String, String> {
-    let img = image::load_from_memory(png_bytes)
-        .map_err(|e| format!("load image: {e}"))?
-        .to_luma8();
-
-    let mut prepared = rqrr::PreparedImage::prepare(img);
-    let grids = prepared.detect_grids();
-
-    if grids.is_empty() {
-        return Err("no QR code found".into());
-    }
-
-    let (_meta, content) = grids[0]
-        .decode()
-        .map_err(|e| format!("decode QR: {e}"))?;
-
-    Ok(content)
-}
-
 #[cfg(target_os = "macos")]
 fn capture_macos(x: f64, y: f64, width: f64, height: f64) -> Result<Vec<u8>, String> {
     let rect = CGRect::new(&CGPoint::new(x, y), &CGSize::new(width, height));
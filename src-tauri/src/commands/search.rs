@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Manager};
+
+use super::accounts::{get_creds, without_otp};
+use super::{account_service, audit, data_dir};
+use crate::models::types::*;
+use crate::search;
+use crate::service::now;
+use crate::state::AppState;
+use crate::store;
+
+const DEFAULT_LIMIT: usize = 50;
+
+/// Loads `usage.json` into the app state at startup.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let usage = store::read_usage(&data_dir(app)?);
+    let state = app.state::<AppState>();
+    *state.usage.lock().map_err(|e| format!("lock: {e}"))? = usage;
+    Ok(())
+}
+
+/// Counts a use of `account_id`'s code towards its search ranking.
+pub(crate) fn note_use(app: &AppHandle, account_id: u32) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let server_url = get_creds(&state)?.server_url.clone();
+    let usage = {
+        let mut usage = state.usage.lock().map_err(|e| format!("lock: {e}"))?;
+        let entry = usage.entry(server_url).or_default().entry(account_id).or_default();
+        entry.count = entry.count.saturating_add(1);
+        entry.last_used = now();
+        usage.clone()
+    };
+    store::write_usage(&data_dir(app)?, &usage)
+}
+
+/// Called by the webview after it copied a code. The source comes from the
+/// window that called, not from the webview's word.
+#[tauri::command]
+pub async fn record_usage(
+    app: AppHandle,
+    window: tauri::Window,
+    account_id: u32,
+) -> Result<(), String> {
+    let source = match window.label() {
+        "tray-popup" => AuditSource::Tray,
+        _ => AuditSource::App,
+    };
+    audit::record(&app, AuditAction::CodeCopied, Some(account_id), source, None);
+    note_use(&app, account_id)
+}
+
+#[tauri::command]
+pub async fn search_accounts(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<Account>, String> {
+    // Once the token is rejected nothing is fetched; the last list is
+    // searched as it is, like `fetch_accounts` shows it.
+    let rejected = state.auth.is_rejected();
+    let need_accounts = !rejected
+        && state
+            .accounts
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?
+            .is_none();
+    let need_groups = !rejected
+        && state
+            .group_names
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?
+            .is_none();
+
+    if need_accounts || need_groups {
+        let http = state.http_client()?;
+        let service = account_service(&app, &state, &http)?;
+        if need_accounts {
+            match service.list().await {
+                Ok(accounts) => {
+                    *state.accounts.lock().map_err(|e| format!("lock: {e}"))? = Some(accounts);
+                }
+                Err(_) if state.auth.is_rejected() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if need_groups && !state.auth.is_rejected() {
+            // Groups only refine the ranking; search without them if the
+            // server won't list them.
+            let groups = service.groups().await.unwrap_or_else(|e| {
+                log::warn!("search: groups unavailable: {e}");
+                Vec::new()
+            });
+            *state.group_names.lock().map_err(|e| format!("lock: {e}"))? =
+                Some(groups.into_iter().map(|g| (g.id, g.name)).collect());
+        }
+    }
+
+    // The list fetch may just have been the one rejected.
+    let rejected = state.auth.is_rejected();
+    let server_url = get_creds(&state)?.server_url.clone();
+    let pinned = state.settings.lock().map_err(|e| format!("lock: {e}"))?.pinned.clone();
+    let accounts = state.accounts.lock().map_err(|e| format!("lock: {e}"))?;
+    let group_names = state.group_names.lock().map_err(|e| format!("lock: {e}"))?;
+    let usage = state.usage.lock().map_err(|e| format!("lock: {e}"))?;
+    let ranked = search::rank(
+        accounts.as_deref().unwrap_or_default(),
+        group_names.as_ref().unwrap_or(&HashMap::new()),
+        usage.get(&server_url).unwrap_or(&HashMap::new()),
+        &pinned,
+        &query,
+        now(),
+        limit.unwrap_or(DEFAULT_LIMIT),
+    );
+    Ok(if rejected {
+        ranked.into_iter().map(without_otp).collect()
+    } else {
+        ranked.into_iter().cloned().collect()
+    })
+}
@@ -0,0 +1,141 @@
+use tauri::{AppHandle, Manager};
+
+use super::{account_service, data_dir};
+use crate::api::retry::{MAX_RETRIES, MAX_TIMEOUT_SECS};
+use crate::models::types::Settings;
+use crate::state::AppState;
+use crate::store;
+
+/// Loads `settings.json` into the app state at startup. A file that can't
+/// be read leaves the defaults in place rather than stopping the app.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let dir = data_dir(app)?;
+    let settings = store::read_settings(&dir).unwrap_or_else(|e| {
+        log::warn!("{e}; using the default settings");
+        Settings::default()
+    });
+    let state = app.state::<AppState>();
+    let mut lock = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+    *lock = settings;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_settings(state: tauri::State<'_, AppState>) -> Result<Settings, String> {
+    Ok(state
+        .settings
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .clone())
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    mut settings: Settings,
+) -> Result<(), String> {
+    validate(&settings)?;
+    let preferences = state
+        .settings
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .preferences;
+    // The server has the last word on preferences, so they only change
+    // locally once it took them. The other settings are saved regardless.
+    let pushed = if settings.preferences != preferences && is_configured(&state)? {
+        let push = async {
+            let http = state.http_client()?;
+            account_service(&app, &state, &http)?
+                .push_preferences(&preferences, &settings.preferences)
+                .await
+        };
+        push.await
+    } else {
+        Ok(())
+    };
+    if pushed.is_err() {
+        settings.preferences = preferences;
+    }
+    {
+        let mut current = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+        // Only `migrate_key_store` can change it, since the vault has to follow.
+        settings.key_source = current.key_source;
+        // These change behind the webview's back, through the socket's
+        // approval prompt and the tray, so its copy may be out of date.
+        settings.ipc_allowlist = current.ipc_allowlist.clone();
+        settings.pinned = current.pinned.clone();
+        settings.server_url = current.server_url.clone();
+        store::write_settings(&data_dir(&app)?, &settings)?;
+        *current = settings;
+    }
+    #[cfg(unix)]
+    crate::ipc::sync(&app)?;
+    pushed.map_err(|e| format!("settings saved, but not your 2FAuth preferences: {e}"))
+}
+
+/// Takes back a program's access to the IPC socket.
+#[tauri::command]
+pub async fn revoke_ipc_client(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    client: String,
+) -> Result<Vec<String>, String> {
+    let mut settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+    settings.ipc_allowlist.retain(|allowed| *allowed != client);
+    store::write_settings(&data_dir(&app)?, &settings)?;
+    Ok(settings.ipc_allowlist.clone())
+}
+
+/// Brings the user's 2FAuth preferences up to date from the server and
+/// returns the settings. If the server can't be reached the local copy
+/// stays as it is.
+#[tauri::command]
+pub async fn sync_preferences(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Settings, String> {
+    let current = state
+        .settings
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .preferences;
+    if !is_configured(&state)? || state.auth.is_rejected() {
+        return get_settings(state).await;
+    }
+
+    let http = state.http_client()?;
+    match account_service(&app, &state, &http)?
+        .pull_preferences(current)
+        .await
+    {
+        Ok(Some(preferences)) if preferences != current => {
+            let mut settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+            settings.preferences = preferences;
+            store::write_settings(&data_dir(&app)?, &settings)?;
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("preferences: {e}"),
+    }
+    get_settings(state).await
+}
+
+fn validate(settings: &Settings) -> Result<(), String> {
+    if !(1..=MAX_TIMEOUT_SECS).contains(&settings.request_timeout_secs) {
+        return Err(format!(
+            "request timeout must be between 1 and {MAX_TIMEOUT_SECS} seconds"
+        ));
+    }
+    if settings.max_retries > MAX_RETRIES {
+        return Err(format!("at most {MAX_RETRIES} retries"));
+    }
+    Ok(())
+}
+
+fn is_configured(state: &AppState) -> Result<bool, String> {
+    Ok(state
+        .credentials
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .is_some())
+}
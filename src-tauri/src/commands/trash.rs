@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use tauri::AppHandle;
+
+use super::{account_service, audit, data_dir};
+use crate::models::types::*;
+use crate::service;
+use crate::state::AppState;
+use crate::trash;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Drops expired trash now and every few minutes while the app runs.
+pub fn start_purge(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = data_dir(&app).and_then(|dir| trash::purge_expired(&dir, service::now())) {
+                log::warn!("trash: {e}");
+            }
+            tokio::time::sleep(PURGE_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_trash(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<TrashEntry>, String> {
+    let http = state.http_client()?;
+    account_service(&app, &state, &http)?.trash()
+}
+
+/// Re-creates a deleted account from the trash. The server gives it a new
+/// id, which the returned account carries.
+#[tauri::command]
+pub async fn undo_delete(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    account_id: u32,
+) -> Result<Account, String> {
+    let http = state.http_client()?;
+    let account = account_service(&app, &state, &http)?
+        .undo_delete(account_id)
+        .await?;
+    audit::record(
+        &app,
+        AuditAction::AccountCreated,
+        Some(account.id),
+        AuditSource::App,
+        Some(format!("restored from trash (was {account_id})")),
+    );
+    Ok(account)
+}
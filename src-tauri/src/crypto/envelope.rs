@@ -0,0 +1,181 @@
+//! The on-disk format of the vault files, `credentials.enc` and `trash.enc`.
+//!
+//! | offset | size | field                                        |
+//! |--------|------|----------------------------------------------|
+//! | 0      | 4    | magic `2FAV`                                 |
+//! | 4      | 1    | format version, currently `1`                |
+//! | 5      | 1    | algorithm, `1` = AES-256-GCM                 |
+//! | 6      | 1    | key source, `1` keyfile, `2` keyring, `3` password |
+//! | 7      | 12   | nonce                                        |
+//! | 19     | ..   | ciphertext followed by the 16-byte tag       |
+//!
+//! The first 19 bytes are the associated data, so a changed version,
+//! algorithm or key source fails decryption like a changed ciphertext.
+//!
+//! Files written before the envelope existed (version 0) are a bare
+//! `nonce || ciphertext || tag` with no associated data. They are still read;
+//! the next write upgrades them.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use rand::RngCore;
+
+use crate::models::types::KeySource;
+
+pub const MAGIC: &[u8; 4] = b"2FAV";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 19;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Aes256Gcm,
+}
+
+impl Algorithm {
+    fn id(self) -> u8 {
+        match self {
+            Algorithm::Aes256Gcm => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Algorithm::Aes256Gcm),
+            _ => None,
+        }
+    }
+}
+
+fn key_source_id(source: KeySource) -> u8 {
+    match source {
+        KeySource::Keyfile => 1,
+        KeySource::Keyring => 2,
+        KeySource::Password => 3,
+    }
+}
+
+fn key_source_from_id(id: u8) -> Option<KeySource> {
+    match id {
+        1 => Some(KeySource::Keyfile),
+        2 => Some(KeySource::Keyring),
+        3 => Some(KeySource::Password),
+        _ => None,
+    }
+}
+
+/// A vault file split into its parts, before any decryption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Envelope<'a> {
+    /// Version 0: `nonce || ciphertext`, no associated data.
+    Legacy {
+        nonce: &'a [u8],
+        ciphertext: &'a [u8],
+    },
+    V1 {
+        algorithm: Algorithm,
+        key_source: KeySource,
+        /// The header, authenticated with the ciphertext.
+        aad: &'a [u8],
+        nonce: &'a [u8],
+        ciphertext: &'a [u8],
+    },
+}
+
+impl Envelope<'_> {
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, Envelope::Legacy { .. })
+    }
+}
+
+/// Splits `data` without decrypting it. Anything without the magic is taken
+/// for a legacy file.
+pub fn parse(data: &[u8]) -> Result<Envelope<'_>, String> {
+    if !data.starts_with(MAGIC) {
+        if data.len() < NONCE_LEN + TAG_LEN {
+            return Err("vault file is truncated".into());
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        return Ok(Envelope::Legacy { nonce, ciphertext });
+    }
+    if data.len() < MAGIC.len() + 1 {
+        return Err("vault file is truncated".into());
+    }
+    let version = data[4];
+    if version != VERSION {
+        return Err(format!("unsupported vault version {version}"));
+    }
+    if data.len() < HEADER_LEN + TAG_LEN {
+        return Err("vault file is truncated".into());
+    }
+    let algorithm = Algorithm::from_id(data[5])
+        .ok_or_else(|| format!("unsupported vault algorithm {}", data[5]))?;
+    let key_source = key_source_from_id(data[6])
+        .ok_or_else(|| format!("unknown vault key source {}", data[6]))?;
+    let (aad, ciphertext) = data.split_at(HEADER_LEN);
+    Ok(Envelope::V1 {
+        algorithm,
+        key_source,
+        aad,
+        nonce: &aad[7..HEADER_LEN],
+        ciphertext,
+    })
+}
+
+pub fn seal(plaintext: &[u8], key: &[u8; 32], source: KeySource) -> Result<Vec<u8>, String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.push(Algorithm::Aes256Gcm.id());
+    out.push(key_source_id(source));
+    out.extend_from_slice(&nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| format!("cipher init: {e}"))?;
+    let ciphertext = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &out,
+            },
+        )
+        .map_err(|e| format!("encrypt: {e}"))?;
+    out.extend(ciphertext);
+    Ok(out)
+}
+
+/// Decrypts a vault file written by `seal` with a key from `source`, or a
+/// legacy one.
+pub fn open(data: &[u8], key: &[u8; 32], source: KeySource) -> Result<Vec<u8>, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| format!("cipher init: {e}"))?;
+    let decrypt = |nonce: &[u8], msg: &[u8], aad: &[u8]| {
+        cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg, aad })
+            .map_err(|_| "decrypt: wrong key or corrupt vault file".to_string())
+    };
+    let result = match parse(data) {
+        Ok(Envelope::Legacy { nonce, ciphertext }) => return decrypt(nonce, ciphertext, &[]),
+        Ok(Envelope::V1 { key_source, .. }) if key_source != source => Err(format!(
+            "vault was encrypted with a {key_source:?} key, not {source:?}"
+        )),
+        Ok(Envelope::V1 {
+            aad,
+            nonce,
+            ciphertext,
+            ..
+        }) => decrypt(nonce, ciphertext, aad),
+        Err(e) => Err(e),
+    };
+    // A legacy file's random nonce starts with the magic once in 2^32.
+    result.or_else(|e| {
+        if data.len() < NONCE_LEN + TAG_LEN {
+            return Err(e);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        decrypt(nonce, ciphertext, &[]).map_err(|_| e)
+    })
+}
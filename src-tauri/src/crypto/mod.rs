@@ -1 +1,3 @@
 pub mod encryption;
+pub mod envelope;
+pub mod secret;
@@ -0,0 +1,125 @@
+//! Google Authenticator "Transfer accounts" payloads.
+//!
+//! `otpauth-migration://offline?data=<base64>` carries a protobuf
+//! `MigrationPayload`:
+//!
+//! ```text
+//! message MigrationPayload {
+//!   repeated OtpParameters otp_parameters = 1;
+//!   int32 version = 2;
+//!   int32 batch_size = 3;
+//!   int32 batch_index = 4;
+//!   int32 batch_id = 5;
+//! }
+//! message OtpParameters {
+//!   bytes secret = 1;
+//!   string name = 2;
+//!   string issuer = 3;
+//!   Algorithm algorithm = 4;  // 1 SHA1, 2 SHA256, 3 SHA512, 4 MD5
+//!   DigitCount digits = 5;    // 1 six, 2 eight
+//!   OtpType type = 6;         // 1 HOTP, 2 TOTP
+//!   int64 counter = 7;
+//! }
+//! ```
+//!
+//! The message is small enough that it is encoded by hand here.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use data_encoding::BASE32_NOPAD;
+use rand::Rng;
+use reqwest::Url;
+
+use crate::models::types::ExportedAccount;
+
+/// Accounts per QR code; Google Authenticator itself uses batches of ten.
+pub const BATCH_SIZE: usize = 10;
+const PAYLOAD_VERSION: u64 = 1;
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_varint_field(out: &mut Vec<u8>, field: u32, value: u64) {
+    put_varint(out, u64::from(field << 3));
+    put_varint(out, value);
+}
+
+fn put_bytes_field(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_varint(out, u64::from(field << 3 | 2));
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Encodes one account as `OtpParameters`, or explains why Google
+/// Authenticator can't hold it.
+pub fn otp_parameters(account: &ExportedAccount) -> Result<Vec<u8>, String> {
+    let secret = account.secret.as_deref().ok_or("account has no secret")?;
+    let secret = BASE32_NOPAD
+        .decode(secret.trim_end_matches('=').to_uppercase().as_bytes())
+        .map_err(|e| format!("secret is not base32: {e}"))?;
+
+    let otp_type = match account.otp_type.as_str() {
+        "hotp" => 1,
+        "totp" => 2,
+        other => return Err(format!("{other} is not supported by Google Authenticator")),
+    };
+    if otp_type == 2 && account.period.is_some_and(|p| p != 30) {
+        return Err("Google Authenticator only supports a 30 second period".into());
+    }
+    let digits = match account.digits.unwrap_or(6) {
+        6 => 1,
+        8 => 2,
+        d => return Err(format!("Google Authenticator doesn't support {d} digits")),
+    };
+    let algorithm = match account.algorithm.as_deref().unwrap_or("sha1") {
+        "sha1" => 1,
+        "sha256" => 2,
+        "sha512" => 3,
+        "md5" => 4,
+        other => return Err(format!("unknown algorithm {other}")),
+    };
+
+    let mut out = Vec::new();
+    put_bytes_field(&mut out, 1, &secret);
+    put_bytes_field(&mut out, 2, account.account.as_deref().unwrap_or_default().as_bytes());
+    put_bytes_field(&mut out, 3, account.service.as_deref().unwrap_or_default().as_bytes());
+    put_varint_field(&mut out, 4, algorithm);
+    put_varint_field(&mut out, 5, digits);
+    put_varint_field(&mut out, 6, otp_type);
+    if otp_type == 1 {
+        put_varint_field(&mut out, 7, u64::from(account.counter.unwrap_or(0)));
+    }
+    Ok(out)
+}
+
+/// Packs pre-encoded `OtpParameters` into `otpauth-migration://` URIs of at
+/// most `BATCH_SIZE` accounts each, sharing one batch id.
+pub fn to_uris(parameters: &[Vec<u8>]) -> Result<Vec<String>, String> {
+    let batches: Vec<_> = parameters.chunks(BATCH_SIZE).collect();
+    let batch_id = u64::from(rand::thread_rng().gen::<u32>() >> 1);
+
+    batches
+        .iter()
+        .enumerate()
+        .map(|(index, batch)| {
+            let mut payload = Vec::new();
+            for params in batch.iter() {
+                put_bytes_field(&mut payload, 1, params);
+            }
+            put_varint_field(&mut payload, 2, PAYLOAD_VERSION);
+            put_varint_field(&mut payload, 3, batches.len() as u64);
+            put_varint_field(&mut payload, 4, index as u64);
+            put_varint_field(&mut payload, 5, batch_id);
+
+            let mut uri = Url::parse("otpauth-migration://offline")
+                .map_err(|e| format!("uri: {e}"))?;
+            uri.query_pairs_mut()
+                .append_pair("data", &B64.encode(&payload));
+            Ok(uri.to_string())
+        })
+        .collect()
+}
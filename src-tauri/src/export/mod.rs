@@ -0,0 +1,32 @@
+pub mod migration;
+pub mod otpauth;
+pub mod qr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One `otpauth://` URI per line in a single text file.
+    UriList,
+    /// One QR code PNG per account.
+    QrCodes,
+    /// Google Authenticator `otpauth-migration://` QR codes, several accounts each.
+    Migration,
+}
+
+/// Short, filesystem-safe name for an account's output file.
+pub fn file_stem(index: usize, service: Option<&str>, account: Option<&str>) -> String {
+    let label = [service, account]
+        .into_iter()
+        .flatten()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let safe: String = label
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .take(60)
+        .collect();
+    format!("{:03}-{}", index + 1, safe)
+}
@@ -0,0 +1,19 @@
+use image::Luma;
+use qrcode::{EcLevel, QrCode};
+
+/// Smallest edge of a rendered code, big enough to print and scan reliably.
+const MIN_SIZE: u32 = 400;
+
+pub fn render_png(data: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+        .map_err(|e| format!("qr encode: {e}"))?;
+    let img = code
+        .render::<Luma<u8>>()
+        .min_dimensions(MIN_SIZE, MIN_SIZE)
+        .build();
+
+    let mut png_bytes: Vec<u8> = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|e| format!("png encode: {e}"))?;
+    Ok(png_bytes)
+}
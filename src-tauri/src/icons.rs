@@ -0,0 +1,97 @@
+//! Account icons, cached on disk and served to the webview through the
+//! `icon://` scheme so they load offline and without the webview talking to
+//! the server. The frontend builds URLs with `convertFileSrc(filename, "icon")`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use tauri::http::{header, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::commands::accounts::get_creds;
+use crate::commands::data_dir;
+use crate::state::AppState;
+use crate::store;
+
+/// The cache for one server's icons. Servers name icons independently, so
+/// the same filename on two of them can be two different images.
+pub fn cache_dir(dir: &Path, server_url: &str) -> Result<PathBuf, String> {
+    let server = hex::encode(&Sha256::digest(server_url.as_bytes())[..8]);
+    let icons = dir.join("icons").join(server);
+    store::create_private_dir(&icons)?;
+    Ok(icons)
+}
+
+/// Where `filename` is cached for the configured server.
+fn cached_path(app: &AppHandle, filename: &str) -> Result<PathBuf, String> {
+    if !is_valid_filename(filename) {
+        return Err(format!("invalid icon name: {filename}"));
+    }
+    let server_url = get_creds(&app.state::<AppState>())?.server_url.clone();
+    Ok(cache_dir(&data_dir(app)?, &server_url)?.join(filename))
+}
+
+/// 2FAuth names icons `<random>.<ext>`; anything else could escape the cache
+/// directory.
+pub fn is_valid_filename(filename: &str) -> bool {
+    !filename.is_empty()
+        && !filename.starts_with('.')
+        && filename
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+}
+
+pub fn content_type(filename: &str) -> &'static str {
+    match filename.rsplit('.').next().map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The icon from the cache, downloading it first if needed.
+pub async fn load(app: &AppHandle, filename: &str) -> Result<Vec<u8>, String> {
+    let path = cached_path(app, filename)?;
+    if let Ok(bytes) = fs::read(&path) {
+        return Ok(bytes);
+    }
+
+    let state = app.state::<AppState>();
+    let http = state.http_client()?;
+    let client = state.client(&http)?;
+    let bytes = client.download_icon(filename).await?;
+    store::write_atomic(&path, &bytes)?;
+    Ok(bytes)
+}
+
+/// Handles `icon://localhost/<filename>` (`http://icon.localhost/<filename>`
+/// on Windows). Icons are only ever shown through `<img>`, where an SVG's
+/// scripts don't run; the sandbox keeps them from running if one is opened
+/// some other way.
+pub async fn serve(app: &AppHandle, path: &str) -> Response<Vec<u8>> {
+    let filename = path.trim_start_matches('/');
+    let response = match load(app, filename).await {
+        Ok(bytes) => Response::builder()
+            .header(header::CONTENT_TYPE, content_type(filename))
+            .header(header::CACHE_CONTROL, "max-age=86400")
+            .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(
+                header::CONTENT_SECURITY_POLICY,
+                "default-src 'none'; style-src 'unsafe-inline'; sandbox",
+            )
+            .body(bytes),
+        Err(e) => {
+            log::debug!("icon {filename}: {e}");
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Vec::new())
+        }
+    };
+    response.unwrap_or_default()
+}
@@ -0,0 +1,179 @@
+//! Aegis Authenticator vault exports, plain or password-encrypted.
+//!
+//! An encrypted vault keeps the database as base64 AES-256-GCM ciphertext.
+//! The master key sits in one or more header "slots", each wrapped with
+//! AES-256-GCM under a key derived from the password with scrypt.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportEntry, Importer, OtpFields};
+use crate::crypto::encryption;
+
+/// Slot type for password-derived keys; other types (biometric) can't be
+/// opened off the phone.
+const PASSWORD_SLOT: u8 = 1;
+/// Refuse to spend more than 1 GiB, or more than 16 passes, deriving a key
+/// for a vault we didn't write. Aegis itself uses n = 2^15, r = 8, p = 1.
+const MAX_SCRYPT_MEMORY: u64 = 1 << 30;
+const MAX_SCRYPT_P: u32 = 16;
+
+#[derive(Deserialize)]
+struct Vault {
+    header: Header,
+    db: Value,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    slots: Option<Vec<Slot>>,
+    params: Option<KeyParams>,
+}
+
+#[derive(Deserialize)]
+struct Slot {
+    #[serde(rename = "type")]
+    kind: u8,
+    key: String,
+    key_params: KeyParams,
+    n: Option<u64>,
+    r: Option<u32>,
+    p: Option<u32>,
+    salt: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KeyParams {
+    nonce: String,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+struct Db {
+    entries: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    issuer: Option<String>,
+    info: Info,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    secret: String,
+    algo: Option<String>,
+    digits: Option<u8>,
+    period: Option<u32>,
+    counter: Option<u32>,
+}
+
+pub struct Aegis;
+
+impl Importer for Aegis {
+    fn name(&self) -> &'static str {
+        "aegis"
+    }
+
+    fn detect(&self, _data: &[u8], json: Option<&Value>) -> bool {
+        json.is_some_and(|j| j.get("header").is_some() && j.get("db").is_some())
+    }
+
+    fn parse(&self, data: &[u8], password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+        let vault: Vault =
+            serde_json::from_slice(data).map_err(|e| format!("not an Aegis vault: {e}"))?;
+
+        let db: Db = match vault.db {
+            Value::String(ref ciphertext) => {
+                let password =
+                    password.ok_or("this vault is encrypted, a password is required")?;
+                let plaintext = decrypt_db(&vault.header, ciphertext, password)?;
+                serde_json::from_slice(&plaintext).map_err(|e| format!("parse vault: {e}"))?
+            }
+            db => serde_json::from_value(db).map_err(|e| format!("parse vault: {e}"))?,
+        };
+
+        Ok(db.entries.into_iter().map(map_entry).collect())
+    }
+}
+
+fn decrypt_db(header: &Header, ciphertext: &str, password: &str) -> Result<Vec<u8>, String> {
+    let params = header.params.as_ref().ok_or("vault header has no params")?;
+    let slots = header.slots.as_deref().unwrap_or_default();
+
+    let master_key = slots
+        .iter()
+        .filter(|s| s.kind == PASSWORD_SLOT)
+        .find_map(|slot| unwrap_slot(slot, password).ok())
+        .ok_or("wrong password or no password slot in vault")?;
+
+    let db = B64
+        .decode(ciphertext.trim())
+        .map_err(|e| format!("decode vault: {e}"))?;
+    gcm_open(&master_key, params, &db)
+}
+
+fn unwrap_slot(slot: &Slot, password: &str) -> Result<[u8; 32], String> {
+    let n = slot.n.ok_or("slot missing n")?;
+    if !n.is_power_of_two() {
+        return Err("invalid scrypt n".into());
+    }
+    let r = slot.r.ok_or("slot missing r")?;
+    let p = slot.p.ok_or("slot missing p")?;
+    let memory = n.checked_mul(u64::from(r)).and_then(|v| v.checked_mul(128));
+    if !memory.is_some_and(|m| m <= MAX_SCRYPT_MEMORY) || p > MAX_SCRYPT_P {
+        return Err("scrypt parameters are too large".into());
+    }
+    let params = scrypt::Params::new(n.trailing_zeros() as u8, r, p, 32)
+        .map_err(|e| format!("scrypt params: {e}"))?;
+    let salt = hex::decode(slot.salt.as_deref().ok_or("slot missing salt")?)
+        .map_err(|e| format!("decode salt: {e}"))?;
+
+    let mut derived = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), &salt, &params, &mut derived)
+        .map_err(|e| format!("scrypt: {e}"))?;
+
+    let wrapped = hex::decode(&slot.key).map_err(|e| format!("decode slot key: {e}"))?;
+    let master = gcm_open(&derived, &slot.key_params, &wrapped)?;
+    master
+        .try_into()
+        .map_err(|_| "invalid master key length".to_string())
+}
+
+/// Aegis stores nonce and tag apart from the ciphertext; reassemble them into
+/// the `nonce || ciphertext || tag` layout `encryption::decrypt` expects.
+fn gcm_open(key: &[u8; 32], params: &KeyParams, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut sealed = hex::decode(&params.nonce).map_err(|e| format!("decode nonce: {e}"))?;
+    if sealed.len() != 12 {
+        return Err("invalid nonce length".into());
+    }
+    sealed.extend_from_slice(ciphertext);
+    sealed.extend(hex::decode(&params.tag).map_err(|e| format!("decode tag: {e}"))?);
+    encryption::decrypt(&sealed, key)
+}
+
+fn map_entry(raw: Value) -> ImportEntry {
+    match serde_json::from_value::<Entry>(raw.clone()) {
+        Ok(entry) => OtpFields {
+            service: entry.issuer,
+            account: entry.name,
+            kind: entry.kind,
+            secret: entry.info.secret,
+            algorithm: entry.info.algo,
+            digits: entry.info.digits,
+            period: entry.info.period,
+            counter: entry.info.counter,
+        }
+        .into(),
+        Err(e) => ImportEntry::unsupported(
+            raw.get("issuer").and_then(Value::as_str),
+            raw.get("name").and_then(Value::as_str),
+            raw.get("type").and_then(Value::as_str).unwrap_or("unknown"),
+            format!("malformed entry: {e}"),
+        ),
+    }
+}
@@ -0,0 +1,137 @@
+//! andOTP backups: a plain JSON array, or the same JSON sealed with
+//! AES-256-GCM. Encrypted backups are
+//! `iterations (u32 BE) || salt (12) || nonce (12) || ciphertext || tag`,
+//! keyed with PBKDF2-HMAC-SHA1; backups from before andOTP 0.6.3 use
+//! `nonce || ciphertext || tag` keyed with SHA-256 of the password.
+//!
+//! Neither layout has a magic number. The iteration count at the front of
+//! the current one is what tells it apart from any other binary file, so
+//! the older layout, which starts with a random nonce, is not recognised.
+
+use serde::Deserialize;
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::{ImportEntry, Importer, OtpFields};
+use crate::crypto::encryption;
+
+const ITERATIONS_LEN: usize = 4;
+const SALT_LEN: usize = 12;
+/// Iteration counts andOTP never writes; anything outside is the old layout.
+const MAX_ITERATIONS: u32 = 1_000_000;
+/// The shortest encrypted backup: the header, then a nonce and a tag around
+/// no ciphertext.
+const MIN_ENCRYPTED_LEN: usize = ITERATIONS_LEN + SALT_LEN + 12 + 16;
+
+#[derive(Deserialize)]
+struct Entry {
+    secret: String,
+    issuer: Option<String>,
+    label: Option<String>,
+    #[serde(rename = "type")]
+    kind: String,
+    algorithm: Option<String>,
+    digits: Option<u8>,
+    period: Option<u32>,
+    counter: Option<u32>,
+}
+
+pub struct AndOtp;
+
+impl Importer for AndOtp {
+    fn name(&self) -> &'static str {
+        "andotp"
+    }
+
+    fn detect(&self, data: &[u8], json: Option<&Value>) -> bool {
+        match json {
+            Some(Value::Array(entries)) => entries
+                .first()
+                .is_some_and(|e| e.get("secret").is_some() && e.get("type").is_some()),
+            Some(_) => false,
+            // Not JSON: the only binary format we read is an encrypted andOTP
+            // backup, which opens with an iteration count andOTP could have
+            // written. PNG, zip and the like start with bytes far outside it.
+            None => {
+                data.len() >= MIN_ENCRYPTED_LEN
+                    && (1..=MAX_ITERATIONS).contains(&iterations(data))
+                    && std::str::from_utf8(data).is_err()
+            }
+        }
+    }
+
+    fn parse(&self, data: &[u8], password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+        let entries: Vec<Value> = match serde_json::from_slice(data) {
+            Ok(entries) => entries,
+            Err(_) => {
+                let password = password.ok_or("this backup is encrypted, a password is required")?;
+                let plaintext = decrypt(data, password)?;
+                serde_json::from_slice(&plaintext).map_err(|e| format!("parse backup: {e}"))?
+            }
+        };
+        Ok(entries.into_iter().map(map_entry).collect())
+    }
+}
+
+fn decrypt(data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    let header = ITERATIONS_LEN + SALT_LEN;
+    if data.len() > header {
+        let iterations = iterations(data);
+        if (1..=MAX_ITERATIONS).contains(&iterations) {
+            let mut key = [0u8; 32];
+            pbkdf2::pbkdf2_hmac::<Sha1>(
+                password.as_bytes(),
+                &data[ITERATIONS_LEN..header],
+                iterations,
+                &mut key,
+            );
+            if let Ok(plaintext) = encryption::decrypt(&data[header..], &key) {
+                return Ok(plaintext);
+            }
+        }
+    }
+
+    let key: [u8; 32] = Sha256::digest(password.as_bytes()).into();
+    encryption::decrypt(data, &key).map_err(|_| "wrong password or corrupt backup".to_string())
+}
+
+fn iterations(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn map_entry(raw: Value) -> ImportEntry {
+    let entry = match serde_json::from_value::<Entry>(raw.clone()) {
+        Ok(entry) => entry,
+        Err(e) => {
+            return ImportEntry::unsupported(
+                raw.get("issuer").and_then(Value::as_str),
+                raw.get("label").and_then(Value::as_str),
+                raw.get("type").and_then(Value::as_str).unwrap_or("unknown"),
+                format!("malformed entry: {e}"),
+            )
+        }
+    };
+
+    // Older andOTP versions kept everything in the label as "Issuer:account".
+    let label = entry.label.unwrap_or_default();
+    let (service, account) = match entry.issuer.filter(|i| !i.is_empty()) {
+        Some(issuer) => (Some(issuer), Some(label)),
+        None => match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), Some(account.trim().to_string())),
+            None => (None, Some(label)),
+        },
+    };
+
+    OtpFields {
+        service,
+        account,
+        kind: entry.kind,
+        secret: entry.secret,
+        algorithm: entry.algorithm,
+        digits: entry.digits,
+        period: entry.period,
+        counter: entry.counter,
+    }
+    .into()
+}
@@ -0,0 +1,106 @@
+//! Bitwarden JSON vault exports. Only login items with a `login.totp` value
+//! are considered; password-protected exports must be re-exported unencrypted.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportEntry, Importer, OtpFields};
+use crate::models::types::CreateAccountPayload;
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    name: Option<String>,
+    login: Option<Login>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    username: Option<String>,
+    totp: Option<String>,
+}
+
+pub struct Bitwarden;
+
+impl Importer for Bitwarden {
+    fn name(&self) -> &'static str {
+        "bitwarden"
+    }
+
+    fn detect(&self, _data: &[u8], json: Option<&Value>) -> bool {
+        json.is_some_and(|j| j.get("items").is_some() && j.get("encrypted").is_some())
+    }
+
+    fn parse(&self, data: &[u8], _password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+        let export: Export =
+            serde_json::from_slice(data).map_err(|e| format!("not a Bitwarden export: {e}"))?;
+        if export.encrypted {
+            return Err("encrypted Bitwarden exports are not supported, export as plain JSON".into());
+        }
+        Ok(export.items.into_iter().filter_map(map_item).collect())
+    }
+}
+
+fn map_item(raw: Value) -> Option<ImportEntry> {
+    let item = match serde_json::from_value::<Item>(raw.clone()) {
+        Ok(item) => item,
+        Err(e) => {
+            return Some(ImportEntry::unsupported(
+                raw.get("name").and_then(Value::as_str),
+                None,
+                "unknown",
+                format!("malformed entry: {e}"),
+            ))
+        }
+    };
+    let login = item.login?;
+    let totp = login.totp.filter(|t| !t.trim().is_empty())?;
+    let totp = totp.trim();
+
+    // `login.totp` holds either a full otpauth URI, a `steam://` secret or a
+    // bare base32 TOTP secret.
+    if totp.starts_with("otpauth://") {
+        let otp_type = if totp.starts_with("otpauth://hotp") { "hotp" } else { "totp" };
+        return Some(ImportEntry {
+            service: item.name,
+            account: login.username,
+            otp_type: otp_type.to_string(),
+            payload: Ok(CreateAccountPayload {
+                uri: Some(totp.to_string()),
+                service: None,
+                account: None,
+                secret: None,
+                otp_type: None,
+                digits: None,
+                period: None,
+                counter: None,
+                algorithm: None,
+            }),
+        });
+    }
+
+    let (kind, secret) = match totp.strip_prefix("steam://") {
+        Some(secret) => ("steam", secret),
+        None => ("totp", totp),
+    };
+    Some(
+        OtpFields {
+            service: item.name,
+            account: login.username,
+            kind: kind.to_string(),
+            secret: secret.to_string(),
+            algorithm: None,
+            digits: None,
+            period: None,
+            counter: None,
+        }
+        .into(),
+    )
+}
@@ -0,0 +1,165 @@
+pub mod aegis;
+pub mod andotp;
+pub mod bitwarden;
+pub mod twofas;
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::backup;
+use crate::models::types::{
+    Account, CreateAccountPayload, ImportCandidate, ImportPreview, ImportStatus,
+};
+
+/// A third-party export format that can be turned into candidate accounts.
+pub trait Importer: Send + Sync {
+    /// Short identifier reported back in the preview.
+    fn name(&self) -> &'static str;
+
+    /// Whether the file looks like this format. `json` is the parsed file, or
+    /// `None` when it isn't JSON at all (e.g. an encrypted binary backup).
+    fn detect(&self, data: &[u8], json: Option<&Value>) -> bool;
+
+    /// Reads every entry. Only file-level problems (bad password, wrong
+    /// format) are errors; a bad entry becomes an unsupported `ImportEntry`.
+    fn parse(&self, data: &[u8], password: Option<&str>) -> Result<Vec<ImportEntry>, String>;
+}
+
+static IMPORTERS: &[&dyn Importer] = &[
+    &backup::Backup,
+    &aegis::Aegis,
+    &twofas::TwoFas,
+    &bitwarden::Bitwarden,
+    &andotp::AndOtp,
+];
+
+/// Picks the importer for a file by looking at its contents.
+pub fn detect(data: &[u8]) -> Result<&'static dyn Importer, String> {
+    let json = serde_json::from_slice::<Value>(data).ok();
+    IMPORTERS
+        .iter()
+        .copied()
+        .find(|importer| importer.detect(data, json.as_ref()))
+        .ok_or_else(|| "unrecognized export format".to_string())
+}
+
+/// One account read from a third-party export. `payload` is `Err` with a
+/// reason when the entry can't be represented in 2FAuth.
+#[derive(Debug, Clone)]
+pub struct ImportEntry {
+    pub service: Option<String>,
+    pub account: Option<String>,
+    pub otp_type: String,
+    pub payload: Result<CreateAccountPayload, String>,
+}
+
+impl ImportEntry {
+    pub fn unsupported(
+        service: Option<&str>,
+        account: Option<&str>,
+        otp_type: &str,
+        reason: String,
+    ) -> Self {
+        Self {
+            service: service.map(str::to_string),
+            account: account.map(str::to_string),
+            otp_type: otp_type.to_string(),
+            payload: Err(reason),
+        }
+    }
+}
+
+/// The fields every secret-based export carries, in the exporter's own
+/// spelling; converting to an `ImportEntry` normalises them for 2FAuth.
+pub struct OtpFields {
+    pub service: Option<String>,
+    pub account: Option<String>,
+    pub kind: String,
+    pub secret: String,
+    pub algorithm: Option<String>,
+    pub digits: Option<u8>,
+    pub period: Option<u32>,
+    pub counter: Option<u32>,
+}
+
+impl From<OtpFields> for ImportEntry {
+    fn from(f: OtpFields) -> Self {
+        let service = f.service.filter(|s| !s.is_empty());
+        let account = f.account.filter(|s| !s.is_empty());
+        let otp_type = match f.kind.to_lowercase().as_str() {
+            "totp" => Ok("totp"),
+            "hotp" => Ok("hotp"),
+            "steam" | "steamtotp" => Ok("steamtotp"),
+            _ => Err(format!("{} is not supported by 2FAuth", f.kind)),
+        };
+
+        let payload = otp_type.map(|otp_type| CreateAccountPayload {
+            uri: None,
+            service: service.clone(),
+            account: account.clone(),
+            secret: Some(f.secret.replace(' ', "").to_uppercase()),
+            otp_type: Some(otp_type.to_string()),
+            digits: f.digits,
+            period: f.period.filter(|_| otp_type != "hotp"),
+            counter: f.counter.filter(|_| otp_type == "hotp"),
+            algorithm: f.algorithm.map(|a| a.to_lowercase()),
+        });
+
+        ImportEntry {
+            service,
+            account,
+            otp_type: f.kind.to_lowercase(),
+            payload,
+        }
+    }
+}
+
+fn identity(service: Option<&str>, account: Option<&str>) -> (String, String) {
+    let norm = |s: Option<&str>| s.unwrap_or_default().trim().to_lowercase();
+    (norm(service), norm(account))
+}
+
+/// Classifies every entry before anything is created: unsupported entries,
+/// entries already on the server (same service and account), and repeats
+/// within the file itself are all flagged.
+pub fn preview(format: &str, entries: &[ImportEntry], existing: &[Account]) -> ImportPreview {
+    let on_server: HashSet<(String, String)> = existing
+        .iter()
+        .map(|a| identity(a.service.as_deref(), a.account.as_deref()))
+        .collect();
+    let mut in_file = HashSet::new();
+
+    let candidates = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let (status, reason) = match entry.payload {
+                Err(ref reason) => (ImportStatus::Unsupported, Some(reason.clone())),
+                Ok(_) => {
+                    let key = identity(entry.service.as_deref(), entry.account.as_deref());
+                    if on_server.contains(&key) {
+                        (ImportStatus::Duplicate, Some("already on the server".into()))
+                    } else if !in_file.insert(key) {
+                        (ImportStatus::Duplicate, Some("repeated in this file".into()))
+                    } else {
+                        (ImportStatus::Ready, None)
+                    }
+                }
+            };
+            ImportCandidate {
+                index,
+                service: entry.service.clone(),
+                account: entry.account.clone(),
+                otp_type: entry.otp_type.clone(),
+                status,
+                reason,
+            }
+        })
+        .collect();
+
+    ImportPreview {
+        format: format.to_string(),
+        candidates,
+    }
+}
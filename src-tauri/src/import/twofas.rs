@@ -0,0 +1,80 @@
+//! 2FAS Authenticator backups (`.2fas`). Only unencrypted backups can be
+//! read; 2FAS encrypts the whole service list when a password is set.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{ImportEntry, Importer, OtpFields};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backup {
+    #[serde(default)]
+    services: Vec<Value>,
+    services_encrypted: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Service {
+    name: Option<String>,
+    secret: String,
+    otp: Otp,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Otp {
+    account: Option<String>,
+    issuer: Option<String>,
+    digits: Option<u8>,
+    period: Option<u32>,
+    algorithm: Option<String>,
+    token_type: Option<String>,
+    counter: Option<u32>,
+}
+
+pub struct TwoFas;
+
+impl Importer for TwoFas {
+    fn name(&self) -> &'static str {
+        "2fas"
+    }
+
+    fn detect(&self, _data: &[u8], json: Option<&Value>) -> bool {
+        json.is_some_and(|j| {
+            j.get("schemaVersion").is_some()
+                && (j.get("services").is_some() || j.get("servicesEncrypted").is_some())
+        })
+    }
+
+    fn parse(&self, data: &[u8], _password: Option<&str>) -> Result<Vec<ImportEntry>, String> {
+        let backup: Backup =
+            serde_json::from_slice(data).map_err(|e| format!("not a 2FAS backup: {e}"))?;
+        if backup.services_encrypted.is_some() {
+            return Err("encrypted 2FAS backups are not supported, export without a password".into());
+        }
+        Ok(backup.services.into_iter().map(map_service).collect())
+    }
+}
+
+fn map_service(raw: Value) -> ImportEntry {
+    match serde_json::from_value::<Service>(raw.clone()) {
+        Ok(service) => OtpFields {
+            service: service.otp.issuer.or(service.name),
+            account: service.otp.account,
+            kind: service.otp.token_type.unwrap_or_else(|| "TOTP".into()),
+            secret: service.secret,
+            algorithm: service.otp.algorithm,
+            digits: service.otp.digits,
+            period: service.otp.period,
+            counter: service.otp.counter,
+        }
+        .into(),
+        Err(e) => ImportEntry::unsupported(
+            raw.get("name").and_then(Value::as_str),
+            raw.pointer("/otp/account").and_then(Value::as_str),
+            raw.pointer("/otp/tokenType").and_then(Value::as_str).unwrap_or("unknown"),
+            format!("malformed entry: {e}"),
+        ),
+    }
+}
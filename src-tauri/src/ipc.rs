@@ -0,0 +1,444 @@
+//! Opt-in local socket that lets other programs (scripts, editor plugins,
+//! SSH wrappers) ask the running app for codes without holding the API
+//! token. One JSON object per line in each direction:
+//!
+//! ```text
+//! -> {"method":"list"}
+//! -> {"method":"get","id":12}
+//! -> {"method":"get","query":"github"}
+//! -> {"method":"autofill","url":"https://github.com/login","origin":"..."}
+//! <- {"ok":true,"result":...}
+//! <- {"ok":false,"error":"..."}
+//! ```
+//!
+//! Only processes of the same user can connect. Each program has to be
+//! approved once through a native prompt; approvals are kept in
+//! `Settings::ipc_allowlist`. Programs are identified by their executable,
+//! and for interpreters such as `/usr/bin/python3` also by the script they
+//! run, where it can be found (Linux only). An interpreter approved without
+//! a script, or a relay like `socat`, passes on the approval to anything it
+//! runs; the prompt says so.
+//!
+//! `autofill` is what the browser native messaging host sends: the accounts
+//! matching the page are offered in the tray and the code is only returned
+//! once the user picks one there.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener as StdUnixListener;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::autofill;
+use crate::commands::{self, data_dir};
+use crate::models::types::{AuditAction, AuditSource, AutofillCandidate};
+use crate::search::{self, Match};
+use crate::state::AppState;
+use crate::store;
+
+/// No legitimate request comes close to this.
+const MAX_REQUEST_LEN: u64 = 4096;
+
+/// Programs that run other code given to them. Matched on the executable's
+/// name without a version suffix, so `python3.12` counts as `python`.
+const INTERPRETERS: &[&str] = &[
+    "python",
+    "perl",
+    "ruby",
+    "node",
+    "deno",
+    "bun",
+    "php",
+    "lua",
+    "sh",
+    "bash",
+    "dash",
+    "zsh",
+    "fish",
+    "osascript",
+    "socat",
+    "nc",
+    "ncat",
+    "netcat",
+];
+
+/// A connecting program as the allowlist knows it.
+struct Peer {
+    id: String,
+    /// An interpreter whose script wasn't found; approving it approves
+    /// whatever it runs.
+    unscoped: bool,
+}
+
+/// Keeps a burst of connections from unknown programs to a single prompt.
+static PROMPT: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum Request {
+    List,
+    Get {
+        id: Option<u32>,
+        query: Option<String>,
+    },
+    Autofill {
+        url: String,
+        origin: Option<String>,
+    },
+}
+
+/// Starts or stops the server to match `Settings::ipc_enabled`.
+pub fn sync(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let enabled = state
+        .settings
+        .lock()
+        .map_err(|e| format!("lock: {e}"))?
+        .ipc_enabled;
+    let mut server = state.ipc_server.lock().map_err(|e| format!("lock: {e}"))?;
+    let path = store::socket_path(&data_dir(app)?);
+
+    if enabled && server.is_none() {
+        let listener = bind(&path)?;
+        log::info!("ipc: listening on {}", path.display());
+        *server = Some(tauri::async_runtime::spawn(serve(app.clone(), listener)));
+    } else if !enabled {
+        if let Some(handle) = server.take() {
+            handle.abort();
+            let _ = fs::remove_file(&path);
+        }
+    }
+    Ok(())
+}
+
+fn bind(path: &Path) -> Result<StdUnixListener, String> {
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err("ipc: socket is in use by another instance".into());
+        }
+        fs::remove_file(path).map_err(|e| format!("remove stale socket: {e}"))?;
+    }
+    let listener = StdUnixListener::bind(path).map_err(|e| format!("bind socket: {e}"))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("socket permissions: {e}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("socket nonblocking: {e}"))?;
+    Ok(listener)
+}
+
+async fn serve(app: AppHandle, listener: StdUnixListener) {
+    let listener = match UnixListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("ipc: {e}");
+            return;
+        }
+    };
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = handle(&app, stream).await {
+                        log::warn!("ipc: {e}");
+                    }
+                });
+            }
+            Err(e) => {
+                log::error!("ipc accept: {e}");
+                return;
+            }
+        }
+    }
+}
+
+async fn handle(app: &AppHandle, stream: UnixStream) -> Result<(), String> {
+    let peer = peer(&stream)?;
+    let (read, mut write) = stream.into_split();
+
+    if !authorize(app, &peer).await? {
+        respond(&mut write, Err(format!("access denied for {}", peer.id))).await?;
+        return Ok(());
+    }
+
+    let mut reader = BufReader::new(read);
+    loop {
+        let mut line = String::new();
+        let n = (&mut reader)
+            .take(MAX_REQUEST_LEN)
+            .read_line(&mut line)
+            .await
+            .map_err(|e| format!("read: {e}"))?;
+        if n == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') && n as u64 == MAX_REQUEST_LEN {
+            return respond(&mut write, Err("request too long".into())).await;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = dispatch(app, &line).await;
+        if let Err(ref e) = result {
+            log::info!("ipc: {}: {e}", peer.id);
+        }
+        respond(&mut write, result).await?;
+    }
+}
+
+async fn respond(write: &mut OwnedWriteHalf, result: Result<Value, String>) -> Result<(), String> {
+    let body = match result {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    };
+    let mut line = body.to_string();
+    line.push('\n');
+    write
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| format!("write: {e}"))
+}
+
+async fn dispatch(app: &AppHandle, line: &str) -> Result<Value, String> {
+    let request: Request =
+        serde_json::from_str(line).map_err(|e| format!("bad request: {e}"))?;
+
+    let state = app.state::<AppState>();
+    let http = state.http_client()?;
+    let service = commands::account_service(app, &state, &http)?;
+
+    match request {
+        Request::List => {
+            let accounts = service.list().await?;
+            Ok(accounts
+                .iter()
+                .map(|a| {
+                    json!({
+                        "id": a.id,
+                        "service": a.service,
+                        "account": a.account,
+                        "otp_type": a.otp_type,
+                    })
+                })
+                .collect())
+        }
+        Request::Get { id, query } => {
+            let id = match (id, query) {
+                (Some(id), _) => id,
+                (None, Some(query)) => {
+                    let accounts = service.list().await?;
+                    match search::resolve(&accounts, &query) {
+                        Match::One(account) => account.id,
+                        other => return Err(search::describe_failure(&query, &other)),
+                    }
+                }
+                (None, None) => return Err("get needs an id or a query".into()),
+            };
+            let otp = service.otp(id).await?;
+            record_use(app, id, AuditSource::Ipc);
+            Ok(json!({ "id": id, "otp": otp }))
+        }
+        Request::Autofill { url, origin } => {
+            let host = autofill::host_of(&url).ok_or("autofill needs an http(s) page url")?;
+            let accounts = service.list().await?;
+            let patterns = state
+                .settings
+                .lock()
+                .map_err(|e| format!("lock: {e}"))?
+                .url_patterns
+                .clone();
+            let candidates: Vec<_> = autofill::match_accounts(&accounts, &host, &patterns)
+                .into_iter()
+                .map(|a| AutofillCandidate {
+                    id: a.id,
+                    service: a.service.clone(),
+                    account: a.account.clone(),
+                })
+                .collect();
+            if candidates.is_empty() {
+                return Err(format!("no account for {host}"));
+            }
+
+            let id = commands::autofill::confirm(app, origin, host, candidates)
+                .await?
+                .ok_or("request denied")?;
+            let account = accounts.iter().find(|a| a.id == id);
+            let otp = service.otp(id).await?;
+            record_use(app, id, AuditSource::Browser);
+            Ok(json!({
+                "id": id,
+                "service": account.and_then(|a| a.service.clone()),
+                "account": account.and_then(|a| a.account.clone()),
+                "otp": otp,
+            }))
+        }
+    }
+}
+
+fn record_use(app: &AppHandle, account_id: u32, source: AuditSource) {
+    commands::audit::record(app, AuditAction::CodeRequested, Some(account_id), source, None);
+    if let Err(e) = commands::search::note_use(app, account_id) {
+        log::warn!("ipc: record usage: {e}");
+    }
+}
+
+fn is_allowed(app: &AppHandle, id: &str) -> Result<bool, String> {
+    let state = app.state::<AppState>();
+    let settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+    Ok(settings.ipc_allowlist.iter().any(|allowed| allowed == id))
+}
+
+async fn authorize(app: &AppHandle, peer: &Peer) -> Result<bool, String> {
+    if is_allowed(app, &peer.id)? {
+        return Ok(true);
+    }
+    let _prompt = PROMPT.lock().await;
+    // Another connection from the same program may have been approved meanwhile.
+    if is_allowed(app, &peer.id)? {
+        return Ok(true);
+    }
+
+    let dialog = app.clone();
+    let mut message = format!(
+        "{} wants to read one-time codes from 2FA Auth.\n\n\
+         Allowing it lets it request codes whenever the app is running. \
+         You can revoke access in Settings.",
+        peer.id
+    );
+    if peer.unscoped {
+        message.push_str(
+            "\n\nThis program runs other scripts and commands. Allowing it \
+             allows everything it runs, not just the one asking now.",
+        );
+    }
+    let allowed = tauri::async_runtime::spawn_blocking(move || {
+        dialog
+            .dialog()
+            .message(message)
+            .title("Allow access to your codes?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Allow".into(),
+                "Deny".into(),
+            ))
+            .blocking_show()
+    })
+    .await
+    .map_err(|e| format!("prompt: {e}"))?;
+
+    if allowed {
+        let state = app.state::<AppState>();
+        let settings = {
+            let mut settings = state.settings.lock().map_err(|e| format!("lock: {e}"))?;
+            settings.ipc_allowlist.push(peer.id.clone());
+            settings.clone()
+        };
+        store::write_settings(&data_dir(app)?, &settings)?;
+        commands::audit::record(
+            app,
+            AuditAction::IpcClientApproved,
+            None,
+            AuditSource::Ipc,
+            Some(peer.id.clone()),
+        );
+    }
+    log::info!(
+        "ipc: {} {}",
+        peer.id,
+        if allowed { "allowed" } else { "denied" }
+    );
+    Ok(allowed)
+}
+
+fn peer(stream: &UnixStream) -> Result<Peer, String> {
+    let cred = stream
+        .peer_cred()
+        .map_err(|e| format!("peer credentials: {e}"))?;
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    if cred.uid() != unsafe { libc::geteuid() } {
+        return Err("rejected connection from another user".into());
+    }
+    let pid = cred.pid().ok_or("peer process id unavailable")?;
+    let exe = executable_of(pid)?;
+    if !is_interpreter(&exe) {
+        return Ok(Peer {
+            id: exe,
+            unscoped: false,
+        });
+    }
+    Ok(match script_of(pid) {
+        Some(script) => Peer {
+            id: format!("{exe} {script}"),
+            unscoped: false,
+        },
+        None => Peer {
+            id: exe,
+            unscoped: true,
+        },
+    })
+}
+
+fn is_interpreter(exe: &str) -> bool {
+    let name = Path::new(exe)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let name = name.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS.contains(&name)
+}
+
+/// The script file an interpreter was started with: its first argument
+/// that isn't an option and names an existing file. `python3 -c ...` and
+/// the like have none.
+#[cfg(target_os = "linux")]
+fn script_of(pid: i32) -> Option<String> {
+    let cmdline = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let arg = cmdline
+        .split(|b| *b == 0)
+        .skip(1)
+        .map(String::from_utf8_lossy)
+        .find(|arg| !arg.is_empty() && !arg.starts_with('-'))?;
+    let cwd = fs::read_link(format!("/proc/{pid}/cwd")).ok()?;
+    let path = fs::canonicalize(cwd.join(&*arg)).ok()?;
+    path.is_file().then(|| path.display().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn script_of(_pid: i32) -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn executable_of(pid: i32) -> Result<String, String> {
+    fs::read_link(format!("/proc/{pid}/exe"))
+        .map(|path| path.display().to_string())
+        .map_err(|e| format!("peer executable: {e}"))
+}
+
+#[cfg(target_os = "macos")]
+fn executable_of(pid: i32) -> Result<String, String> {
+    let mut buf = vec![0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+    // SAFETY: the buffer is valid for writes of its full length.
+    let len = unsafe { libc::proc_pidpath(pid, buf.as_mut_ptr().cast(), buf.len() as u32) };
+    if len <= 0 {
+        return Err(format!(
+            "peer executable: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    buf.truncate(len as usize);
+    String::from_utf8(buf).map_err(|e| format!("peer executable: {e}"))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn executable_of(_pid: i32) -> Result<String, String> {
+    Err("peer executable: unsupported platform".into())
+}
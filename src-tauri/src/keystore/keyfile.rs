@@ -0,0 +1,57 @@
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Key, KeyStore};
+use crate::crypto::secret::{Secret, Zeroizing};
+use crate::models::types::KeySource;
+use crate::store;
+
+/// `keyfile.key` in the data directory, readable by anyone who can read the
+/// encrypted files next to it.
+pub struct KeyFile {
+    path: PathBuf,
+}
+
+impl KeyFile {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: store::key_path(dir),
+        }
+    }
+}
+
+impl KeyStore for KeyFile {
+    fn source(&self) -> KeySource {
+        KeySource::Keyfile
+    }
+
+    fn load(&self) -> Result<Option<Key>, String> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let encoded = Zeroizing::new(
+            fs::read_to_string(&self.path).map_err(|e| format!("read key: {e}"))?,
+        );
+        let bytes = Zeroizing::new(
+            B64.decode(encoded.trim())
+                .map_err(|e| format!("decode key: {e}"))?,
+        );
+        let key: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| "invalid key length".to_string())?;
+        Ok(Some(Secret::new(key)))
+    }
+
+    fn save(&self, key: &Key) -> Result<(), String> {
+        store::write_atomic(&self.path, Zeroizing::new(B64.encode(key.expose())).as_bytes())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        if self.path.exists() {
+            fs::remove_file(&self.path).map_err(|e| format!("remove key: {e}"))?;
+        }
+        Ok(())
+    }
+}
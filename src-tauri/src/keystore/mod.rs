@@ -0,0 +1,176 @@
+//! Where the key that encrypts `credentials.enc` and `trash.enc` is kept.
+//! `Settings::key_source` picks the store; `migrate` re-encrypts the vault
+//! under a different one.
+
+mod keyfile;
+mod os_keyring;
+pub mod password;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::secret::Secret;
+use crate::crypto::{encryption, envelope};
+use crate::models::types::KeySource;
+use crate::{store, trash};
+
+pub use keyfile::KeyFile;
+pub use os_keyring::OsKeyring;
+pub use password::MasterPassword;
+
+pub type Key = Secret<[u8; 32]>;
+
+pub trait KeyStore {
+    fn source(&self) -> KeySource;
+
+    /// The key, or `None` if this store has none yet.
+    fn load(&self) -> Result<Option<Key>, String>;
+
+    fn save(&self, key: &Key) -> Result<(), String>;
+
+    /// Forgets the key; anything encrypted with it is lost.
+    fn clear(&self) -> Result<(), String>;
+
+    fn load_or_create(&self) -> Result<Key, String> {
+        if let Some(key) = self.load()? {
+            return Ok(key);
+        }
+        let key = Secret::new(encryption::generate_key());
+        self.save(&key)?;
+        Ok(key)
+    }
+}
+
+/// The store `source` names for the vault in `dir`. A master password store
+/// opened this way only has a key once `password::unlock` succeeded.
+pub fn open(dir: &Path, source: KeySource) -> Box<dyn KeyStore> {
+    match source {
+        KeySource::Keyfile => Box::new(KeyFile::new(dir)),
+        KeySource::Keyring => Box::new(OsKeyring::new(dir)),
+        KeySource::Password => Box::new(MasterPassword::new(dir)),
+    }
+}
+
+/// The store the settings in `dir` select.
+pub fn current(dir: &Path) -> Result<Box<dyn KeyStore>, String> {
+    Ok(open(dir, store::read_settings(dir)?.key_source))
+}
+
+/// The store `source` names, opened with `password` for a master password
+/// store, e.g. to save a new key in it.
+pub fn open_with_password(
+    dir: &Path,
+    source: KeySource,
+    password: Option<&str>,
+) -> Result<Box<dyn KeyStore>, String> {
+    Ok(match source {
+        KeySource::Password => Box::new(MasterPassword::with_password(
+            dir,
+            password.ok_or("a master password is needed")?,
+        )),
+        other => open(dir, other),
+    })
+}
+
+/// Re-encrypts the vault under a fresh key in the store it already uses. A
+/// master password store needs its current password to wrap the new key.
+pub fn rotate(dir: &Path, password: Option<&str>) -> Result<(), String> {
+    let target = open_with_password(dir, current(dir)?.source(), password)?;
+    if target.source() == KeySource::Password {
+        target.load()?;
+    }
+    migrate(dir, target.as_ref())
+}
+
+fn vault_files(dir: &Path) -> [PathBuf; 2] {
+    [store::credentials_path(dir), trash::trash_path(dir)]
+}
+
+fn staging_path(path: &Path) -> PathBuf {
+    path.with_extension("enc.new")
+}
+
+/// Re-encrypts the vault under a new key kept in `to`, then switches the
+/// settings over and forgets the old key. The current store must be
+/// readable, so a master password has to be unlocked first.
+///
+/// Until the settings name the new store, each vault file's backup holds it
+/// under the old key, so `store::read_vault` falls back to the old vault if
+/// the process dies in between. Failures before that point put the old files
+/// back.
+pub fn migrate(dir: &Path, to: &dyn KeyStore) -> Result<(), String> {
+    let from = current(dir)?;
+    let mut settings = store::read_settings(dir)?;
+    let mut vault = Vec::new();
+    for path in vault_files(dir) {
+        let _ = fs::remove_file(staging_path(&path));
+        if path.exists() {
+            if let Some(plaintext) = store::read_vault(dir, &path)? {
+                let old = fs::read(&path).map_err(|e| format!("read {}: {e}", path.display()))?;
+                store::write_atomic(&store::backup_path(&path), &old)?;
+                vault.push((path, plaintext, old));
+            }
+        }
+    }
+
+    let new_key = Secret::new(encryption::generate_key());
+    let staged: Result<Vec<Vec<u8>>, String> = vault
+        .iter()
+        .map(|(path, plaintext, _)| {
+            let encrypted = envelope::seal(plaintext, new_key.expose(), to.source())?;
+            store::write_atomic(&staging_path(path), &encrypted)?;
+            Ok(encrypted)
+        })
+        .collect();
+    let staged = match staged {
+        Ok(staged) => staged,
+        Err(e) => {
+            for (path, ..) in &vault {
+                let _ = fs::remove_file(staging_path(path));
+            }
+            return Err(e);
+        }
+    };
+
+    let mut replaced = 0;
+    let result = vault
+        .iter()
+        .try_for_each(|(path, ..)| {
+            fs::rename(staging_path(path), path).map_err(|e| format!("replace vault: {e}"))?;
+            replaced += 1;
+            Ok(())
+        })
+        .and_then(|()| to.save(&new_key))
+        .and_then(|()| {
+            // A rotation has nothing to switch, and its old key is gone.
+            if settings.key_source == to.source() {
+                return Ok(());
+            }
+            settings.key_source = to.source();
+            store::write_settings(dir, &settings)
+        });
+    if let Err(e) = result {
+        for (path, _, old) in &vault[..replaced] {
+            if let Err(e) = store::write_atomic(path, old) {
+                log::warn!("{}: left for its backup to restore: {e}", path.display());
+            }
+        }
+        for (path, ..) in &vault[replaced..] {
+            let _ = fs::remove_file(staging_path(path));
+        }
+        return Err(e);
+    }
+    for ((path, ..), encrypted) in vault.iter().zip(&staged) {
+        // The settings name the new key, so the backups can follow it.
+        if let Err(e) = store::write_atomic(&store::backup_path(path), encrypted) {
+            log::warn!("{}: backup still under the old key: {e}", path.display());
+        }
+    }
+
+    if from.source() != to.source() {
+        if let Err(e) = from.clear() {
+            log::warn!("keystore: old key left behind: {e}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use super::{Key, KeyStore};
+use crate::crypto::secret::{Secret, Zeroizing};
+use crate::models::types::KeySource;
+use crate::store::APP_IDENTIFIER;
+
+/// The OS keyring: the freedesktop Secret Service (GNOME Keyring, KWallet)
+/// on Linux, the Keychain on macOS and the Credential Manager on Windows.
+pub struct OsKeyring {
+    /// One entry per data directory, so a second profile or a test run
+    /// doesn't overwrite the app's key.
+    user: String,
+}
+
+impl OsKeyring {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            user: format!("vault-key:{}", dir.display()),
+        }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(APP_IDENTIFIER, &self.user).map_err(|e| format!("keyring: {e}"))
+    }
+}
+
+impl KeyStore for OsKeyring {
+    fn source(&self) -> KeySource {
+        KeySource::Keyring
+    }
+
+    fn load(&self) -> Result<Option<Key>, String> {
+        match self.entry()?.get_secret() {
+            Ok(bytes) => Zeroizing::new(bytes)
+                .as_slice()
+                .try_into()
+                .map(|key| Some(Secret::new(key)))
+                .map_err(|_| "keyring: invalid key length".to_string()),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("keyring: {e}")),
+        }
+    }
+
+    fn save(&self, key: &Key) -> Result<(), String> {
+        self.entry()?
+            .set_secret(key.expose())
+            .map_err(|e| format!("keyring: {e}"))
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        match self.entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("keyring: {e}")),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! A key that only opens with the master password. The vault key is random
+//! and stored in `password.key`, wrapped with a key derived from the
+//! password by Argon2id. Once unlocked it stays in memory until the process
+//! exits, so the password is asked for once per start.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{Key, KeyStore};
+use crate::crypto::secret::{Secret, SecretString, Zeroizing};
+use crate::backup::{affordable, derive_key, MIN_PASSWORD_LEN};
+use crate::crypto::encryption;
+use crate::models::types::KeySource;
+use crate::store;
+
+const M_COST: u32 = 64 * 1024;
+const T_COST: u32 = 3;
+const P_COST: u32 = 1;
+const SALT_LEN: usize = 16;
+
+/// Keys unlocked in this process, by data directory.
+static UNLOCKED: Mutex<Vec<(PathBuf, Key)>> = Mutex::new(Vec::new());
+
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    salt: String,
+    key: String,
+}
+
+pub struct MasterPassword {
+    dir: PathBuf,
+    password: Option<SecretString>,
+}
+
+impl MasterPassword {
+    /// The store as the rest of the app sees it: usable once unlocked.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            password: None,
+        }
+    }
+
+    /// The store opened, or about to be set up, with `password`.
+    pub fn with_password(dir: &Path, password: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            password: Some(password.into()),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join("password.key")
+    }
+
+    fn cached(&self) -> Result<Option<Key>, String> {
+        let unlocked = UNLOCKED.lock().map_err(|e| format!("lock: {e}"))?;
+        Ok(unlocked
+            .iter()
+            .find(|(dir, _)| *dir == self.dir)
+            .map(|(_, key)| key.clone()))
+    }
+
+    fn cache(&self, key: Option<Key>) -> Result<(), String> {
+        let mut unlocked = UNLOCKED.lock().map_err(|e| format!("lock: {e}"))?;
+        unlocked.retain(|(dir, _)| *dir != self.dir);
+        unlocked.extend(key.map(|key| (self.dir.clone(), key)));
+        Ok(())
+    }
+}
+
+impl KeyStore for MasterPassword {
+    fn source(&self) -> KeySource {
+        KeySource::Password
+    }
+
+    fn load(&self) -> Result<Option<Key>, String> {
+        let path = self.path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let Some(password) = &self.password else {
+            return match self.cached()? {
+                Some(key) => Ok(Some(key)),
+                None => Err("the vault is locked, enter the master password".into()),
+            };
+        };
+        let data = fs::read(&path).map_err(|e| format!("read key: {e}"))?;
+        let wrapped: WrappedKey =
+            serde_json::from_slice(&data).map_err(|e| format!("parse key: {e}"))?;
+        if !affordable(wrapped.m_cost, wrapped.t_cost, wrapped.p_cost) {
+            return Err("key derivation parameters are too large".into());
+        }
+        let salt = B64
+            .decode(&wrapped.salt)
+            .map_err(|e| format!("decode key: {e}"))?;
+        let sealed = B64
+            .decode(&wrapped.key)
+            .map_err(|e| format!("decode key: {e}"))?;
+        let kek = derive_key(
+            password.expose(),
+            &salt,
+            wrapped.m_cost,
+            wrapped.t_cost,
+            wrapped.p_cost,
+        )?;
+        let plain = Zeroizing::new(
+            encryption::decrypt(&sealed, kek.expose()).map_err(|_| "wrong master password".to_string())?,
+        );
+        let key = Secret::new(
+            plain
+                .as_slice()
+                .try_into()
+                .map_err(|_| "invalid key length".to_string())?,
+        );
+        self.cache(Some(key.clone()))?;
+        Ok(Some(key))
+    }
+
+    fn save(&self, key: &Key) -> Result<(), String> {
+        let password = self
+            .password
+            .as_ref()
+            .map(|p| p.expose().as_str())
+            .ok_or("the master password is needed to store the key")?;
+        if password.chars().count() < MIN_PASSWORD_LEN {
+            return Err(format!(
+                "master password must be at least {MIN_PASSWORD_LEN} characters"
+            ));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let kek = derive_key(password, &salt, M_COST, T_COST, P_COST)?;
+        let wrapped = WrappedKey {
+            m_cost: M_COST,
+            t_cost: T_COST,
+            p_cost: P_COST,
+            salt: B64.encode(salt),
+            key: B64.encode(encryption::encrypt(key.expose(), kek.expose())?),
+        };
+        let json = serde_json::to_vec_pretty(&wrapped).map_err(|e| format!("serialize: {e}"))?;
+        store::write_atomic(&self.path(), &json)?;
+        self.cache(Some(key.clone()))
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        let path = self.path();
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("remove key: {e}"))?;
+        }
+        self.cache(None)
+    }
+}
+
+/// Unlocks the vault in `dir` for the rest of this process.
+pub fn unlock(dir: &Path, password: &str) -> Result<(), String> {
+    MasterPassword::with_password(dir, password)
+        .load()?
+        .map(|_| ())
+        .ok_or_else(|| "no master password is set".to_string())
+}
+
+/// Whether `dir` needs a master password that hasn't been entered yet.
+pub fn is_locked(dir: &Path) -> Result<bool, String> {
+    let store = MasterPassword::new(dir);
+    Ok(store.path().exists() && store.cached()?.is_none())
+}
@@ -1,20 +1,43 @@
-mod api;
+pub mod api;
+pub mod audit;
+pub mod autofill;
+pub mod backup;
 mod commands;
-mod crypto;
-mod models;
+pub mod crypto;
+mod export;
+pub mod import;
+mod icons;
+#[cfg(unix)]
+mod ipc;
+pub mod keystore;
+pub mod models;
+pub mod qr;
+pub mod search;
+pub mod service;
 mod state;
+pub mod store;
+pub mod trash;
 mod tray;
 
 use state::AppState;
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_positioner::init())
         .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .manage(AppState::new())
+        .register_asynchronous_uri_scheme_protocol("icon", |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            let path = request.uri().path().to_string();
+            tauri::async_runtime::spawn(async move {
+                responder.respond(icons::serve(&app, &path).await);
+            });
+        })
         .setup(|app| {
+            app.manage(AppState::new()?);
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
@@ -22,6 +45,19 @@ pub fn run() {
                         .build(),
                 )?;
             }
+            store::restrict_permissions(&commands::data_dir(app.handle())?)?;
+            let handle = app.handle().clone();
+            app.state::<AppState>().auth.on_rejected(move || {
+                log::warn!("the server rejected the API token");
+                let _ = handle.emit("auth-required", ());
+            });
+            commands::settings::init(app.handle())?;
+            commands::search::init(app.handle())?;
+            commands::trash::start_purge(app.handle());
+            #[cfg(unix)]
+            if let Err(e) = ipc::sync(app.handle()) {
+                log::error!("ipc: {e}");
+            }
             tray::setup_tray(app)?;
             Ok(())
         })
@@ -31,16 +67,50 @@ pub fn run() {
             commands::credentials::clear_credentials,
             commands::credentials::get_credentials,
             commands::credentials::verify_connection,
+            commands::credentials::reauthenticate,
+            commands::credentials::replace_api_token,
+            commands::credentials::is_auth_required,
+            commands::credentials::get_certificate_pin,
+            commands::credentials::approve_certificate_pin,
+            commands::credentials::clear_certificate_pin,
+            commands::keystore::is_vault_locked,
+            commands::keystore::unlock_vault,
+            commands::keystore::migrate_key_store,
+            commands::keystore::rotate_vault_key,
             commands::accounts::fetch_accounts,
             commands::accounts::create_account,
             commands::accounts::preview_account,
             commands::accounts::delete_account,
+            commands::accounts::delete_accounts,
+            commands::accounts::move_accounts_to_group,
+            commands::accounts::reorder_accounts,
+            commands::accounts::set_pinned,
+            commands::trash::list_trash,
+            commands::trash::undo_delete,
             commands::otp::fetch_otp,
+            commands::search::search_accounts,
+            commands::search::record_usage,
+            commands::autofill::pending_autofill_requests,
+            commands::autofill::respond_autofill,
+            commands::import::preview_import,
+            commands::import::import_accounts,
+            commands::backup::export_backup,
+            commands::backup::restore_backup,
+            commands::export::export_accounts,
             commands::qrcode::decode_qr,
+            commands::icons::upload_icon,
+            commands::icons::fetch_official_icon,
+            commands::icons::set_account_icon,
             commands::screenshot::check_screen_permission,
             commands::screenshot::request_screen_permission,
             commands::screenshot::capture_screen_region,
             commands::screenshot::scan_screen_for_qr,
+            commands::audit::query_audit_log,
+            commands::audit::verify_audit_log,
+            commands::settings::get_settings,
+            commands::settings::update_settings,
+            commands::settings::sync_preferences,
+            commands::settings::revoke_ipc_client,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
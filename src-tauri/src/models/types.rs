@@ -1,9 +1,59 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::crypto::secret::SecretString;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Credentials {
     pub server_url: String,
-    pub api_token: String,
+    pub api_token: SecretString,
+    #[serde(default)]
+    pub cert_pin: Option<String>,
+    /// Found by `verify_connection`; `None` for a server never probed, where
+    /// every feature is tried.
+    #[serde(default)]
+    pub capabilities: Option<ServerCapabilities>,
+    /// Whose token it is, so a replacement can be checked without a call
+    /// made with the old token, which may already be revoked. `None` for
+    /// credentials saved before it was kept.
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// The optional parts of the 2FAuth API a server has. 2FAuth doesn't tell
+/// API clients its version, so each is found by probing its endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    pub groups: bool,
+    pub export: bool,
+    pub preferences: bool,
+}
+
+/// The credentials as the webview sees them: the token never leaves the
+/// backend, only a hint of it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialsView {
+    pub server_url: String,
+    pub token_hint: String,
+    pub cert_pin: Option<String>,
+    pub capabilities: Option<ServerCapabilities>,
+}
+
+impl From<&Credentials> for CredentialsView {
+    fn from(creds: &Credentials) -> Self {
+        Self {
+            server_url: creds.server_url.clone(),
+            token_hint: creds.api_token.masked(),
+            cert_pin: creds.cert_pin.clone(),
+            capabilities: creds.capabilities,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificatePinStatus {
+    pub pinned: Option<String>,
+    pub presented: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +68,27 @@ pub struct Account {
     pub counter: Option<u32>,
     pub algorithm: Option<String>,
     pub otp: Option<OtpValue>,
+    #[serde(default)]
+    pub group_id: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IconFile {
+    pub filename: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub id: u32,
+    pub name: String,
+}
+
+/// How often and how recently an account's code was used, for ranking.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub count: u32,
+    /// Unix seconds.
+    pub last_used: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,3 +133,327 @@ pub struct AccountPreview {
 pub struct QrDecodeResponse {
     pub data: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiErrorBody {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub request_timeout_secs: u64,
+    pub max_retries: u32,
+    /// Serve codes to local programs over the IPC socket.
+    pub ipc_enabled: bool,
+    /// Programs approved to use the IPC socket: the executable, followed by
+    /// the script for interpreters.
+    pub ipc_allowlist: Vec<String>,
+    /// Extra sites each account is offered for in browser autofill.
+    pub url_patterns: Vec<UrlPattern>,
+    /// Accounts shown first in the tray, in this order. Local only.
+    pub pinned: Vec<u32>,
+    /// The server `url_patterns` and `pinned` belong to, since they hold
+    /// its account ids.
+    pub server_url: Option<String>,
+    /// How long deleted accounts can be restored.
+    pub trash_retention_hours: u64,
+    /// Where the vault key is kept. Changed through `migrate_key_store`
+    /// only, since the vault has to be re-encrypted.
+    pub key_source: KeySource,
+    /// The user's 2FAuth preferences that apply here, kept in step with
+    /// the server.
+    pub preferences: UserPreferences,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 15,
+            max_retries: 3,
+            ipc_enabled: false,
+            ipc_allowlist: Vec::new(),
+            url_patterns: Vec::new(),
+            pinned: Vec::new(),
+            server_url: None,
+            trash_retention_hours: 24,
+            key_source: KeySource::default(),
+            preferences: UserPreferences::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Ties the account settings to `server_url`, dropping those made for
+    /// another server. Settings from before this was tracked are assumed to
+    /// be for it. Returns whether anything changed.
+    pub fn claim_for(&mut self, server_url: &str) -> bool {
+        match self.server_url.as_deref() {
+            Some(url) if url == server_url => return false,
+            Some(_) => {
+                self.url_patterns.clear();
+                self.pinned.clear();
+            }
+            None => {}
+        }
+        self.server_url = Some(server_url.to_string());
+        true
+    }
+}
+
+/// One entry of `/api/v1/user/preferences`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserPreference {
+    pub key: String,
+    pub value: Value,
+}
+
+/// The 2FAuth preferences the desktop app follows, with 2FAuth's defaults.
+/// `defaultGroup` and `displayMode` are left out since the app shows every
+/// group in one list, and `copyOtpOnDisplay` since codes are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserPreferences {
+    /// Codes are masked until copied.
+    pub show_otp_as_dot: bool,
+    pub show_accounts_icons: bool,
+    /// Close the tray popup once a code is copied.
+    pub close_otp_on_copy: bool,
+    pub clear_search_on_copy: bool,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            show_otp_as_dot: false,
+            show_accounts_icons: true,
+            close_otp_on_copy: false,
+            clear_search_on_copy: false,
+        }
+    }
+}
+
+impl UserPreferences {
+    /// The preferences under their 2FAuth names.
+    pub fn remote(&self) -> [(&'static str, Value); 4] {
+        [
+            ("showOtpAsDot", self.show_otp_as_dot.into()),
+            ("showAccountsIcons", self.show_accounts_icons.into()),
+            ("closeOtpOnCopy", self.close_otp_on_copy.into()),
+            ("clearSearchOnCopy", self.clear_search_on_copy.into()),
+        ]
+    }
+
+    /// Reads the server's entries over `self`; anything the server lacks or
+    /// holds in an unexpected form keeps its current value.
+    pub fn merge_remote(mut self, entries: &[UserPreference]) -> Self {
+        for entry in entries {
+            let Some(value) = entry.value.as_bool() else {
+                continue;
+            };
+            match entry.key.as_str() {
+                "showOtpAsDot" => self.show_otp_as_dot = value,
+                "showAccountsIcons" => self.show_accounts_icons = value,
+                "closeOtpOnCopy" => self.close_otp_on_copy = value,
+                "clearSearchOnCopy" => self.clear_search_on_copy = value,
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// The entries of `self` that differ from `current`, to send to the
+    /// server.
+    pub fn changes_from(&self, current: &UserPreferences) -> Vec<(&'static str, Value)> {
+        self.remote()
+            .into_iter()
+            .zip(current.remote())
+            .filter(|(wanted, had)| wanted != had)
+            .map(|(wanted, _)| wanted)
+            .collect()
+    }
+}
+
+/// Where the key that encrypts the stored credentials is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// `keyfile.key` next to the encrypted files.
+    #[default]
+    Keyfile,
+    /// The OS keyring, e.g. the Secret Service on Linux.
+    Keyring,
+    /// Wrapped with a master password entered once per start.
+    Password,
+}
+
+/// A deleted account that can still be restored; the secret stays in the
+/// backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub account_id: u32,
+    pub service: Option<String>,
+    pub account: Option<String>,
+    /// Unix seconds.
+    pub deleted_at: u64,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlPattern {
+    pub account_id: u32,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillCandidate {
+    pub id: u32,
+    pub service: Option<String>,
+    pub account: Option<String>,
+}
+
+/// A browser asking for a code, waiting for the user to pick an account in
+/// the tray.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutofillRequest {
+    pub id: u64,
+    pub origin: Option<String>,
+    pub host: String,
+    pub candidates: Vec<AutofillCandidate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Ready,
+    Duplicate,
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportCandidate {
+    pub index: usize,
+    pub service: Option<String>,
+    pub account: Option<String>,
+    pub otp_type: String,
+    pub status: ImportStatus,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub format: String,
+    pub candidates: Vec<ImportCandidate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportResult {
+    pub index: usize,
+    pub account: Option<Account>,
+    pub error: Option<String>,
+}
+
+/// Outcome of a batch operation for one of the accounts it was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub account_id: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedAccount {
+    pub service: Option<String>,
+    pub account: Option<String>,
+    pub icon: Option<String>,
+    pub otp_type: String,
+    pub secret: Option<String>,
+    pub digits: Option<u8>,
+    pub algorithm: Option<String>,
+    pub period: Option<u32>,
+    pub counter: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportResponse {
+    pub data: Vec<ExportedAccount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSkip {
+    pub account: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub directory: String,
+    pub files: Vec<String>,
+    pub skipped: Vec<ExportSkip>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    CodeCopied,
+    CodeRequested,
+    AccountCreated,
+    AccountDeleted,
+    AccountUpdated,
+    AccountsImported,
+    AccountsExported,
+    BackupExported,
+    BackupRestored,
+    CredentialsSaved,
+    CredentialsCleared,
+    CertificatePinChanged,
+    Reauthenticated,
+    IpcClientApproved,
+    KeyStoreChanged,
+    VaultKeyRotated,
+    ApiTokenReplaced,
+}
+
+/// Where an audited operation was started from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    App,
+    Tray,
+    Hotkey,
+    Ipc,
+    Browser,
+    Cli,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    /// Unix milliseconds.
+    pub timestamp: u64,
+    pub action: AuditAction,
+    pub account_id: Option<u32>,
+    /// The server the credentials in use belong to.
+    pub profile: Option<String>,
+    pub source: AuditSource,
+    pub detail: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    pub action: Option<AuditAction>,
+    pub account_id: Option<u32>,
+    pub source: Option<AuditSource>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub entries: usize,
+    /// Sequence number of the first entry whose hash or link doesn't check out.
+    pub broken_at: Option<u64>,
+}
@@ -0,0 +1,19 @@
+/// Decodes the first QR code found in an image, locally with rqrr.
+pub fn decode(image_data: &[u8]) -> Result<String, String> {
+    let img = image::load_from_memory(image_data)
+        .map_err(|e| format!("load image: {e}"))?
+        .to_luma8();
+
+    let mut prepared = rqrr::PreparedImage::prepare(img);
+    let grids = prepared.detect_grids();
+
+    if grids.is_empty() {
+        return Err("no QR code found".into());
+    }
+
+    let (_meta, content) = grids[0]
+        .decode()
+        .map_err(|e| format!("decode QR: {e}"))?;
+
+    Ok(content)
+}
@@ -0,0 +1,200 @@
+//! Finding accounts from what a user types.
+
+use std::collections::HashMap;
+
+use crate::models::types::{Account, Usage};
+
+/// How much a field counts towards the match score.
+const SERVICE_WEIGHT: f64 = 1.0;
+const ACCOUNT_WEIGHT: f64 = 0.8;
+const GROUP_WEIGHT: f64 = 0.6;
+
+/// Usage older than this counts half as much.
+const USAGE_HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 3600.0;
+/// How far usage can lift a weaker match: a frecency of 1 adds this much.
+const USAGE_BOOST: f64 = 0.25;
+
+pub enum Match<'a> {
+    One(&'a Account),
+    None,
+    Many(Vec<&'a Account>),
+}
+
+/// Resolves `query` to an account: an id, an exact service name, or else a
+/// case-insensitive substring of service or account name.
+pub fn resolve<'a>(accounts: &'a [Account], query: &str) -> Match<'a> {
+    if let Ok(id) = query.parse::<u32>() {
+        if let Some(account) = accounts.iter().find(|a| a.id == id) {
+            return Match::One(account);
+        }
+    }
+
+    let q = query.to_lowercase();
+    let exact: Vec<_> = accounts
+        .iter()
+        .filter(|a| a.service.as_deref().is_some_and(|s| s.to_lowercase() == q))
+        .collect();
+    let mut matches = if exact.is_empty() {
+        accounts
+            .iter()
+            .filter(|a| {
+                [a.service.as_deref(), a.account.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .any(|s| s.to_lowercase().contains(&q))
+            })
+            .collect()
+    } else {
+        exact
+    };
+
+    match matches.len() {
+        0 => Match::None,
+        1 => Match::One(matches.remove(0)),
+        _ => Match::Many(matches),
+    }
+}
+
+pub fn label(account: &Account) -> String {
+    match (account.service.as_deref(), account.account.as_deref()) {
+        (Some(s), Some(a)) => format!("{s} ({a})"),
+        (Some(s), None) => s.to_string(),
+        (None, Some(a)) => a.to_string(),
+        (None, None) => format!("#{}", account.id),
+    }
+}
+
+/// The error shown when `resolve` didn't find exactly one account.
+pub fn describe_failure(query: &str, result: &Match) -> String {
+    match result {
+        Match::One(account) => label(account),
+        Match::None => format!("no account matches \"{query}\""),
+        Match::Many(many) => format!(
+            "\"{query}\" matches {} accounts: {}",
+            many.len(),
+            many.iter()
+                .map(|a| format!("{} [{}]", label(a), a.id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Scores `candidate` against `query` as a case-insensitive subsequence
+/// match, from 0 (no match) to 1 (equal). Matches at the start of a word
+/// (including camel-case humps) and runs of consecutive characters score
+/// higher, so "gh" prefers "GitHub" over "Lightning".
+pub fn fuzzy_score(candidate: &str, query: &str) -> f64 {
+    let candidate = fold(candidate);
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if query.is_empty() || query.len() > candidate.len() {
+        return 0.0;
+    }
+    if candidate.iter().map(|(c, _)| *c).eq(query.iter().copied()) {
+        return 1.0;
+    }
+
+    // Greedy from every position the first query character occurs at; the
+    // best alignment wins.
+    let best = (0..candidate.len())
+        .filter(|&start| candidate[start].0 == query[0])
+        .filter_map(|start| score_from(&candidate, &query, start))
+        .fold(0.0, f64::max);
+    // Per character: 1 for the match, 2 for following the previous match,
+    // 3 for starting a word; the maximum is kept just below an exact match.
+    best / (query.len() as f64 * 6.0) * 0.95
+}
+
+/// Lowercased characters, each flagged if a word starts there.
+fn fold(s: &str) -> Vec<(char, bool)> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut previous: Option<char> = None;
+    for c in s.chars() {
+        let starts_word = match previous {
+            None => true,
+            Some(p) => !p.is_alphanumeric() || (p.is_lowercase() && c.is_uppercase()),
+        };
+        for (i, lower) in c.to_lowercase().enumerate() {
+            out.push((lower, starts_word && i == 0));
+        }
+        previous = Some(c);
+    }
+    out
+}
+
+fn score_from(candidate: &[(char, bool)], query: &[char], start: usize) -> Option<f64> {
+    let mut score = 0.0;
+    let mut previous: Option<usize> = None;
+    let mut position = start;
+    for &q in query {
+        let found = (position..candidate.len()).find(|&i| candidate[i].0 == q)?;
+        score += 1.0;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 2.0;
+        }
+        if candidate[found].1 {
+            score += 3.0;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// Frequency damped logarithmically, decayed by time since last use.
+fn frecency(usage: Option<&Usage>, now: u64) -> f64 {
+    let Some(usage) = usage else {
+        return 0.0;
+    };
+    let age = now.saturating_sub(usage.last_used) as f64;
+    (1.0 + usage.count as f64).ln() * 0.5f64.powf(age / USAGE_HALF_LIFE_SECS)
+}
+
+/// Accounts matching `query` on service, account name or group name, best
+/// first, with frequently and recently used accounts ranked up. Pinned
+/// accounts that match come before all others. An empty query ranks every
+/// account by use alone.
+pub fn rank<'a>(
+    accounts: &'a [Account],
+    group_names: &HashMap<u32, String>,
+    usage: &HashMap<u32, Usage>,
+    pinned: &[u32],
+    query: &str,
+    now: u64,
+    limit: usize,
+) -> Vec<&'a Account> {
+    let query = query.trim();
+    let mut scored: Vec<(f64, &Account)> = accounts
+        .iter()
+        .filter_map(|account| {
+            let usage = frecency(usage.get(&account.id), now);
+            if query.is_empty() {
+                return Some((usage, account));
+            }
+            let group = account.group_id.and_then(|id| group_names.get(&id));
+            let fields = [
+                (account.service.as_deref(), SERVICE_WEIGHT),
+                (account.account.as_deref(), ACCOUNT_WEIGHT),
+                (group.map(String::as_str), GROUP_WEIGHT),
+            ];
+            let score = fields
+                .iter()
+                .filter_map(|(field, weight)| field.map(|f| fuzzy_score(f, query) * weight))
+                .fold(0.0, f64::max);
+            (score > 0.0).then_some((score * (1.0 + USAGE_BOOST * usage), account))
+        })
+        .collect();
+
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        pinned
+            .contains(&b.id)
+            .cmp(&pinned.contains(&a.id))
+            .then_with(|| b_score.total_cmp(a_score))
+            .then_with(|| label(a).to_lowercase().cmp(&label(b).to_lowercase()))
+    });
+    scored.into_iter().take(limit).map(|(_, account)| account).collect()
+}
@@ -0,0 +1,458 @@
+use std::collections::{HashMap, HashSet};
+
+use futures_util::stream::{self, StreamExt};
+
+use super::{now, Backend, Storage};
+use crate::api::error::ApiError;
+use crate::backup;
+use crate::import::{self, ImportEntry};
+use crate::models::types::*;
+use crate::trash::Tombstone;
+
+/// Requests in flight at once when working through a batch one account at
+/// a time.
+const CONCURRENCY: usize = 4;
+
+/// Prefix of the error `delete` fails with when the server can't export the
+/// account for the trash. The webview matches on it to offer
+/// `delete_for_good` instead.
+pub const CANNOT_UNDO: &str = "this server can't export accounts";
+
+/// Accounts on one server. Deletions go through the trash, so they can be
+/// undone until it expires.
+pub struct AccountService<B, S> {
+    backend: B,
+    storage: S,
+    server_url: String,
+    capabilities: Option<ServerCapabilities>,
+}
+
+impl<B: Backend, S: Storage> AccountService<B, S> {
+    pub fn new(backend: B, storage: S, server_url: &str) -> Self {
+        Self {
+            backend,
+            storage,
+            server_url: server_url.to_string(),
+            capabilities: None,
+        }
+    }
+
+    /// Fails features the server is known not to have up front, instead of
+    /// sending requests it will reject.
+    pub fn with_capabilities(mut self, capabilities: Option<ServerCapabilities>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    fn require(
+        &self,
+        supported: fn(&ServerCapabilities) -> bool,
+        feature: &'static str,
+    ) -> Result<(), ApiError> {
+        match &self.capabilities {
+            Some(capabilities) if !supported(capabilities) => Err(ApiError::Unsupported(feature)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub async fn list(&self) -> Result<Vec<Account>, String> {
+        Ok(self.backend.list_accounts().await?)
+    }
+
+    pub async fn otp(&self, account_id: u32) -> Result<OtpValue, String> {
+        Ok(self.backend.get_otp(account_id).await?)
+    }
+
+    pub async fn create(&self, payload: &CreateAccountPayload) -> Result<Account, String> {
+        Ok(self.backend.create_account(payload).await?)
+    }
+
+    pub async fn preview(&self, uri: &str) -> Result<AccountPreview, String> {
+        Ok(self.backend.preview_uri(uri).await?)
+    }
+
+    /// Decodes a QR code image on the server, for images the local decoder
+    /// can't read.
+    pub async fn decode_qr(&self, image: Vec<u8>) -> Result<String, String> {
+        Ok(self.backend.decode_qr(image).await?)
+    }
+
+    /// The selected accounts with their secrets. Ids the server doesn't know
+    /// are left out.
+    pub async fn export(&self, ids: &[u32]) -> Result<Vec<ExportedAccount>, String> {
+        self.require(|c| c.export, "exporting accounts")?;
+        Ok(self.backend.export_accounts(ids).await?)
+    }
+
+    pub async fn groups(&self) -> Result<Vec<Group>, String> {
+        self.require(|c| c.groups, "groups")?;
+        Ok(self.backend.list_groups().await?)
+    }
+
+    /// Uploads an image to the server's icons, returning its filename there.
+    pub async fn upload_icon(
+        &self,
+        image: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+    ) -> Result<String, String> {
+        Ok(self.backend.upload_icon(image, file_name, mime).await?.filename)
+    }
+
+    /// Has the server fetch the official icon for `service`; `None` if its
+    /// icon collection has none.
+    pub async fn fetch_official_icon(&self, service: &str) -> Result<Option<String>, String> {
+        match self.backend.fetch_official_icon(service).await {
+            Ok(icon) => Ok(Some(icon.filename)),
+            Err(e) if e.is_not_found() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Sets or clears an account's icon. The server wants every field back,
+    /// so the account is exported and sent again with the new icon.
+    pub async fn set_icon(&self, account_id: u32, icon: Option<String>) -> Result<Account, String> {
+        let mut account = self.export_one(account_id).await?;
+        account.icon = icon;
+        Ok(self.backend.update_account(account_id, &account).await?)
+    }
+
+    /// Saves `ids` as the account order, first to last.
+    pub async fn reorder(&self, ids: &[u32]) -> Result<(), String> {
+        Ok(self.backend.reorder_accounts(ids).await?)
+    }
+
+    /// Every account with its secret, for a backup.
+    pub async fn export_all(&self) -> Result<Vec<ExportedAccount>, String> {
+        self.require(|c| c.export, "exporting accounts")?;
+        let ids: Vec<u32> = self.list().await?.iter().map(|a| a.id).collect();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.backend.export_accounts(&ids).await?)
+    }
+
+    /// Deletes an account on the server after saving it, secret included, to
+    /// the trash. If the account can't be fetched and saved first nothing is
+    /// deleted. The tombstone is only dropped again when the server refused
+    /// the delete; after a timeout it may hold the only copy of the secret.
+    pub async fn delete(&self, account_id: u32) -> Result<(), String> {
+        let account = self.keep_copy(account_id).await?;
+        let tombstones = self.tombstones(vec![(account_id, account)])?;
+        self.storage.add_to_trash(tombstones, now())?;
+
+        match self.backend.delete_account(account_id).await {
+            Ok(()) => Ok(()),
+            // Already gone, e.g. a retry after an attempt that timed out
+            // once the server had applied it.
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) => {
+                if e.is_refusal() {
+                    self.untrash(&[account_id]);
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// `delete` for several accounts, with one trash write and the server's
+    /// batch delete, or single deletes when the server lacks it. Accounts
+    /// that fail don't stop the others.
+    pub async fn delete_many(&self, account_ids: &[u32]) -> Result<Vec<BatchResult>, String> {
+        let account_ids = &dedup(account_ids);
+        // The export doesn't say which account is which, so fetch them one by one.
+        let exported: Vec<(u32, Result<ExportedAccount, String>)> =
+            stream::iter(account_ids.iter().copied())
+                .map(|id| async move { (id, self.keep_copy(id).await) })
+                .buffered(CONCURRENCY)
+                .collect()
+                .await;
+
+        let mut errors: HashMap<u32, String> = HashMap::new();
+        let mut found = Vec::new();
+        for (id, result) in exported {
+            match result {
+                Ok(account) => found.push((id, account)),
+                Err(e) => {
+                    errors.insert(id, e);
+                }
+            }
+        }
+        let ids: Vec<u32> = found.iter().map(|(id, _)| *id).collect();
+        self.storage.add_to_trash(self.tombstones(found)?, now())?;
+
+        let deleted = if ids.is_empty() {
+            Ok(())
+        } else {
+            self.backend.delete_accounts(&ids).await
+        };
+        // Only accounts known to still be on the server leave the trash
+        // again, as in `delete`.
+        let mut still_there = Vec::new();
+        let failed: Vec<(u32, String)> = match deleted {
+            Ok(()) => Vec::new(),
+            Err(e) if e.is_unsupported() => {
+                let results: Vec<(u32, Result<(), ApiError>)> = stream::iter(ids.iter().copied())
+                    .map(|id| async move { (id, self.backend.delete_account(id).await) })
+                    .buffer_unordered(CONCURRENCY)
+                    .collect()
+                    .await;
+                let mut failed = Vec::new();
+                for (id, result) in results {
+                    match result {
+                        Err(e) if !e.is_not_found() => {
+                            if e.is_refusal() {
+                                still_there.push(id);
+                            }
+                            failed.push((id, e.to_string()));
+                        }
+                        _ => {}
+                    }
+                }
+                failed
+            }
+            // Some or all of the batch may have been applied; what the
+            // server still lists is what failed.
+            Err(e) => match self.backend.list_accounts().await {
+                Ok(accounts) => {
+                    let listed: HashSet<u32> = accounts.iter().map(|a| a.id).collect();
+                    still_there = ids.iter().copied().filter(|id| listed.contains(id)).collect();
+                    still_there.iter().map(|id| (*id, e.to_string())).collect()
+                }
+                Err(_) => ids.iter().map(|id| (*id, e.to_string())).collect(),
+            },
+        };
+        self.untrash(&still_there);
+        errors.extend(failed);
+
+        Ok(account_ids
+            .iter()
+            .map(|id| BatchResult {
+                account_id: *id,
+                error: errors.remove(id),
+            })
+            .collect())
+    }
+
+    /// Drops the tombstones of accounts the server still has. Failing to
+    /// only leaves them to expire, which beats hiding the error that got
+    /// us here.
+    fn untrash(&self, account_ids: &[u32]) {
+        if account_ids.is_empty() {
+            return;
+        }
+        if let Err(e) = self
+            .storage
+            .discard_from_trash(&self.server_url, account_ids, now())
+        {
+            log::warn!("trash: {e}");
+        }
+    }
+
+    pub fn trash(&self) -> Result<Vec<TrashEntry>, String> {
+        self.storage.list_trash(&self.server_url, now())
+    }
+
+    /// Re-creates a deleted account from the trash. The server gives it a new
+    /// id, which the returned account carries.
+    pub async fn undo_delete(&self, account_id: u32) -> Result<Account, String> {
+        let tombstone = self
+            .storage
+            .take_from_trash(&self.server_url, account_id, now())?
+            .ok_or("nothing to undo, the account is no longer in the trash")?;
+        let payload = backup::entry(tombstone.account.clone()).payload?;
+
+        match self.backend.create_account(&payload).await {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                self.storage.add_to_trash(vec![tombstone], now())?;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Moves accounts into a group, or out of any group when `group_id` is
+    /// `None`. The server does this in one request, so they all succeed or
+    /// all fail.
+    pub async fn move_to_group(
+        &self,
+        account_ids: &[u32],
+        group_id: Option<u32>,
+    ) -> Result<Vec<BatchResult>, String> {
+        let account_ids = &dedup(account_ids);
+        if account_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.require(|c| c.groups, "groups")?;
+        let result = match group_id {
+            Some(group_id) => self.backend.assign_to_group(group_id, account_ids).await,
+            None => self.backend.withdraw_from_groups(account_ids).await,
+        };
+        let error = result.err().map(String::from);
+        Ok(account_ids
+            .iter()
+            .map(|account_id| BatchResult {
+                account_id: *account_id,
+                error: error.clone(),
+            })
+            .collect())
+    }
+
+    /// The server's preferences read over `current`, or `None` if the
+    /// server keeps none.
+    pub async fn pull_preferences(
+        &self,
+        current: UserPreferences,
+    ) -> Result<Option<UserPreferences>, String> {
+        if self.require(|c| c.preferences, "preferences").is_err() {
+            return Ok(None);
+        }
+        match self.backend.get_preferences().await {
+            Ok(entries) => Ok(Some(current.merge_remote(&entries))),
+            Err(e) if e.is_unsupported() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Sends the preferences that differ from `current`. A server without
+    /// preferences leaves them local.
+    pub async fn push_preferences(
+        &self,
+        current: &UserPreferences,
+        wanted: &UserPreferences,
+    ) -> Result<(), String> {
+        if self.require(|c| c.preferences, "preferences").is_err() {
+            return Ok(());
+        }
+        for (key, value) in wanted.changes_from(current) {
+            match self.backend.set_preference(key, &value).await {
+                Ok(_) => {}
+                Err(e) if e.is_unsupported() => return Ok(()),
+                Err(e) => return Err(format!("preference {key}: {e}")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Classifies parsed entries against what is already on the server.
+    pub async fn preview_import(
+        &self,
+        format: &str,
+        entries: &[ImportEntry],
+    ) -> Result<ImportPreview, String> {
+        let existing = self.list().await?;
+        Ok(import::preview(format, entries, &existing))
+    }
+
+    /// Creates the given entries one at a time. A failure is recorded for
+    /// that entry and the rest carry on.
+    pub async fn create_entries(
+        &self,
+        entries: &[ImportEntry],
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Vec<ImportResult> {
+        let mut results = Vec::new();
+        for index in indices {
+            let outcome = match entries.get(index).map(|e| &e.payload) {
+                None => Err("no such entry".to_string()),
+                Some(Err(reason)) => Err(reason.clone()),
+                Some(Ok(payload)) => self.create(payload).await,
+            };
+            results.push(match outcome {
+                Ok(account) => ImportResult {
+                    index,
+                    account: Some(account),
+                    error: None,
+                },
+                Err(error) => ImportResult {
+                    index,
+                    account: None,
+                    error: Some(error),
+                },
+            });
+        }
+        results
+    }
+
+    /// Creates every entry not already on the server; the others are
+    /// reported as skipped.
+    pub async fn restore(&self, entries: &[ImportEntry]) -> Result<Vec<ImportResult>, String> {
+        let preview = self.preview_import("2fa-auth-backup", entries).await?;
+        let ready = preview
+            .candidates
+            .iter()
+            .filter(|c| c.status == ImportStatus::Ready)
+            .map(|c| c.index);
+        let mut results = self.create_entries(entries, ready).await;
+        results.extend(
+            preview
+                .candidates
+                .into_iter()
+                .filter(|c| c.status != ImportStatus::Ready)
+                .map(|c| ImportResult {
+                    index: c.index,
+                    account: None,
+                    error: Some(format!("skipped: {}", c.reason.unwrap_or_default())),
+                }),
+        );
+        results.sort_by_key(|r| r.index);
+        Ok(results)
+    }
+
+    /// Deletes an account without keeping it in the trash, once the user
+    /// agreed to lose it for good after `delete` failed with `CANNOT_UNDO`.
+    pub async fn delete_for_good(&self, account_id: u32) -> Result<(), String> {
+        match self.backend.delete_account(account_id).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.is_not_found() => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// `export_one` for the trash, failing with `CANNOT_UNDO` when the
+    /// server has no export.
+    async fn keep_copy(&self, account_id: u32) -> Result<ExportedAccount, String> {
+        let unsupported = || format!("{CANNOT_UNDO}, so the delete couldn't be undone");
+        if self.require(|c| c.export, "exporting accounts").is_err() {
+            return Err(unsupported());
+        }
+        match self.backend.export_accounts(&[account_id]).await {
+            Ok(mut exported) => exported.pop().ok_or_else(|| "account not found".to_string()),
+            Err(e) if e.is_unsupported() => Err(unsupported()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn export_one(&self, account_id: u32) -> Result<ExportedAccount, String> {
+        self.require(|c| c.export, "exporting accounts")?;
+        self.backend
+            .export_accounts(&[account_id])
+            .await?
+            .pop()
+            .ok_or_else(|| "account not found".to_string())
+    }
+
+    fn tombstones(&self, accounts: Vec<(u32, ExportedAccount)>) -> Result<Vec<Tombstone>, String> {
+        let retention = self.storage.read_settings()?.trash_retention_hours;
+        let deleted_at = now();
+        Ok(accounts
+            .into_iter()
+            .map(|(account_id, account)| Tombstone {
+                account_id,
+                server_url: self.server_url.clone(),
+                deleted_at,
+                expires_at: deleted_at.saturating_add(retention.saturating_mul(3600)),
+                account,
+            })
+            .collect())
+    }
+}
+
+fn dedup(ids: &[u32]) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+}
@@ -0,0 +1,94 @@
+use super::{Backend, Storage};
+use crate::crypto::secret::SecretString;
+use crate::models::types::{Credentials, UserInfo};
+
+/// The stored server connection.
+pub struct CredentialService<S> {
+    storage: S,
+}
+
+impl<S: Storage> CredentialService<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// The stored credentials, or `None` if the app was never configured.
+    pub fn load(&self) -> Result<Option<Credentials>, String> {
+        self.storage.read_credentials()
+    }
+
+    pub fn save(&self, creds: &Credentials) -> Result<(), String> {
+        self.storage.write_credentials(creds)
+    }
+
+    /// Forgets the credentials, and the trash that is encrypted with the
+    /// same key.
+    pub fn clear(&self) -> Result<(), String> {
+        self.storage.clear_trash()?;
+        self.storage.clear_credentials()
+    }
+
+    /// Checks a token against a server before it is saved.
+    pub async fn verify(&self, backend: &impl Backend) -> Result<UserInfo, String> {
+        Ok(backend.verify_user().await?)
+    }
+
+    /// Confirms the user still knows the API token: it must match `stored`
+    /// and still be accepted by the server.
+    pub async fn reauthenticate(
+        &self,
+        stored: &Credentials,
+        api_token: &str,
+        backend: &impl Backend,
+    ) -> Result<(), String> {
+        if !constant_time_eq(stored.api_token.expose().as_bytes(), api_token.as_bytes()) {
+            return Err("token does not match".into());
+        }
+        backend.verify_user().await?;
+        Ok(())
+    }
+
+    /// Swaps in a new token for the same user on the same server.
+    /// `replacement` talks to the server with `api_token`, which must be
+    /// accepted and name the email stored with the credentials. Only for
+    /// credentials saved without one is `current`, using the stored token,
+    /// asked for it instead.
+    pub async fn replace_token(
+        &self,
+        stored: &Credentials,
+        api_token: SecretString,
+        current: &impl Backend,
+        replacement: &impl Backend,
+    ) -> Result<Credentials, String> {
+        let expected = match &stored.email {
+            Some(email) => Some(email.clone()),
+            None => {
+                current
+                    .verify_user()
+                    .await
+                    .map_err(|e| format!("current token: {e}"))?
+                    .email
+            }
+        };
+        let after = replacement
+            .verify_user()
+            .await
+            .map_err(|e| format!("new token: {e}"))?;
+        if expected.is_none() || expected != after.email {
+            return Err("the new token belongs to a different user".into());
+        }
+        let creds = Credentials {
+            server_url: stored.server_url.clone(),
+            api_token,
+            cert_pin: stored.cert_pin.clone(),
+            capabilities: stored.capabilities,
+            email: expected,
+        };
+        self.save(&creds)?;
+        Ok(creds)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,257 @@
+//! What the app does with accounts and credentials, independent of Tauri.
+//! The commands, the socket server and the `2fa` CLI all go through these
+//! services; they only differ in where the backends come from. Tests swap
+//! in a mock server and in-memory storage.
+
+mod accounts;
+mod credentials;
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::client::TwoFAuthClient;
+use crate::api::error::ApiError;
+use crate::models::types::*;
+use crate::store;
+use crate::trash::{self, Tombstone};
+
+pub use accounts::{AccountService, CANNOT_UNDO};
+pub use credentials::CredentialService;
+
+/// The 2FAuth API, as the services use it. `TwoFAuthClient` is the real one.
+pub trait Backend: Sync {
+    fn verify_user(&self) -> impl Future<Output = Result<UserInfo, ApiError>> + Send;
+    fn list_accounts(&self) -> impl Future<Output = Result<Vec<Account>, ApiError>> + Send;
+    fn get_otp(&self, account_id: u32) -> impl Future<Output = Result<OtpValue, ApiError>> + Send;
+    fn create_account(
+        &self,
+        payload: &CreateAccountPayload,
+    ) -> impl Future<Output = Result<Account, ApiError>> + Send;
+    fn preview_uri(
+        &self,
+        uri: &str,
+    ) -> impl Future<Output = Result<AccountPreview, ApiError>> + Send;
+    fn delete_account(&self, account_id: u32) -> impl Future<Output = Result<(), ApiError>> + Send;
+    fn delete_accounts(&self, ids: &[u32]) -> impl Future<Output = Result<(), ApiError>> + Send;
+    fn reorder_accounts(&self, ids: &[u32]) -> impl Future<Output = Result<(), ApiError>> + Send;
+    fn export_accounts(
+        &self,
+        ids: &[u32],
+    ) -> impl Future<Output = Result<Vec<ExportedAccount>, ApiError>> + Send;
+    fn assign_to_group(
+        &self,
+        group_id: u32,
+        ids: &[u32],
+    ) -> impl Future<Output = Result<(), ApiError>> + Send;
+    fn withdraw_from_groups(
+        &self,
+        ids: &[u32],
+    ) -> impl Future<Output = Result<(), ApiError>> + Send;
+    fn update_account(
+        &self,
+        account_id: u32,
+        account: &ExportedAccount,
+    ) -> impl Future<Output = Result<Account, ApiError>> + Send;
+    fn list_groups(&self) -> impl Future<Output = Result<Vec<Group>, ApiError>> + Send;
+    fn get_preferences(
+        &self,
+    ) -> impl Future<Output = Result<Vec<UserPreference>, ApiError>> + Send;
+    fn set_preference(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> impl Future<Output = Result<UserPreference, ApiError>> + Send;
+    fn upload_icon(
+        &self,
+        image_bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+    ) -> impl Future<Output = Result<IconFile, ApiError>> + Send;
+    fn fetch_official_icon(
+        &self,
+        service: &str,
+    ) -> impl Future<Output = Result<IconFile, ApiError>> + Send;
+    fn decode_qr(
+        &self,
+        image_bytes: Vec<u8>,
+    ) -> impl Future<Output = Result<String, ApiError>> + Send;
+}
+
+impl Backend for TwoFAuthClient<'_> {
+    async fn verify_user(&self) -> Result<UserInfo, ApiError> {
+        TwoFAuthClient::verify_user(self).await
+    }
+
+    async fn list_accounts(&self) -> Result<Vec<Account>, ApiError> {
+        TwoFAuthClient::list_accounts(self).await
+    }
+
+    async fn get_otp(&self, account_id: u32) -> Result<OtpValue, ApiError> {
+        TwoFAuthClient::get_otp(self, account_id).await
+    }
+
+    async fn create_account(&self, payload: &CreateAccountPayload) -> Result<Account, ApiError> {
+        TwoFAuthClient::create_account(self, payload).await
+    }
+
+    async fn preview_uri(&self, uri: &str) -> Result<AccountPreview, ApiError> {
+        TwoFAuthClient::preview_uri(self, uri).await
+    }
+
+    async fn delete_account(&self, account_id: u32) -> Result<(), ApiError> {
+        TwoFAuthClient::delete_account(self, account_id).await
+    }
+
+    async fn delete_accounts(&self, ids: &[u32]) -> Result<(), ApiError> {
+        TwoFAuthClient::delete_accounts(self, ids).await
+    }
+
+    async fn reorder_accounts(&self, ids: &[u32]) -> Result<(), ApiError> {
+        TwoFAuthClient::reorder_accounts(self, ids).await
+    }
+
+    async fn export_accounts(&self, ids: &[u32]) -> Result<Vec<ExportedAccount>, ApiError> {
+        TwoFAuthClient::export_accounts(self, ids).await
+    }
+
+    async fn assign_to_group(&self, group_id: u32, ids: &[u32]) -> Result<(), ApiError> {
+        TwoFAuthClient::assign_to_group(self, group_id, ids).await
+    }
+
+    async fn withdraw_from_groups(&self, ids: &[u32]) -> Result<(), ApiError> {
+        TwoFAuthClient::withdraw_from_groups(self, ids).await
+    }
+
+    async fn update_account(
+        &self,
+        account_id: u32,
+        account: &ExportedAccount,
+    ) -> Result<Account, ApiError> {
+        TwoFAuthClient::update_account(self, account_id, account).await
+    }
+
+    async fn list_groups(&self) -> Result<Vec<Group>, ApiError> {
+        TwoFAuthClient::list_groups(self).await
+    }
+
+    async fn get_preferences(&self) -> Result<Vec<UserPreference>, ApiError> {
+        TwoFAuthClient::get_preferences(self).await
+    }
+
+    async fn set_preference(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<UserPreference, ApiError> {
+        TwoFAuthClient::set_preference(self, key, value).await
+    }
+
+    async fn upload_icon(
+        &self,
+        image_bytes: Vec<u8>,
+        file_name: &str,
+        mime: &str,
+    ) -> Result<IconFile, ApiError> {
+        TwoFAuthClient::upload_icon(self, image_bytes, file_name, mime).await
+    }
+
+    async fn fetch_official_icon(&self, service: &str) -> Result<IconFile, ApiError> {
+        TwoFAuthClient::fetch_official_icon(self, service).await
+    }
+
+    async fn decode_qr(&self, image_bytes: Vec<u8>) -> Result<String, ApiError> {
+        TwoFAuthClient::decode_qr(self, image_bytes).await
+    }
+}
+
+/// The local files the services read and write. `FileStorage` is the real
+/// one.
+pub trait Storage: Send + Sync {
+    fn read_credentials(&self) -> Result<Option<Credentials>, String>;
+    fn write_credentials(&self, creds: &Credentials) -> Result<(), String>;
+    fn clear_credentials(&self) -> Result<(), String>;
+    fn read_settings(&self) -> Result<Settings, String>;
+
+    fn add_to_trash(&self, tombstones: Vec<Tombstone>, now: u64) -> Result<(), String>;
+    fn take_from_trash(
+        &self,
+        server_url: &str,
+        account_id: u32,
+        now: u64,
+    ) -> Result<Option<Tombstone>, String>;
+    fn discard_from_trash(
+        &self,
+        server_url: &str,
+        account_ids: &[u32],
+        now: u64,
+    ) -> Result<(), String>;
+    fn list_trash(&self, server_url: &str, now: u64) -> Result<Vec<TrashEntry>, String>;
+    fn clear_trash(&self) -> Result<(), String>;
+}
+
+/// The app data directory, shared by the app and the CLI.
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Storage for FileStorage {
+    fn read_credentials(&self) -> Result<Option<Credentials>, String> {
+        store::read_credentials(&self.dir)
+    }
+
+    fn write_credentials(&self, creds: &Credentials) -> Result<(), String> {
+        store::write_credentials(&self.dir, creds)
+    }
+
+    fn clear_credentials(&self) -> Result<(), String> {
+        store::clear_credentials(&self.dir)
+    }
+
+    fn read_settings(&self) -> Result<Settings, String> {
+        store::read_settings(&self.dir)
+    }
+
+    fn add_to_trash(&self, tombstones: Vec<Tombstone>, now: u64) -> Result<(), String> {
+        trash::add_all(&self.dir, tombstones, now)
+    }
+
+    fn take_from_trash(
+        &self,
+        server_url: &str,
+        account_id: u32,
+        now: u64,
+    ) -> Result<Option<Tombstone>, String> {
+        trash::take(&self.dir, server_url, account_id, now)
+    }
+
+    fn discard_from_trash(
+        &self,
+        server_url: &str,
+        account_ids: &[u32],
+        now: u64,
+    ) -> Result<(), String> {
+        trash::discard(&self.dir, server_url, account_ids, now)
+    }
+
+    fn list_trash(&self, server_url: &str, now: u64) -> Result<Vec<TrashEntry>, String> {
+        trash::list(&self.dir, server_url, now)
+    }
+
+    fn clear_trash(&self) -> Result<(), String> {
+        trash::clear(&self.dir)
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
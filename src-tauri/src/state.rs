@@ -1,16 +1,146 @@
-use std::sync::Mutex;
-use crate::models::types::Credentials;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::api::auth::AuthObserver;
+use crate::api::client::TwoFAuthClient;
+use crate::api::pinning::{self, PinObserver};
+use crate::api::retry::RequestPolicy;
+use crate::import::ImportEntry;
+use crate::models::types::{
+    Account, AutofillRequest, Credentials, ServerCapabilities, Settings, Usage,
+};
+use tokio::sync::oneshot;
+
+/// How long a successful `reauthenticate` unlocks secret-revealing commands.
+const REAUTH_WINDOW: Duration = Duration::from_secs(120);
+
+/// Certificate fingerprint seen by the last `verify_connection`, waiting to be
+/// pinned when the credentials for that server are saved.
+pub struct PendingPin {
+    pub server_url: String,
+    pub fingerprint: String,
+}
+
+/// What the last successful `verify_connection` found out: what the server
+/// can do and whose token it was. Saved with the credentials for that
+/// server.
+pub struct ProbedServer {
+    pub server_url: String,
+    pub capabilities: Option<ServerCapabilities>,
+    pub email: Option<String>,
+}
+
+/// An autofill request shown in the tray, and where to send the account the
+/// user picked (`None` to deny).
+pub struct PendingAutofill {
+    pub request: AutofillRequest,
+    pub reply: oneshot::Sender<Option<u32>>,
+}
 
 pub struct AppState {
-    pub credentials: Mutex<Option<Credentials>>,
-    pub http_client: reqwest::Client,
+    /// Shared rather than cloned, so the token exists once in memory.
+    pub credentials: Mutex<Option<Arc<Credentials>>>,
+    pub http_client: Mutex<reqwest::Client>,
+    pub pin_observer: Arc<PinObserver>,
+    /// Set once the server rejects the stored token; nothing is sent to it
+    /// until new credentials are saved.
+    pub auth: Arc<AuthObserver>,
+    pub pending_pin: Mutex<Option<PendingPin>>,
+    pub probed_server: Mutex<Option<ProbedServer>>,
+    pub settings: Mutex<Settings>,
+    pub pending_import: Mutex<Option<Vec<ImportEntry>>>,
+    pub reauthenticated_at: Mutex<Option<Instant>>,
+    pub pending_autofill: Mutex<HashMap<u64, PendingAutofill>>,
+    /// The last account list fetched, searched without a round trip; `None`
+    /// until the first fetch.
+    pub accounts: Mutex<Option<Vec<Account>>>,
+    /// Group names by id, fetched on first search after each refresh.
+    pub group_names: Mutex<Option<HashMap<u32, String>>>,
+    /// Usage by server URL, then account id.
+    pub usage: Mutex<HashMap<String, HashMap<u32, Usage>>>,
+    #[cfg(unix)]
+    pub ipc_server: Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        Self {
+    pub fn new() -> Result<Self, String> {
+        let pin_observer = Arc::new(PinObserver::default());
+        let http_client = pinning::build_client(None, pin_observer.clone())?;
+        Ok(Self {
             credentials: Mutex::new(None),
-            http_client: reqwest::Client::new(),
+            http_client: Mutex::new(http_client),
+            pin_observer,
+            auth: Arc::new(AuthObserver::default()),
+            pending_pin: Mutex::new(None),
+            probed_server: Mutex::new(None),
+            settings: Mutex::new(Settings::default()),
+            pending_import: Mutex::new(None),
+            reauthenticated_at: Mutex::new(None),
+            pending_autofill: Mutex::new(HashMap::new()),
+            accounts: Mutex::new(None),
+            group_names: Mutex::new(None),
+            usage: Mutex::new(HashMap::new()),
+            #[cfg(unix)]
+            ipc_server: Mutex::new(None),
+        })
+    }
+
+    pub fn http_client(&self) -> Result<reqwest::Client, String> {
+        Ok(self
+            .http_client
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?
+            .clone())
+    }
+
+    pub fn request_policy(&self) -> Result<RequestPolicy, String> {
+        let settings = self.settings.lock().map_err(|e| format!("lock: {e}"))?;
+        Ok(RequestPolicy::from(&*settings))
+    }
+
+    /// A client for the configured server over `http`, which comes from
+    /// `http_client`. Fails once the server has rejected the token.
+    pub fn client<'a>(&self, http: &'a reqwest::Client) -> Result<TwoFAuthClient<'a>, String> {
+        if self.auth.is_rejected() {
+            return Err("re-authentication required: the server rejected the API token".into());
+        }
+        let creds = self
+            .credentials
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?
+            .clone()
+            .ok_or_else(|| "not configured".to_string())?;
+        Ok(TwoFAuthClient::new(http, &creds.server_url, creds.api_token.expose())
+            .with_policy(self.request_policy()?)
+            .with_auth_observer(self.auth.clone()))
+    }
+
+    /// Drops the cached accounts and groups, which belong to the
+    /// credentials being replaced.
+    pub fn forget_accounts(&self) -> Result<(), String> {
+        *self.accounts.lock().map_err(|e| format!("lock: {e}"))? = None;
+        *self.group_names.lock().map_err(|e| format!("lock: {e}"))? = None;
+        Ok(())
+    }
+
+    /// Fails unless the user re-entered their credentials within the last
+    /// couple of minutes.
+    pub fn require_reauth(&self) -> Result<(), String> {
+        let at = *self
+            .reauthenticated_at
+            .lock()
+            .map_err(|e| format!("lock: {e}"))?;
+        match at {
+            Some(at) if at.elapsed() < REAUTH_WINDOW => Ok(()),
+            _ => Err("re-authentication required".into()),
         }
     }
+
+    /// Swaps in a client that enforces `pin`; connections made by the old
+    /// client are dropped with it.
+    pub fn apply_pin(&self, pin: Option<&str>) -> Result<(), String> {
+        let client = pinning::build_client(pin, self.pin_observer.clone())?;
+        *self.http_client.lock().map_err(|e| format!("lock: {e}"))? = client;
+        Ok(())
+    }
 }
@@ -0,0 +1,285 @@
+//! Files kept in the app data directory: the encrypted credentials, the
+//! keyfile when the key is kept there (see `keystore`) and the plain
+//! settings. Shared by the desktop app and the `2fa` command-line companion.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::crypto::envelope;
+use crate::crypto::secret::Zeroizing;
+use crate::keystore;
+use crate::models::types::{Credentials, KeySource, Settings, Usage};
+
+/// Must match `identifier` in tauri.conf.json; Tauri names the app data
+/// directory after it.
+pub const APP_IDENTIFIER: &str = "com.2faauth.desktop";
+
+/// The app data directory as Tauri resolves it, for callers that run
+/// without a Tauri app (the CLI).
+pub fn data_dir() -> Result<PathBuf, String> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| "app data dir: no data directory on this system".to_string())?
+        .join(APP_IDENTIFIER);
+    create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Creates `dir` if needed and makes it accessible to the owner only.
+pub fn create_private_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("create dir: {e}"))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("protect dir: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Makes the secret files already in `dir` readable by the owner only, for
+/// installs from before they were created that way.
+pub fn restrict_permissions(dir: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let files = [
+            credentials_path(dir),
+            key_path(dir),
+            dir.join("password.key"),
+            dir.join("trash.enc"),
+            dir.join("audit.log"),
+        ];
+        for path in files.iter().flat_map(|p| [p.clone(), backup_path(p)]) {
+            if path.exists() {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+                    .map_err(|e| format!("protect {}: {e}", path.display()))?;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// Replaces `path` with `bytes` so that a crash leaves either the old file
+/// or the new one, never a torn mix: the data goes to a temp file next to
+/// it, is flushed to disk and renamed over it. The file is readable by the
+/// owner only.
+///
+/// The temp file gets a name of its own and must not exist yet, so the app
+/// and the CLI writing the same file at once can't write into each other's
+/// temp file, and a link planted in its place isn't followed.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("write {}: not a file", path.display()))?;
+    let temp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let write = || -> std::io::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)?;
+        // The rename itself is only durable once the directory is synced.
+        #[cfg(unix)]
+        if let Some(parent) = path.parent() {
+            fs::File::open(parent)?.sync_all()?;
+        }
+        Ok(())
+    };
+    write().map_err(|e| {
+        if e.kind() != std::io::ErrorKind::AlreadyExists {
+            let _ = fs::remove_file(&temp);
+        }
+        format!("write {}: {e}", name.to_string_lossy())
+    })
+}
+
+/// The copy of a vault file kept in case it stops decrypting.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Writes a vault file, first keeping the file it replaces as the backup
+/// if that still decrypts, so the backup is always the last good copy and
+/// never a file that was only half written.
+pub fn write_vault(dir: &Path, path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Ok(current) = fs::read(path) {
+        if matches!(open(dir, &current), Ok(Some(_))) {
+            write_atomic(&backup_path(path), &current)?;
+        }
+    }
+    write_atomic(path, bytes)
+}
+
+/// Removes a vault file and its backup.
+pub fn remove_vault(path: &Path) -> Result<(), String> {
+    for path in [backup_path(path), path.to_path_buf()] {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("remove {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads and decrypts a vault file, or `None` if there is no key for it.
+/// If the file no longer decrypts but its backup does, the backup is put
+/// back. A file in the legacy format is rewritten in the current one.
+pub fn read_vault(dir: &Path, path: &Path) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+    let mut data = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let plaintext = match open(dir, &data) {
+        Ok(plaintext) => plaintext,
+        Err(e) => {
+            let backup = fs::read(backup_path(path)).map_err(|_| e.clone())?;
+            let Ok(plaintext) = open(dir, &backup) else {
+                return Err(e);
+            };
+            log::warn!("{}: {e}; restoring the backup", path.display());
+            write_atomic(path, &backup)?;
+            data = backup;
+            plaintext
+        }
+    };
+    let Some(plaintext) = plaintext else {
+        return Ok(None);
+    };
+    if envelope::parse(&data).is_ok_and(|e| e.is_legacy()) {
+        if let Err(e) = seal(dir, &plaintext).and_then(|sealed| write_vault(dir, path, &sealed)) {
+            log::warn!("{}: keeping legacy format: {e}", path.display());
+        }
+    }
+    Ok(Some(plaintext))
+}
+
+pub fn credentials_path(dir: &Path) -> PathBuf {
+    dir.join("credentials.enc")
+}
+
+pub fn key_path(dir: &Path) -> PathBuf {
+    dir.join("keyfile.key")
+}
+
+pub fn settings_path(dir: &Path) -> PathBuf {
+    dir.join("settings.json")
+}
+
+pub fn usage_path(dir: &Path) -> PathBuf {
+    dir.join("usage.json")
+}
+
+/// Where the app listens for local programs when IPC is enabled.
+pub fn socket_path(dir: &Path) -> PathBuf {
+    dir.join("ipc.sock")
+}
+
+/// Encrypts the contents of a vault file under the configured key, which
+/// is created on first use.
+pub fn seal(dir: &Path, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let keystore = keystore::current(dir)?;
+    envelope::seal(plaintext, keystore.load_or_create()?.expose(), keystore.source())
+}
+
+/// Decrypts a vault file, or `None` if there is no key for it.
+pub fn open(dir: &Path, data: &[u8]) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+    let keystore = keystore::current(dir)?;
+    let Some(key) = keystore.load()? else {
+        return Ok(None);
+    };
+    envelope::open(data, key.expose(), keystore.source()).map(|plaintext| Some(Zeroizing::new(plaintext)))
+}
+
+/// Reads the stored credentials, or `None` if the app was never configured.
+pub fn read_credentials(dir: &Path) -> Result<Option<Credentials>, String> {
+    let path = credentials_path(dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let Some(decrypted) = read_vault(dir, &path)? else {
+        return Ok(None);
+    };
+    let creds = serde_json::from_slice(&decrypted).map_err(|e| format!("parse: {e}"))?;
+    Ok(Some(creds))
+}
+
+pub fn write_credentials(dir: &Path, creds: &Credentials) -> Result<(), String> {
+    let json = Zeroizing::new(serde_json::to_vec(creds).map_err(|e| format!("serialize: {e}"))?);
+    let encrypted = seal(dir, &json)?;
+    write_vault(dir, &credentials_path(dir), &encrypted)
+}
+
+/// Removes the credentials and their key. The next setup starts over with a
+/// keyfile, since a master password would have nothing left to unlock.
+pub fn clear_credentials(dir: &Path) -> Result<(), String> {
+    remove_vault(&credentials_path(dir))?;
+    let mut settings = read_settings(dir)?;
+    keystore::open(dir, settings.key_source).clear()?;
+    if settings.key_source != KeySource::default() {
+        settings.key_source = KeySource::default();
+        write_settings(dir, &settings)?;
+    }
+    Ok(())
+}
+
+/// A missing file means defaults; unknown or missing fields fall back to
+/// their defaults too. A file that doesn't parse is logged and read as the
+/// defaults rather than stopping every caller, except for the key source,
+/// which the vault needs and is kept if it can still be found.
+pub fn read_settings(dir: &Path) -> Result<Settings, String> {
+    let path = settings_path(dir);
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+    let data = fs::read(&path).map_err(|e| format!("read settings: {e}"))?;
+    match serde_json::from_slice(&data) {
+        Ok(settings) => Ok(settings),
+        Err(e) => {
+            log::warn!("parse settings: {e}; using the default settings");
+            let key_source = serde_json::from_slice::<serde_json::Value>(&data)
+                .ok()
+                .and_then(|value| serde_json::from_value(value["key_source"].clone()).ok());
+            Ok(Settings {
+                key_source: key_source.unwrap_or_default(),
+                ..Settings::default()
+            })
+        }
+    }
+}
+
+pub fn write_settings(dir: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| format!("serialize: {e}"))?;
+    write_atomic(&settings_path(dir), &json)
+}
+
+/// Per-account usage counts by server URL, since account ids are only
+/// unique on one server. Unreadable data only costs ranking quality, so it
+/// is dropped rather than reported.
+pub fn read_usage(dir: &Path) -> HashMap<String, HashMap<u32, Usage>> {
+    fs::read(usage_path(dir))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_usage(
+    dir: &Path,
+    usage: &HashMap<String, HashMap<u32, Usage>>,
+) -> Result<(), String> {
+    let json = serde_json::to_vec(usage).map_err(|e| format!("serialize: {e}"))?;
+    write_atomic(&usage_path(dir), &json)
+}
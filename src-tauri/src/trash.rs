@@ -0,0 +1,129 @@
+//! Deleted accounts, secrets included, kept for a while so a deletion can
+//! be undone. The list is stored in `trash.enc`, encrypted with the same key
+//! as the credentials; expired entries are dropped whenever it is touched.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::secret::Zeroizing;
+use crate::models::types::{ExportedAccount, TrashEntry};
+use crate::store;
+
+/// Serialises read-modify-write cycles on the trash file.
+static TRASH: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    pub account_id: u32,
+    /// The server the account was deleted from; only that profile sees it.
+    pub server_url: String,
+    pub deleted_at: u64,
+    pub expires_at: u64,
+    pub account: ExportedAccount,
+}
+
+impl Tombstone {
+    pub fn entry(&self) -> TrashEntry {
+        TrashEntry {
+            account_id: self.account_id,
+            service: self.account.service.clone(),
+            account: self.account.account.clone(),
+            deleted_at: self.deleted_at,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+pub fn trash_path(dir: &Path) -> PathBuf {
+    dir.join("trash.enc")
+}
+
+fn read(dir: &Path) -> Result<Vec<Tombstone>, String> {
+    let path = trash_path(dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let decrypted = store::read_vault(dir, &path)?.ok_or("trash: the key is missing")?;
+    serde_json::from_slice(&decrypted).map_err(|e| format!("parse trash: {e}"))
+}
+
+fn write(dir: &Path, tombstones: &[Tombstone]) -> Result<(), String> {
+    let path = trash_path(dir);
+    if tombstones.is_empty() {
+        return store::remove_vault(&path);
+    }
+    let json = Zeroizing::new(serde_json::to_vec(tombstones).map_err(|e| format!("serialize: {e}"))?);
+    let encrypted = store::seal(dir, &json)?;
+    store::write_vault(dir, &path, &encrypted)
+}
+
+/// Runs `f` on the unexpired tombstones and saves what it leaves.
+fn update<T>(
+    dir: &Path,
+    now: u64,
+    f: impl FnOnce(&mut Vec<Tombstone>) -> T,
+) -> Result<T, String> {
+    let _guard = TRASH.lock().map_err(|e| format!("lock: {e}"))?;
+    let mut tombstones = read(dir)?;
+    let before = tombstones.len();
+    tombstones.retain(|t| t.expires_at > now);
+    let expired = before - tombstones.len();
+    let result = f(&mut tombstones);
+    write(dir, &tombstones)?;
+    if expired > 0 {
+        log::info!("trash: purged {expired} expired accounts");
+    }
+    Ok(result)
+}
+
+/// Adds tombstones, also when an undo failed and one has to be put back.
+pub fn add_all(dir: &Path, added: Vec<Tombstone>, now: u64) -> Result<(), String> {
+    update(dir, now, |tombstones| {
+        tombstones.retain(|t| {
+            !added
+                .iter()
+                .any(|a| a.account_id == t.account_id && a.server_url == t.server_url)
+        });
+        tombstones.extend(added);
+    })
+}
+
+pub fn list(dir: &Path, server_url: &str, now: u64) -> Result<Vec<TrashEntry>, String> {
+    update(dir, now, |tombstones| {
+        tombstones
+            .iter()
+            .filter(|t| t.server_url == server_url)
+            .map(Tombstone::entry)
+            .collect()
+    })
+}
+
+/// Removes and returns the tombstone, if it hasn't expired.
+pub fn take(dir: &Path, server_url: &str, account_id: u32, now: u64) -> Result<Option<Tombstone>, String> {
+    update(dir, now, |tombstones| {
+        let index = tombstones
+            .iter()
+            .position(|t| t.account_id == account_id && t.server_url == server_url)?;
+        Some(tombstones.remove(index))
+    })
+}
+
+/// Removes the tombstones of accounts whose deletion didn't go through.
+pub fn discard(dir: &Path, server_url: &str, account_ids: &[u32], now: u64) -> Result<(), String> {
+    update(dir, now, |tombstones| {
+        tombstones.retain(|t| !(t.server_url == server_url && account_ids.contains(&t.account_id)));
+    })
+}
+
+/// Drops every tombstone, for when the credentials and the key that
+/// encrypts the trash are removed.
+pub fn clear(dir: &Path) -> Result<(), String> {
+    let _guard = TRASH.lock().map_err(|e| format!("lock: {e}"))?;
+    write(dir, &[])
+}
+
+pub fn purge_expired(dir: &Path, now: u64) -> Result<(), String> {
+    update(dir, now, |_| ())
+}
@@ -1,14 +1,72 @@
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{Menu, MenuItem, PredefinedMenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    Manager, WebviewUrl, WebviewWindowBuilder,
+    AppHandle, Manager, WebviewUrl, WebviewWindowBuilder,
 };
 use tauri_plugin_positioner::{Position, WindowExt};
 
+use crate::commands::otp;
+use crate::search;
+use crate::state::AppState;
+
+/// Pinned accounts beyond this stay in the popup only.
+const MAX_MENU_ACCOUNTS: usize = 10;
+
+/// Pinned accounts (click to copy their code), then Settings and Quit.
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+    let menu = Menu::new(app)?;
+
+    let state = app.state::<AppState>();
+    let pinned = state
+        .settings
+        .lock()
+        .map(|s| s.pinned.clone())
+        .unwrap_or_default();
+    let accounts = state
+        .accounts
+        .lock()
+        .map(|a| a.clone().unwrap_or_default())
+        .unwrap_or_default();
+    let pinned: Vec<_> = pinned
+        .iter()
+        .filter_map(|id| accounts.iter().find(|a| a.id == *id))
+        .take(MAX_MENU_ACCOUNTS)
+        .collect();
+    for account in &pinned {
+        menu.append(&MenuItem::with_id(
+            app,
+            format!("copy:{}", account.id),
+            search::label(account),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    if !pinned.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+
+    menu.append(&MenuItem::with_id(app, "settings", "Settings", true, None::<&str>)?)?;
+    menu.append(&MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+/// Rebuilds the tray menu after the accounts or pins changed.
+pub fn refresh_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id("main-tray") else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("tray menu: {e}");
+            }
+        }
+        Err(e) => log::warn!("tray menu: {e}"),
+    }
+}
+
 pub fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
-    let settings_item = MenuItem::with_id(app, "settings", "Settings", true, None::<&str>)?;
-    let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-    let menu = Menu::with_items(app, &[&settings_item, &quit_item])?;
+    let menu = build_menu(app.handle())?;
 
     TrayIconBuilder::with_id("main-tray")
         .icon(app.default_window_icon().unwrap().clone())
@@ -23,7 +81,16 @@ pub fn setup_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             "quit" => app.exit(0),
-            _ => {}
+            id => {
+                if let Some(account_id) = id.strip_prefix("copy:").and_then(|id| id.parse().ok()) {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = otp::copy_code(&app, account_id).await {
+                            log::warn!("tray copy: {e}");
+                        }
+                    });
+                }
+            }
         })
         .on_tray_icon_event(|tray_handle, event| {
             tauri_plugin_positioner::on_tray_event(tray_handle.app_handle(), &event);
@@ -68,9 +135,15 @@ fn toggle_tray_popup(app: &tauri::AppHandle) {
         if popup.is_visible().unwrap_or(false) {
             let _ = popup.hide();
         } else {
-            let _ = popup.move_window(Position::TrayCenter);
-            let _ = popup.show();
-            let _ = popup.set_focus();
+            show_tray_popup(app);
         }
     }
 }
+
+pub fn show_tray_popup(app: &tauri::AppHandle) {
+    if let Some(popup) = app.get_webview_window("tray-popup") {
+        let _ = popup.move_window(Position::TrayCenter);
+        let _ = popup.show();
+        let _ = popup.set_focus();
+    }
+}
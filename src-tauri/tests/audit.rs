@@ -0,0 +1,80 @@
+//! The audit log: appends from several writers at once, and reading it back
+//! when a line is damaged.
+
+mod support;
+
+use std::fs;
+
+use app_lib::audit::{self, Record};
+use app_lib::models::types::*;
+use support::TempDir;
+
+fn copied(account_id: u32) -> Record<'static> {
+    Record {
+        action: AuditAction::CodeCopied,
+        account_id: Some(account_id),
+        profile: None,
+        source: AuditSource::Cli,
+        detail: None,
+    }
+}
+
+#[test]
+fn concurrent_appends_keep_one_chain() {
+    let dir = TempDir::new("concurrent");
+    std::thread::scope(|scope| {
+        for writer in 0..4 {
+            let dir = &dir.0;
+            scope.spawn(move || {
+                for n in 0..10 {
+                    audit::append(dir, copied(writer * 100 + n)).unwrap();
+                }
+            });
+        }
+    });
+
+    let verification = audit::verify(&dir.0).unwrap();
+    assert_eq!(verification.entries, 40);
+    assert_eq!(verification.broken_at, None);
+}
+
+#[test]
+fn a_damaged_line_is_skipped_and_located() {
+    let dir = TempDir::new("damaged");
+    for id in 1..=3 {
+        audit::append(&dir.0, copied(id)).unwrap();
+    }
+    let path = dir.0.join("audit.log");
+    let data = fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = data.lines().collect();
+    fs::write(&path, format!("{}\n{{not json\n{}\n", lines[0], lines[2])).unwrap();
+
+    let entries = audit::query(&dir.0, &AuditFilter::default()).unwrap();
+    let ids: Vec<_> = entries.iter().map(|e| e.account_id).collect();
+    assert_eq!(ids, [Some(3), Some(1)]);
+    assert_eq!(audit::verify(&dir.0).unwrap().broken_at, Some(1));
+}
+
+#[test]
+fn appends_carry_on_after_a_torn_last_line() {
+    let dir = TempDir::new("torn");
+    for id in 1..=3 {
+        audit::append(&dir.0, copied(id)).unwrap();
+    }
+    // A crash halfway through writing the third entry.
+    let path = dir.0.join("audit.log");
+    let data = fs::read_to_string(&path).unwrap();
+    let torn = data.len() - data.lines().last().unwrap().len() / 2 - 1;
+    fs::write(&path, &data[..torn]).unwrap();
+
+    let entry = audit::append(&dir.0, copied(4)).unwrap();
+    assert_eq!(entry.seq, 2);
+    let ids: Vec<_> = audit::query(&dir.0, &AuditFilter::default())
+        .unwrap()
+        .iter()
+        .map(|e| e.account_id)
+        .collect();
+    assert_eq!(ids, [Some(4), Some(2), Some(1)]);
+    let verification = audit::verify(&dir.0).unwrap();
+    assert_eq!(verification.broken_at, Some(2));
+}
@@ -0,0 +1,68 @@
+//! Which accounts a browser page is offered, including pages made to look
+//! like another site.
+
+use app_lib::autofill::{host_of, match_accounts};
+use app_lib::models::types::*;
+
+fn account(id: u32, service: &str) -> Account {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "service": service,
+        "account": "me",
+        "otp_type": "totp",
+        "digits": 6,
+    }))
+    .unwrap()
+}
+
+fn offered(url: &str, accounts: &[Account], patterns: &[UrlPattern]) -> Vec<u32> {
+    let host = host_of(url).unwrap();
+    match_accounts(accounts, &host, patterns)
+        .iter()
+        .map(|a| a.id)
+        .collect()
+}
+
+#[test]
+fn services_match_their_own_domain() {
+    let accounts = [
+        account(1, "GitHub"),
+        account(2, "Proton Mail"),
+        account(3, "bank.co.uk"),
+    ];
+    assert_eq!(offered("https://github.com/login", &accounts, &[]), [1]);
+    assert_eq!(offered("https://www.GitHub.com", &accounts, &[]), [1]);
+    assert_eq!(offered("https://account.protonmail.com", &accounts, &[]), [2]);
+    assert_eq!(offered("https://login.bank.co.uk", &accounts, &[]), [3]);
+    assert!(host_of("ftp://github.com").is_none());
+}
+
+#[test]
+fn lookalike_hosts_get_nothing() {
+    let accounts = [account(1, "GitHub"), account(2, "github.com")];
+    for url in [
+        "https://github.com.evil.io",
+        "https://github.attacker.net",
+        "https://login.github.evil.co.uk",
+        "https://notgithub.com",
+        "https://github-login.com",
+    ] {
+        assert!(offered(url, &accounts, &[]).is_empty(), "{url}");
+    }
+
+    // A public suffix as the service covers nothing.
+    let accounts = [account(3, "co.uk")];
+    assert!(offered("https://bank.co.uk", &accounts, &[]).is_empty());
+}
+
+#[test]
+fn patterns_take_precedence() {
+    let accounts = [account(1, "GitHub"), account(2, "Work SSO")];
+    let patterns = [UrlPattern {
+        account_id: 2,
+        pattern: "*.corp.example".into(),
+    }];
+    assert_eq!(offered("https://sso.corp.example", &accounts, &patterns), [2]);
+    assert!(offered("https://corp.example", &accounts, &patterns).is_empty());
+    assert_eq!(offered("https://github.com", &accounts, &patterns), [1]);
+}
@@ -0,0 +1,499 @@
+//! `TwoFAuthClient` against the mock server: every method's happy path, and
+//! how each kind of failure comes back.
+
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use app_lib::api::auth::AuthObserver;
+use app_lib::api::client::TwoFAuthClient;
+use app_lib::api::error::ApiError;
+use app_lib::api::retry::RequestPolicy;
+use app_lib::models::types::*;
+use support::{fast_policy, Fault, MockServer};
+
+fn status_of(e: &ApiError) -> Option<u16> {
+    match e {
+        ApiError::Status { status, .. } => Some(status.as_u16()),
+        _ => None,
+    }
+}
+
+fn fields(service: &str, account: &str) -> CreateAccountPayload {
+    CreateAccountPayload {
+        uri: None,
+        service: Some(service.into()),
+        account: Some(account.into()),
+        secret: Some("JBSWY3DPEHPK3PXP".into()),
+        otp_type: Some("totp".into()),
+        digits: Some(6),
+        period: Some(30),
+        counter: None,
+        algorithm: Some("sha1".into()),
+    }
+}
+
+fn uri(uri: &str) -> CreateAccountPayload {
+    CreateAccountPayload {
+        uri: Some(uri.into()),
+        service: None,
+        account: None,
+        secret: None,
+        otp_type: None,
+        digits: None,
+        period: None,
+        counter: None,
+        algorithm: None,
+    }
+}
+
+#[tokio::test]
+async fn verify_user_returns_the_profile() {
+    let server = MockServer::start().await;
+    let user = server.client().verify_user().await.unwrap();
+    assert_eq!(user.email.as_deref(), Some("test@example.com"));
+}
+
+#[tokio::test]
+async fn wrong_token_is_unauthorized() {
+    let server = MockServer::start().await;
+    let err = server
+        .client_with("wrong", fast_policy())
+        .verify_user()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApiError::Unauthorized { .. }));
+    assert!(err.to_string().contains("Unauthenticated."));
+    // 401 is final, not worth retrying.
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 1);
+}
+
+#[tokio::test]
+async fn a_revoked_token_is_reported_once() {
+    let server = MockServer::start().await;
+    let auth = Arc::new(AuthObserver::default());
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    auth.on_rejected(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    let client = server.client().with_auth_observer(auth.clone());
+
+    client.verify_user().await.unwrap();
+    assert!(!auth.is_rejected());
+    server.revoke_token(support::TOKEN);
+    assert!(client.list_accounts().await.is_err());
+    assert!(client.verify_user().await.is_err());
+    assert!(auth.is_rejected());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    auth.reset();
+    assert!(!auth.is_rejected());
+}
+
+#[tokio::test]
+async fn list_accounts_includes_codes() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    server.add_account("GitLab", "me");
+
+    let accounts = server.client().list_accounts().await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0].id, id);
+    assert!(accounts[0].otp.is_some());
+    assert_eq!(
+        server.requests(),
+        vec!["GET /api/v1/twofaccounts?withOtp=true".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_parse_error() {
+    let server = MockServer::start().await;
+    server.fail("GET", "/api/v1/twofaccounts", Fault::Malformed);
+    let err = server.client().list_accounts().await.unwrap_err();
+    assert!(matches!(err, ApiError::Parse(_)), "{err:?}");
+}
+
+#[tokio::test]
+async fn list_groups() {
+    let server = MockServer::start().await;
+    server.add_group("Work");
+    let groups = server.client().list_groups().await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name, "Work");
+}
+
+#[tokio::test]
+async fn get_otp_and_missing_account() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let client = server.client();
+
+    let otp = client.get_otp(id).await.unwrap();
+    assert_eq!(otp.password.len(), 6);
+
+    let err = client.get_otp(999).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(404));
+}
+
+#[tokio::test]
+async fn create_account_from_fields_and_uri() {
+    let server = MockServer::start().await;
+    let client = server.client();
+
+    let account = client.create_account(&fields("GitHub", "me")).await.unwrap();
+    assert_eq!(account.service.as_deref(), Some("GitHub"));
+
+    let account = client
+        .create_account(&uri("otpauth://totp/ACME:alice?secret=JBSWY3DPEHPK3PXP&issuer=ACME&digits=8"))
+        .await
+        .unwrap();
+    assert_eq!(account.service.as_deref(), Some("ACME"));
+    assert_eq!(account.account.as_deref(), Some("alice"));
+    assert_eq!(account.digits, 8);
+    assert_eq!(server.accounts().len(), 2);
+}
+
+#[tokio::test]
+async fn validation_errors_keep_the_server_message() {
+    let server = MockServer::start().await;
+    let mut payload = fields("GitHub", "me");
+    payload.secret = None;
+
+    let err = server.client().create_account(&payload).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(422));
+    assert!(err.to_string().contains("The secret field is required."), "{err}");
+}
+
+#[tokio::test]
+async fn post_is_never_retried() {
+    let server = MockServer::start().await;
+    server.fail_times("POST", "/api/v1/twofaccounts", Fault::Status(503, "Down"), 1);
+
+    let err = server.client().create_account(&fields("GitHub", "me")).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(503));
+    assert_eq!(server.request_count("POST", "/api/v1/twofaccounts"), 1);
+    assert!(server.accounts().is_empty());
+}
+
+#[tokio::test]
+async fn server_errors_are_not_retried() {
+    let server = MockServer::start().await;
+    server.fail("GET", "/api/v1/twofaccounts", Fault::Status(500, "Server Error"));
+
+    let err = server.client().list_accounts().await.unwrap_err();
+    assert_eq!(status_of(&err), Some(500));
+    assert!(err.to_string().contains("Server Error"));
+    assert_eq!(server.request_count("GET", "/api/v1/twofaccounts"), 1);
+}
+
+#[tokio::test]
+async fn gateway_errors_are_retried() {
+    let server = MockServer::start().await;
+    server.add_account("GitHub", "me");
+    server.fail_times("GET", "/api/v1/twofaccounts", Fault::Status(502, "Bad Gateway"), 2);
+
+    let accounts = server.client().list_accounts().await.unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(server.request_count("GET", "/api/v1/twofaccounts"), 3);
+}
+
+#[tokio::test]
+async fn retries_give_up_after_the_policy_limit() {
+    let server = MockServer::start().await;
+    server.fail("GET", "/api/v1/user", Fault::Status(503, "Down"));
+
+    let err = server.client().verify_user().await.unwrap_err();
+    assert_eq!(status_of(&err), Some(503));
+    assert_eq!(
+        server.request_count("GET", "/api/v1/user"),
+        fast_policy().max_retries as usize + 1
+    );
+}
+
+#[tokio::test]
+async fn short_rate_limits_are_waited_out() {
+    let server = MockServer::start().await;
+    server.fail_times("GET", "/api/v1/user", Fault::RateLimited(1), 1);
+
+    let started = Instant::now();
+    server.client().verify_user().await.unwrap();
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 2);
+}
+
+#[tokio::test]
+async fn long_rate_limits_are_reported() {
+    let server = MockServer::start().await;
+    server.fail("GET", "/api/v1/user", Fault::RateLimited(120));
+
+    let err = server.client().verify_user().await.unwrap_err();
+    match err {
+        ApiError::RateLimited(wait) => assert_eq!(wait, Duration::from_secs(120)),
+        other => panic!("expected a rate limit, got {other:?}"),
+    }
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 1);
+}
+
+#[tokio::test]
+async fn slow_responses_time_out() {
+    let server = MockServer::start().await;
+    server.fail("GET", "/api/v1/user", Fault::Delay(Duration::from_secs(2)));
+    let policy = RequestPolicy {
+        timeout: Duration::from_millis(200),
+        max_retries: 0,
+        ..fast_policy()
+    };
+
+    let err = server
+        .client_with(support::TOKEN, policy)
+        .verify_user()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ApiError::Timeout), "{err:?}");
+}
+
+#[tokio::test]
+async fn timed_out_reads_are_retried() {
+    let server = MockServer::start().await;
+    server.fail_times("GET", "/api/v1/user", Fault::Delay(Duration::from_secs(2)), 1);
+    let policy = RequestPolicy {
+        timeout: Duration::from_millis(300),
+        ..fast_policy()
+    };
+
+    server
+        .client_with(support::TOKEN, policy)
+        .verify_user()
+        .await
+        .unwrap();
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 2);
+}
+
+#[tokio::test]
+async fn preview_uri() {
+    let server = MockServer::start().await;
+    let client = server.client();
+
+    let preview = client
+        .preview_uri("otpauth://hotp/Bank:me?secret=JBSWY3DPEHPK3PXP&counter=4")
+        .await
+        .unwrap();
+    assert_eq!(preview.service.as_deref(), Some("Bank"));
+    assert_eq!(preview.otp_type, "hotp");
+
+    let err = client.preview_uri("https://example.com").await.unwrap_err();
+    assert_eq!(status_of(&err), Some(422));
+    // Previews create nothing.
+    assert!(server.accounts().is_empty());
+}
+
+#[tokio::test]
+async fn delete_account() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let client = server.client();
+
+    client.delete_account(id).await.unwrap();
+    assert!(server.accounts().is_empty());
+
+    let err = client.delete_account(id).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(404));
+}
+
+#[tokio::test]
+async fn batch_delete() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("GitLab", "me");
+    let c = server.add_account("Bank", "me");
+
+    server.client().delete_accounts(&[a, c]).await.unwrap();
+    let left: Vec<u32> = server.accounts().iter().map(|a| a.id).collect();
+    assert_eq!(left, vec![b]);
+    assert_eq!(
+        server.requests(),
+        vec![format!("DELETE /api/v1/twofaccounts?ids={a},{c}")]
+    );
+}
+
+#[tokio::test]
+async fn batch_delete_on_an_old_server_is_unsupported() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    server.without_batch_delete();
+
+    let err = server.client().delete_accounts(&[id]).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(405));
+    assert!(err.is_unsupported());
+    assert_eq!(server.accounts().len(), 1);
+}
+
+#[tokio::test]
+async fn capabilities_are_probed_per_endpoint() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let client = server.client();
+
+    let capabilities = client.probe_capabilities().await.unwrap();
+    assert_eq!(
+        capabilities,
+        ServerCapabilities {
+            groups: true,
+            export: true,
+            preferences: true,
+        }
+    );
+    // Probing must not touch the accounts.
+    assert_eq!(server.accounts().len(), 1);
+    assert_eq!(server.accounts()[0].id, id);
+    assert!(server.requests().iter().all(|r| r.starts_with("GET ")));
+
+    server.fail("GET", "/api/v1/groups", Fault::Status(404, "Not found"));
+    server.fail("GET", "/api/v1/user/preferences", Fault::Status(404, "Not found"));
+    let capabilities = client.probe_capabilities().await.unwrap();
+    assert!(!capabilities.groups);
+    assert!(!capabilities.preferences);
+
+    let err = client.list_groups().await.unwrap_err();
+    assert!(err.is_unsupported());
+    assert_eq!(err.to_string(), "server too old: groups needs a newer version of 2FAuth");
+}
+
+#[tokio::test]
+async fn preferences_are_read_and_written_by_name() {
+    let server = MockServer::start().await;
+    let client = server.client();
+
+    let entries = client.get_preferences().await.unwrap();
+    assert!(entries.contains(&UserPreference {
+        key: "showAccountsIcons".into(),
+        value: true.into(),
+    }));
+
+    let saved = client
+        .set_preference("showOtpAsDot", &true.into())
+        .await
+        .unwrap();
+    assert_eq!(saved.value, true);
+    assert_eq!(server.preference("showOtpAsDot"), Some(true.into()));
+
+    let err = client
+        .set_preference("noSuchThing", &true.into())
+        .await
+        .unwrap_err();
+    assert_eq!(status_of(&err), Some(400));
+}
+
+#[tokio::test]
+async fn reorder_accounts() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("GitLab", "me");
+
+    server.client().reorder_accounts(&[b, a]).await.unwrap();
+    let order: Vec<u32> = server.accounts().iter().map(|a| a.id).collect();
+    assert_eq!(order, vec![b, a]);
+
+    let err = server.client().reorder_accounts(&[]).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(422));
+}
+
+#[tokio::test]
+async fn export_accounts_includes_secrets() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    server.add_account("GitLab", "me");
+
+    let exported = server.client().export_accounts(&[a, 999]).await.unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].service.as_deref(), Some("GitHub"));
+    assert_eq!(exported[0].secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+}
+
+#[tokio::test]
+async fn update_account() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let client = server.client();
+
+    let mut account = client.export_accounts(&[id]).await.unwrap().remove(0);
+    account.icon = Some("github.png".into());
+    let updated = client.update_account(id, &account).await.unwrap();
+    assert_eq!(updated.id, id);
+    assert_eq!(updated.icon.as_deref(), Some("github.png"));
+
+    account.secret = None;
+    let err = client.update_account(id, &account).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(422));
+}
+
+#[tokio::test]
+async fn assign_and_withdraw_groups() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("GitLab", "me");
+    let group = server.add_group("Work");
+    let client = server.client();
+
+    client.assign_to_group(group, &[a, b]).await.unwrap();
+    assert!(server.accounts().iter().all(|a| a.group_id == Some(group)));
+
+    client.withdraw_from_groups(&[a]).await.unwrap();
+    let groups: Vec<_> = server.accounts().iter().map(|a| a.group_id).collect();
+    assert_eq!(groups, vec![None, Some(group)]);
+
+    let err = client.assign_to_group(42, &[a]).await.unwrap_err();
+    assert_eq!(status_of(&err), Some(404));
+}
+
+#[tokio::test]
+async fn icons() {
+    let server = MockServer::start().await;
+    let client = server.client();
+
+    let uploaded = client
+        .upload_icon(b"\x89PNG-data".to_vec(), "logo.png", "image/png")
+        .await
+        .unwrap();
+    let bytes = client.download_icon(&uploaded.filename).await.unwrap();
+    // The mock keeps the whole multipart body.
+    assert!(bytes.windows(9).any(|w| w == b"\x89PNG-data"));
+
+    let official = client.fetch_official_icon("GitHub").await.unwrap();
+    assert_eq!(official.filename, "github.png");
+    let err = client.fetch_official_icon("Nowhere").await.unwrap_err();
+    assert_eq!(status_of(&err), Some(404));
+
+    let err = client.download_icon("missing.png").await.unwrap_err();
+    assert_eq!(status_of(&err), Some(404));
+}
+
+#[tokio::test]
+async fn decode_qr() {
+    let server = MockServer::start().await;
+    server.set_qr_uri("otpauth://totp/Example:me?secret=JBSWY3DPEHPK3PXP");
+
+    let uri = server.client().decode_qr(b"\x89PNG".to_vec()).await.unwrap();
+    assert_eq!(uri, "otpauth://totp/Example:me?secret=JBSWY3DPEHPK3PXP");
+
+    server.fail("POST", "/api/v1/qrcode/decode", Fault::Status(422, "The qrcode is unreadable."));
+    let err = server.client().decode_qr(b"noise".to_vec()).await.unwrap_err();
+    assert!(err.to_string().contains("unreadable"));
+}
+
+#[tokio::test]
+async fn unreachable_server_is_a_request_error() {
+    let http = reqwest::Client::new();
+    // Nothing listens on the discard port.
+    let client = TwoFAuthClient::new(&http, "http://127.0.0.1:9", "t")
+        .with_policy(RequestPolicy {
+            max_retries: 1,
+            ..fast_policy()
+        });
+    let err = client.verify_user().await.unwrap_err();
+    assert!(matches!(err, ApiError::Request(_)), "{err:?}");
+}
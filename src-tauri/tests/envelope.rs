@@ -0,0 +1,149 @@
+//! The vault file format: round trips, tampering and the legacy layout,
+//! over generated inputs. `fuzz/` runs the parser under libFuzzer as well.
+
+use std::fs;
+
+use proptest::prelude::*;
+
+use app_lib::crypto::encryption;
+use app_lib::crypto::envelope::{self, Envelope, HEADER_LEN, MAGIC, VERSION};
+use app_lib::keystore::{KeyFile, KeyStore};
+use app_lib::models::types::{Credentials, KeySource};
+use app_lib::store;
+
+fn key_source() -> impl Strategy<Value = KeySource> {
+    prop_oneof![
+        Just(KeySource::Keyfile),
+        Just(KeySource::Keyring),
+        Just(KeySource::Password),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn sealed_data_opens_with_the_same_key(
+        plaintext in prop::collection::vec(any::<u8>(), 0..512),
+        key in any::<[u8; 32]>(),
+        source in key_source(),
+    ) {
+        let sealed = envelope::seal(&plaintext, &key, source).unwrap();
+        prop_assert!(sealed.starts_with(MAGIC));
+        let parsed = envelope::parse(&sealed).unwrap();
+        let matches = matches!(parsed, Envelope::V1 { key_source, .. } if key_source == source);
+        prop_assert!(matches);
+        prop_assert_eq!(envelope::open(&sealed, &key, source).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn any_flipped_bit_is_rejected(
+        plaintext in prop::collection::vec(any::<u8>(), 0..64),
+        key in any::<[u8; 32]>(),
+        source in key_source(),
+        bit in any::<prop::sample::Index>(),
+    ) {
+        let mut sealed = envelope::seal(&plaintext, &key, source).unwrap();
+        let bit = bit.index(sealed.len() * 8);
+        sealed[bit / 8] ^= 1 << (bit % 8);
+        prop_assert!(envelope::open(&sealed, &key, source).is_err());
+    }
+
+    #[test]
+    fn a_different_key_is_rejected(
+        plaintext in prop::collection::vec(any::<u8>(), 0..64),
+        key in any::<[u8; 32]>(),
+        other in any::<[u8; 32]>(),
+    ) {
+        prop_assume!(key != other);
+        let sealed = envelope::seal(&plaintext, &key, KeySource::Keyfile).unwrap();
+        prop_assert!(envelope::open(&sealed, &other, KeySource::Keyfile).is_err());
+    }
+
+    #[test]
+    fn legacy_data_still_opens(
+        plaintext in prop::collection::vec(any::<u8>(), 0..512),
+        key in any::<[u8; 32]>(),
+        source in key_source(),
+    ) {
+        let legacy = encryption::encrypt(&plaintext, &key).unwrap();
+        prop_assert_eq!(envelope::open(&legacy, &key, source).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn parse_never_panics(data in prop::collection::vec(any::<u8>(), 0..128)) {
+        let _ = envelope::parse(&data);
+    }
+
+    #[test]
+    fn parse_never_panics_on_headers(
+        version in any::<u8>(),
+        algorithm in any::<u8>(),
+        source in any::<u8>(),
+        rest in prop::collection::vec(any::<u8>(), 0..64),
+    ) {
+        let mut data = MAGIC.to_vec();
+        data.extend([version, algorithm, source]);
+        data.extend(rest);
+        if let Ok(Envelope::V1 { aad, nonce, ciphertext, .. }) = envelope::parse(&data) {
+            prop_assert_eq!(version, VERSION);
+            prop_assert_eq!(aad.len(), HEADER_LEN);
+            prop_assert_eq!(nonce.len(), 12);
+            prop_assert!(ciphertext.len() >= 16);
+        }
+    }
+}
+
+#[test]
+fn unknown_versions_and_ids_are_named() {
+    let key = [1u8; 32];
+    let sealed = envelope::seal(b"{}", &key, KeySource::Keyfile).unwrap();
+
+    let mut future = sealed.clone();
+    future[4] = 2;
+    assert_eq!(
+        envelope::parse(&future).unwrap_err(),
+        "unsupported vault version 2"
+    );
+    let mut algorithm = sealed.clone();
+    algorithm[5] = 9;
+    assert_eq!(
+        envelope::parse(&algorithm).unwrap_err(),
+        "unsupported vault algorithm 9"
+    );
+    assert!(envelope::parse(&sealed[..HEADER_LEN]).is_err());
+
+    let err = envelope::open(&sealed, &key, KeySource::Password).unwrap_err();
+    assert!(err.contains("Keyfile"), "{err}");
+}
+
+#[test]
+fn legacy_credentials_are_upgraded_when_read() {
+    let dir = std::env::temp_dir().join(format!("2fa-envelope-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let creds = Credentials {
+        server_url: "https://2fauth.example.com".into(),
+        api_token: "secret-token".into(),
+        cert_pin: None,
+        capabilities: None,
+        email: None,
+    };
+    // What older versions wrote: a keyfile and a bare nonce || ciphertext.
+    store::write_credentials(&dir, &creds).unwrap();
+    let key = KeyFile::new(&dir).load().unwrap().unwrap();
+    let legacy = encryption::encrypt(&serde_json::to_vec(&creds).unwrap(), key.expose()).unwrap();
+    fs::write(store::credentials_path(&dir), &legacy).unwrap();
+
+    let read = store::read_credentials(&dir).unwrap().unwrap();
+    assert_eq!(read.api_token.expose(), "secret-token");
+    let upgraded = fs::read(store::credentials_path(&dir)).unwrap();
+    assert!(upgraded.starts_with(MAGIC));
+    assert_eq!(
+        store::read_credentials(&dir)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,31 @@
+//! The `otpauth://` URIs written by an export.
+
+use app_lib::export::otpauth;
+use app_lib::models::types::ExportedAccount;
+
+fn account(service: &str, name: &str) -> ExportedAccount {
+    ExportedAccount {
+        service: Some(service.into()),
+        account: Some(name.into()),
+        icon: None,
+        otp_type: "totp".into(),
+        secret: Some("JBSWY3DPEHPK3PXP".into()),
+        digits: Some(6),
+        algorithm: Some("sha1".into()),
+        period: Some(30),
+        counter: None,
+    }
+}
+
+#[test]
+fn spaces_are_percent_encoded_and_plus_signs_kept() {
+    let uri = otpauth::to_uri(&account("Acme Cloud", "me+work@example.com")).unwrap();
+    assert_eq!(
+        uri,
+        "otpauth://totp/Acme%20Cloud:me+work@example.com\
+         ?secret=JBSWY3DPEHPK3PXP&issuer=Acme%20Cloud&algorithm=SHA1&digits=6&period=30"
+    );
+
+    let uri = otpauth::to_uri(&account("C++ & Co", "me")).unwrap();
+    assert!(uri.contains("&issuer=C%2B%2B%20%26%20Co&"), "{uri}");
+}
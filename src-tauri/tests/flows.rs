@@ -0,0 +1,338 @@
+//! The multi-step flows the commands and the CLI run, end to end against the
+//! mock server.
+
+mod support;
+
+use app_lib::backup::{self, BackupPayload};
+use app_lib::import;
+use app_lib::models::types::*;
+use app_lib::search::{self, Match};
+use app_lib::service::{AccountService, FileStorage};
+use support::{Fault, MockServer, TempDir};
+
+const AEGIS: &str = r#"{
+    "version": 1,
+    "header": { "slots": null, "params": null },
+    "db": {
+        "version": 2,
+        "entries": [
+            { "type": "totp", "name": "me", "issuer": "GitHub",
+              "info": { "secret": "JBSWY3DPEHPK3PXP", "algo": "SHA1", "digits": 6, "period": 30 } },
+            { "type": "totp", "name": "alice", "issuer": "ACME",
+              "info": { "secret": "KRSXG5CTMVRXEZLU", "algo": "SHA256", "digits": 8, "period": 60 } },
+            { "type": "motp", "name": "old", "issuer": "Legacy",
+              "info": { "secret": "abcd", "pin": "1234" } }
+        ]
+    }
+}"#;
+
+/// An Aegis vault encrypted with the password "correct horse", with scrypt
+/// turned down to n = 16 so the test doesn't spend a second deriving it.
+const AEGIS_ENCRYPTED: &str = r#"{
+    "version": 1,
+    "header": {
+        "slots": [{
+            "type": 1, "uuid": "00000000-0000-0000-0000-000000000000",
+            "key": "8443450deade13ef4507d7adbfd8ff284623496b92b7d3b8282bb5ef2a00bf92",
+            "key_params": { "nonce": "010101010101010101010101", "tag": "709cc3fd90c1a7e1104ec4e138c02f3d" },
+            "n": 16, "r": 8, "p": 1,
+            "salt": "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+        }],
+        "params": { "nonce": "020202020202020202020202", "tag": "2be43492a22e977cdc0ffea8f6b0a03d" }
+    },
+    "db": "icdwZ66PPuzDiKkD5sTWkbtseKD4i1eKfLFtP837Sh/zcuV5YIv0c73Ow9r7mQl1PV+iZLNJOw8dJSoQKhX2HKi+nSJNLDEAnPlGWxCXanSieFz0Ow4NzM+J3/VInWF7TMEXPnIs2ybUCDi4qzjkTa82g+TiKnR6yppdE3HAIbaxMWTXEUtXJxiECbH4eTZG3Q=="
+}"#;
+
+/// Previews the file, then creates every `Ready` candidate, as the import
+/// command and the CLI do.
+async fn run_import(
+    server: &MockServer,
+    data: &[u8],
+    password: Option<&str>,
+) -> (ImportPreview, Vec<ImportResult>) {
+    let importer = import::detect(data).unwrap();
+    let entries = importer.parse(data, password).unwrap();
+    // One directory per server, as tests run side by side.
+    let port = server.url.rsplit(':').next().unwrap();
+    let dir = TempDir::new(&format!("import-{port}"));
+    let storage = FileStorage::new(dir.0.clone());
+    let service = AccountService::new(server.client(), storage, &server.url);
+
+    let preview = service.preview_import(importer.name(), &entries).await.unwrap();
+    let ready: Vec<usize> = preview
+        .candidates
+        .iter()
+        .filter(|c| c.status == ImportStatus::Ready)
+        .map(|c| c.index)
+        .collect();
+    let results = service.create_entries(&entries, ready).await;
+    (preview, results)
+}
+
+#[tokio::test]
+async fn import_skips_duplicates_and_unsupported_entries() {
+    let server = MockServer::start().await;
+    server.add_account("github", "ME");
+
+    let (preview, results) = run_import(&server, AEGIS.as_bytes(), None).await;
+    let statuses: Vec<_> = preview.candidates.iter().map(|c| c.status).collect();
+    assert_eq!(
+        statuses,
+        vec![ImportStatus::Duplicate, ImportStatus::Ready, ImportStatus::Unsupported]
+    );
+    assert_eq!(results.len(), 1);
+    assert!(results[0].error.is_none());
+
+    let created = server.accounts().pop().unwrap();
+    assert_eq!(created.service.as_deref(), Some("ACME"));
+    assert_eq!(created.algorithm, "sha256");
+    assert_eq!(created.digits, 8);
+    assert_eq!(created.period, Some(60));
+}
+
+#[tokio::test]
+async fn import_reports_per_entry_failures() {
+    let server = MockServer::start().await;
+    server.fail_times("POST", "/api/v1/twofaccounts", Fault::Status(500, "Server Error"), 1);
+
+    let (_, results) = run_import(&server, AEGIS.as_bytes(), None).await;
+    assert_eq!(results.len(), 2);
+    assert!(results[0].error.as_deref().unwrap().contains("500"));
+    assert!(results[1].error.is_none());
+    assert_eq!(server.accounts().len(), 1);
+}
+
+#[tokio::test]
+async fn an_encrypted_aegis_vault_opens_only_with_its_password() {
+    let server = MockServer::start().await;
+    let data = AEGIS_ENCRYPTED.as_bytes();
+    let importer = import::detect(data).unwrap();
+    assert_eq!(importer.name(), "aegis");
+    assert!(importer.parse(data, None).unwrap_err().contains("password is required"));
+    assert!(importer
+        .parse(data, Some("wrong password"))
+        .unwrap_err()
+        .contains("wrong password"));
+
+    let (preview, results) = run_import(&server, data, Some("correct horse")).await;
+    assert_eq!(preview.candidates.len(), 1);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].error.is_none());
+    let created = server.accounts().pop().unwrap();
+    assert_eq!(created.service.as_deref(), Some("GitHub"));
+    assert_eq!(created.secret, "JBSWY3DPEHPK3PXP");
+}
+
+#[test]
+fn unknown_files_are_not_taken_for_andotp_backups() {
+    let body: Vec<u8> = (0..60).map(|i| (i * 37 % 256) as u8 | 0x80).collect();
+    // 150 000 PBKDF2 iterations, as andOTP writes them.
+    let encrypted = [&[0u8, 2, 0x49, 0xf0][..], &body].concat();
+    assert_eq!(import::detect(&encrypted).unwrap().name(), "andotp");
+
+    let png = [&b"\x89PNG\r\n\x1a\n"[..], &body].concat();
+    let zip = [&b"PK\x03\x04"[..], &body].concat();
+    for data in [
+        &png[..],
+        &zip,
+        &body,
+        b"\xff\xfe",
+        b"[]",
+        b"name,secret\nGitHub,JBSWY3DPEHPK3PXP\n",
+    ] {
+        assert!(import::detect(data).is_err(), "{data:?}");
+    }
+}
+
+#[test]
+fn twofas_backups_are_read_entry_by_entry() {
+    let backup = r#"{
+        "schemaVersion": 4,
+        "services": [
+            { "name": "GitHub", "secret": "jbsw y3dp ehpk 3pxp",
+              "otp": { "account": "me", "issuer": "GitHub", "digits": 6, "period": 30,
+                       "algorithm": "SHA1", "tokenType": "TOTP" } },
+            { "name": "Bank", "secret": "KRSXG5CTMVRXEZLU",
+              "otp": { "account": "alice", "tokenType": "HOTP", "counter": 7, "period": 30 } },
+            { "name": "Yandex", "secret": "KRSXG5CTMVRXEZLU",
+              "otp": { "account": "bob", "tokenType": "YAOTP" } },
+            { "name": "Broken", "otp": { "account": "carol" } }
+        ]
+    }"#;
+    let importer = import::detect(backup.as_bytes()).unwrap();
+    assert_eq!(importer.name(), "2fas");
+    let entries = importer.parse(backup.as_bytes(), None).unwrap();
+    assert_eq!(entries.len(), 4);
+
+    let github = entries[0].payload.as_ref().unwrap();
+    assert_eq!(github.service.as_deref(), Some("GitHub"));
+    assert_eq!(github.account.as_deref(), Some("me"));
+    assert_eq!(github.secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+    assert_eq!(github.otp_type.as_deref(), Some("totp"));
+    assert_eq!(github.algorithm.as_deref(), Some("sha1"));
+
+    // No issuer: the service name stands in. HOTP keeps its counter only.
+    let bank = entries[1].payload.as_ref().unwrap();
+    assert_eq!(bank.service.as_deref(), Some("Bank"));
+    assert_eq!(bank.otp_type.as_deref(), Some("hotp"));
+    assert_eq!(bank.counter, Some(7));
+    assert_eq!(bank.period, None);
+
+    assert!(entries[2].payload.as_ref().unwrap_err().contains("not supported"));
+    assert_eq!(entries[3].service.as_deref(), Some("Broken"));
+    assert!(entries[3].payload.as_ref().unwrap_err().contains("malformed"));
+
+    let encrypted = r#"{ "schemaVersion": 4, "servicesEncrypted": "AAAA" }"#;
+    assert!(importer.parse(encrypted.as_bytes(), None).is_err());
+}
+
+#[test]
+fn bitwarden_exports_yield_only_items_with_a_totp() {
+    let export = r#"{
+        "encrypted": false,
+        "items": [
+            { "name": "GitHub", "login": { "username": "me", "totp": "JBSWY3DPEHPK3PXP" } },
+            { "name": "ACME", "login": { "username": "alice",
+              "totp": "otpauth://totp/ACME:alice?secret=KRSXG5CTMVRXEZLU&issuer=ACME" } },
+            { "name": "Steam", "login": { "username": "bob", "totp": "steam://KRSXG5CTMVRXEZLU" } },
+            { "name": "No 2FA", "login": { "username": "carol", "password": "hunter2" } },
+            { "name": "Blank", "login": { "username": "dave", "totp": "  " } },
+            { "name": "Card", "type": 3, "card": {} }
+        ]
+    }"#;
+    let importer = import::detect(export.as_bytes()).unwrap();
+    assert_eq!(importer.name(), "bitwarden");
+    let entries = importer.parse(export.as_bytes(), None).unwrap();
+    assert_eq!(entries.len(), 3);
+
+    let github = entries[0].payload.as_ref().unwrap();
+    assert_eq!(github.service.as_deref(), Some("GitHub"));
+    assert_eq!(github.account.as_deref(), Some("me"));
+    assert_eq!(github.secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+    assert_eq!(github.otp_type.as_deref(), Some("totp"));
+
+    // A full URI goes to the server as it is.
+    assert_eq!(entries[1].service.as_deref(), Some("ACME"));
+    let acme = entries[1].payload.as_ref().unwrap();
+    assert!(acme.uri.as_deref().unwrap().starts_with("otpauth://totp/ACME"));
+    assert_eq!(acme.secret, None);
+
+    let steam = entries[2].payload.as_ref().unwrap();
+    assert_eq!(steam.otp_type.as_deref(), Some("steamtotp"));
+    assert_eq!(steam.secret.as_deref(), Some("KRSXG5CTMVRXEZLU"));
+
+    let encrypted = r#"{ "encrypted": true, "items": [] }"#;
+    assert!(importer.parse(encrypted.as_bytes(), None).is_err());
+}
+
+#[test]
+fn an_aegis_vault_asking_for_absurd_scrypt_parameters_is_refused() {
+    // n = 2^30 with r = 8 would need 1 TiB.
+    let vault = r#"{
+        "version": 1,
+        "header": {
+            "slots": [{
+                "type": 1, "key": "00", "key_params": { "nonce": "00", "tag": "00" },
+                "n": 1073741824, "r": 8, "p": 1, "salt": "00"
+            }],
+            "params": { "nonce": "00", "tag": "00" }
+        },
+        "db": "AAAA"
+    }"#;
+    let importer = import::detect(vault.as_bytes()).unwrap();
+    assert!(importer.parse(vault.as_bytes(), Some("password")).is_err());
+}
+
+#[tokio::test]
+async fn backup_restores_onto_another_server() {
+    let source = MockServer::start().await;
+    let a = source.add_account("GitHub", "me");
+    let b = source.add_account("GitLab", "me");
+
+    let accounts = source.client().export_accounts(&[a, b]).await.unwrap();
+    let sealed = backup::seal(
+        &BackupPayload {
+            created_at: 1_700_000_000,
+            server_url: source.url.clone(),
+            accounts,
+        },
+        "correct horse",
+    )
+    .unwrap();
+    assert!(backup::open(&sealed, "wrong password").is_err());
+
+    let target = MockServer::start().await;
+    target.add_account("GitLab", "me");
+    let payload = backup::open(&sealed, "correct horse").unwrap();
+    let entries = backup::entries(payload);
+    let existing = target.client().list_accounts().await.unwrap();
+    let preview = import::preview("2FA Auth backup", &entries, &existing);
+    assert_eq!(preview.candidates[0].status, ImportStatus::Ready);
+    assert_eq!(preview.candidates[1].status, ImportStatus::Duplicate);
+
+    let payload = entries[0].payload.as_ref().unwrap();
+    target.client().create_account(payload).await.unwrap();
+    let restored = target.accounts().pop().unwrap();
+    assert_eq!(restored.service.as_deref(), Some("GitHub"));
+    assert_eq!(restored.secret, "JBSWY3DPEHPK3PXP");
+}
+
+#[test]
+fn a_backup_asking_for_an_absurd_key_derivation_is_refused() {
+    let payload = BackupPayload {
+        created_at: 1_700_000_000,
+        server_url: "https://2fauth.example.com".into(),
+        accounts: Vec::new(),
+    };
+    assert!(backup::seal(&payload, "short").is_err());
+    let sealed = backup::seal(&payload, "correct horse").unwrap();
+
+    // Iterations, then parallelism, set to u32::MAX.
+    for offset in [13, 17] {
+        let mut tampered = sealed.clone();
+        tampered[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = backup::open(&tampered, "correct horse").unwrap_err();
+        assert!(err.contains("too large"), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn deleted_account_can_be_recreated_from_its_export() {
+    // What undo_delete does with the copy it kept in the trash.
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let client = server.client();
+
+    let kept = client.export_accounts(&[id]).await.unwrap().remove(0);
+    client.delete_account(id).await.unwrap();
+    assert!(server.accounts().is_empty());
+
+    let payload = backup::entry(kept).payload.unwrap();
+    let account = client.create_account(&payload).await.unwrap();
+    assert_ne!(account.id, id);
+    assert_eq!(server.accounts()[0].secret, "JBSWY3DPEHPK3PXP");
+}
+
+#[tokio::test]
+async fn get_by_query_resolves_then_fetches_the_code() {
+    // The CLI's `get` and the socket's `get` with a query.
+    let server = MockServer::start().await;
+    server.add_account("GitHub", "work");
+    server.add_account("GitHub", "personal");
+    let gitlab = server.add_account("GitLab", "me");
+    let client = server.client();
+    let accounts = client.list_accounts().await.unwrap();
+
+    let Match::One(account) = search::resolve(&accounts, "gitlab") else {
+        panic!("expected a single match");
+    };
+    assert_eq!(account.id, gitlab);
+    let otp = client.get_otp(account.id).await.unwrap();
+    assert_eq!(Some(otp.password), account.otp.as_ref().map(|o| o.password.clone()));
+
+    let ambiguous = search::resolve(&accounts, "github");
+    assert!(matches!(ambiguous, Match::Many(_)));
+    assert!(search::describe_failure("github", &ambiguous).contains("work"));
+    let none = search::resolve(&accounts, "bank");
+    assert!(matches!(none, Match::None));
+}
@@ -0,0 +1,311 @@
+//! Key stores and moving the vault between them, on a scratch data
+//! directory.
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+
+use app_lib::crypto::secret::Secret;
+use app_lib::keystore::{self, password, Key, KeyFile, KeyStore, MasterPassword, OsKeyring};
+use app_lib::models::types::*;
+use app_lib::store;
+use app_lib::trash;
+use support::{creds, tombstone, TempDir};
+
+const PASSWORD: &str = "correct horse battery";
+
+fn key_source(dir: &TempDir) -> KeySource {
+    store::read_settings(&dir.0).unwrap().key_source
+}
+
+#[test]
+fn keyfile_keeps_the_key_it_created() {
+    let dir = TempDir::new("keyfile");
+    let store = KeyFile::new(&dir.0);
+    assert!(store.load().unwrap().is_none());
+    let key = store.load_or_create().unwrap();
+    assert_eq!(store.load().unwrap(), Some(key));
+    store.clear().unwrap();
+    assert!(store.load().unwrap().is_none());
+}
+
+#[test]
+fn master_password_opens_only_with_the_password() {
+    let dir = TempDir::new("password");
+    let key = Secret::new([7u8; 32]);
+    assert!(MasterPassword::with_password(&dir.0, "short")
+        .save(&key)
+        .is_err());
+    MasterPassword::with_password(&dir.0, PASSWORD)
+        .save(&key)
+        .unwrap();
+
+    let err = MasterPassword::with_password(&dir.0, "wrong password")
+        .load()
+        .unwrap_err();
+    assert!(err.contains("wrong master password"));
+    assert_eq!(
+        MasterPassword::with_password(&dir.0, PASSWORD)
+            .load()
+            .unwrap(),
+        Some(key.clone())
+    );
+    // Saving and loading with the password unlocked it for this process.
+    assert_eq!(MasterPassword::new(&dir.0).load().unwrap(), Some(key));
+
+    MasterPassword::new(&dir.0).clear().unwrap();
+    assert!(!password::is_locked(&dir.0).unwrap());
+    assert!(MasterPassword::new(&dir.0).load().unwrap().is_none());
+}
+
+#[test]
+fn migration_carries_the_vault_across_stores() {
+    let dir = TempDir::new("migrate");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+    let old = fs::read(store::credentials_path(&dir.0)).unwrap();
+
+    let target = MasterPassword::with_password(&dir.0, PASSWORD);
+    keystore::migrate(&dir.0, &target).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Password);
+    assert!(!store::key_path(&dir.0).exists());
+    assert_ne!(fs::read(store::credentials_path(&dir.0)).unwrap(), old);
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+    assert_eq!(
+        trash::list(&dir.0, &creds("secret-token").server_url, 0).unwrap().len(),
+        1
+    );
+
+    keystore::migrate(&dir.0, &KeyFile::new(&dir.0)).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Keyfile);
+    assert!(!dir.0.join("password.key").exists());
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+    assert_eq!(
+        trash::list(&dir.0, &creds("secret-token").server_url, 0).unwrap().len(),
+        1
+    );
+}
+
+#[test]
+fn failed_migration_leaves_the_vault_alone() {
+    let dir = TempDir::new("failed");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    let old = fs::read(store::credentials_path(&dir.0)).unwrap();
+
+    let target = MasterPassword::with_password(&dir.0, "short");
+    assert!(keystore::migrate(&dir.0, &target).is_err());
+    assert_eq!(key_source(&dir), KeySource::Keyfile);
+    assert_eq!(fs::read(store::credentials_path(&dir.0)).unwrap(), old);
+    assert!(!dir.0.join("credentials.enc.new").exists());
+    assert!(store::read_credentials(&dir.0).unwrap().is_some());
+}
+
+/// Dies while storing the new key, like a crash after the re-encrypted
+/// files were put in place.
+struct CrashingStore;
+
+impl KeyStore for CrashingStore {
+    fn source(&self) -> KeySource {
+        KeySource::Keyfile
+    }
+
+    fn load(&self) -> Result<Option<Key>, String> {
+        Ok(None)
+    }
+
+    fn save(&self, _key: &Key) -> Result<(), String> {
+        panic!("crashed");
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[test]
+fn an_interrupted_rotation_falls_back_to_the_old_vault() {
+    let dir = TempDir::new("crash");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+    let old = fs::read(store::credentials_path(&dir.0)).unwrap();
+
+    let crashed = std::panic::catch_unwind(|| keystore::migrate(&dir.0, &CrashingStore));
+    assert!(crashed.is_err());
+    assert_ne!(fs::read(store::credentials_path(&dir.0)).unwrap(), old);
+
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+    assert_eq!(fs::read(store::credentials_path(&dir.0)).unwrap(), old);
+    assert_eq!(
+        trash::list(&dir.0, &creds("secret-token").server_url, 0).unwrap().len(),
+        1
+    );
+}
+
+/// Stores the key with the master password, then leaves no way to write
+/// the settings, like a full disk right after the key was saved.
+struct SettingsUnwritable(MasterPassword, PathBuf);
+
+impl KeyStore for SettingsUnwritable {
+    fn source(&self) -> KeySource {
+        KeySource::Password
+    }
+
+    fn load(&self) -> Result<Option<Key>, String> {
+        self.0.load()
+    }
+
+    fn save(&self, key: &Key) -> Result<(), String> {
+        self.0.save(key)?;
+        let settings = store::settings_path(&self.1);
+        let _ = fs::remove_file(&settings);
+        fs::create_dir_all(settings.join("blocked")).map_err(|e| e.to_string())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        self.0.clear()
+    }
+}
+
+#[test]
+fn backups_keep_the_old_key_until_the_settings_switch() {
+    let dir = TempDir::new("settings");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    let old = fs::read(store::credentials_path(&dir.0)).unwrap();
+
+    let target = SettingsUnwritable(
+        MasterPassword::with_password(&dir.0, PASSWORD),
+        dir.0.clone(),
+    );
+    assert!(keystore::migrate(&dir.0, &target).is_err());
+    fs::remove_dir_all(store::settings_path(&dir.0)).unwrap();
+
+    assert_eq!(key_source(&dir), KeySource::Keyfile);
+    assert_eq!(fs::read(store::credentials_path(&dir.0)).unwrap(), old);
+    assert_eq!(
+        fs::read(store::backup_path(&store::credentials_path(&dir.0))).unwrap(),
+        old
+    );
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+}
+
+#[test]
+fn a_key_file_asking_for_too_much_work_is_refused() {
+    let dir = TempDir::new("costly");
+    MasterPassword::with_password(&dir.0, PASSWORD)
+        .save(&Secret::new([7u8; 32]))
+        .unwrap();
+    let path = dir.0.join("password.key");
+    let mut wrapped: serde_json::Value =
+        serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    wrapped["m_cost"] = (4u32 * 1024 * 1024).into();
+    fs::write(&path, serde_json::to_vec(&wrapped).unwrap()).unwrap();
+
+    let err = MasterPassword::with_password(&dir.0, PASSWORD)
+        .load()
+        .unwrap_err();
+    assert!(err.contains("too large"));
+}
+
+#[test]
+fn clearing_credentials_goes_back_to_the_keyfile() {
+    let dir = TempDir::new("clear");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    keystore::migrate(&dir.0, &MasterPassword::with_password(&dir.0, PASSWORD)).unwrap();
+
+    store::clear_credentials(&dir.0).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Keyfile);
+    assert!(!dir.0.join("password.key").exists());
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    assert!(store::key_path(&dir.0).exists());
+}
+
+/// Needs a Secret Service, e.g.
+/// `dbus-run-session -- sh -c 'echo | gnome-keyring-daemon --unlock && cargo test -- --ignored'`.
+#[test]
+#[ignore]
+fn os_keyring_round_trip() {
+    let dir = TempDir::new("keyring");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    keystore::migrate(&dir.0, &OsKeyring::new(&dir.0)).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Keyring);
+    assert!(!store::key_path(&dir.0).exists());
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+
+    store::clear_credentials(&dir.0).unwrap();
+    assert!(OsKeyring::new(&dir.0).load().unwrap().is_none());
+}
+
+#[test]
+fn rotation_replaces_the_key_in_place() {
+    let dir = TempDir::new("rotate");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+    let old_key = KeyFile::new(&dir.0).load().unwrap();
+
+    keystore::rotate(&dir.0, None).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Keyfile);
+    assert_ne!(KeyFile::new(&dir.0).load().unwrap(), old_key);
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+    assert_eq!(
+        trash::list(&dir.0, &creds("secret-token").server_url, 0).unwrap().len(),
+        1
+    );
+
+    keystore::migrate(&dir.0, &MasterPassword::with_password(&dir.0, PASSWORD)).unwrap();
+    assert!(keystore::rotate(&dir.0, None).is_err());
+    let err = keystore::rotate(&dir.0, Some("wrong password")).unwrap_err();
+    assert!(err.contains("wrong master password"));
+    keystore::rotate(&dir.0, Some(PASSWORD)).unwrap();
+    assert_eq!(key_source(&dir), KeySource::Password);
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "secret-token"
+    );
+}
@@ -0,0 +1,129 @@
+//! The pinning verifier: what it records and when it refuses a certificate.
+
+use std::sync::Arc;
+
+use app_lib::api::pinning::{self, PinObserver};
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, SignatureScheme};
+
+/// Self-signed P-256 certificates for `a.example` and `b.example`.
+const CERT_A: &str = "MIIBfjCCASWgAwIBAgIUayK/mfHtW4hBBYYx3FVNHCc0KkMwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJYS5leGFtcGxlMCAXDTI2MTAxOTA4MTM0OVoYDzIxMjYwOTI1MDgxMzQ5WjAUMRIwEAYDVQQDDAlhLmV4YW1wbGUwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQstv+9hsTXaXMvNzMOAYi3KiesyXKQU/GexTEYhG8YOUSTjOPxgRDvxC38eNnYJSAD8lOD0IsHmyUlEYqMa5cbo1MwUTAdBgNVHQ4EFgQU+KvNHY3i3u/mVlEFUjUAwg7gRKYwHwYDVR0jBBgwFoAU+KvNHY3i3u/mVlEFUjUAwg7gRKYwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBEAiACfNebpYcROqQ/Yzoqgb7LMgYRWvWY75QS/Kc0RJ6hiwIgHwyDarOp0GBJNOfcwnxlfxIquZKlJgEKLge1UioUsGk=";
+const CERT_B: &str = "MIIBfzCCASWgAwIBAgIUXzT0kN20UUlEBnbaYqmik1C7BeYwCgYIKoZIzj0EAwIwFDESMBAGA1UEAwwJYi5leGFtcGxlMCAXDTI2MTAxOTA4MTM0OVoYDzIxMjYwOTI1MDgxMzQ5WjAUMRIwEAYDVQQDDAliLmV4YW1wbGUwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAT51UJNXM5Yh5ImBmzlIcEaE40MwttzHxCbrO9TypwStCYDqYwK259Knv5QXErDaapRALu0xyiRV+mXah81tPgyo1MwUTAdBgNVHQ4EFgQU8C0JrH7uWl2JaoDSMqekjSaZ5lgwHwYDVR0jBBgwFoAU8C0JrH7uWl2JaoDSMqekjSaZ5lgwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAvPCYG8N5ptx99T6ak5rR46C5DPPXfsIDDMM+KXKYJHYCIGMZzIFEx1ZFdbsDEXzJdo/Xw9QJD6gSVZu4dWNyUNvH";
+
+/// Stands in for the platform verifier: trusts every chain, or none.
+#[derive(Debug)]
+struct Chain {
+    trusted: bool,
+}
+
+impl ServerCertVerifier for Chain {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.trusted {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::UnknownIssuer,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![SignatureScheme::ECDSA_NISTP256_SHA256]
+    }
+}
+
+fn der(b64: &str) -> Vec<u8> {
+    B64.decode(b64).unwrap()
+}
+
+fn fingerprint(b64: &str) -> String {
+    pinning::spki_fingerprint(&der(b64)).unwrap()
+}
+
+/// Runs one handshake's certificate check against `cert`.
+fn verify(
+    trusted: bool,
+    pin: Option<&str>,
+    observer: &Arc<PinObserver>,
+    cert: &str,
+) -> Result<ServerCertVerified, rustls::Error> {
+    let verifier = pinning::verifier(Arc::new(Chain { trusted }), pin, observer.clone());
+    let name = ServerName::try_from("a.example").unwrap();
+    verifier.verify_server_cert(
+        &CertificateDer::from(der(cert)),
+        &[],
+        &name,
+        &[],
+        UnixTime::now(),
+    )
+}
+
+#[test]
+fn first_use_records_the_certificate_to_pin() {
+    let observer = Arc::new(PinObserver::default());
+
+    verify(true, None, &observer, CERT_A).unwrap();
+
+    assert_eq!(observer.last_seen(), Some(fingerprint(CERT_A)));
+}
+
+#[test]
+fn the_pinned_certificate_is_accepted() {
+    let observer = Arc::new(PinObserver::default());
+    let pin = fingerprint(CERT_A);
+
+    verify(true, Some(&pin), &observer, CERT_A).unwrap();
+
+    assert_eq!(observer.last_seen(), Some(pin));
+}
+
+#[test]
+fn another_certificate_is_a_pin_mismatch_and_is_offered_for_approval() {
+    let observer = Arc::new(PinObserver::default());
+    let pin = fingerprint(CERT_A);
+
+    let err = verify(true, Some(&pin), &observer, CERT_B).unwrap_err();
+
+    assert!(pinning::is_pin_mismatch(&err));
+    assert_eq!(observer.last_seen(), Some(fingerprint(CERT_B)));
+}
+
+#[test]
+fn an_untrusted_chain_is_refused_and_never_offered() {
+    let observer = Arc::new(PinObserver::default());
+
+    let err = verify(false, None, &observer, CERT_B).unwrap_err();
+    assert!(!pinning::is_pin_mismatch(&err));
+    // Not even with a pin that would reject it anyway.
+    let pin = fingerprint(CERT_A);
+    verify(false, Some(&pin), &observer, CERT_B).unwrap_err();
+
+    assert_eq!(observer.last_seen(), None);
+}
@@ -0,0 +1,121 @@
+//! Ranking accounts for the search box: fuzzy matching, usage and pins.
+
+use std::collections::HashMap;
+
+use app_lib::models::types::*;
+use app_lib::search::{fuzzy_score, rank};
+
+const NOW: u64 = 1_700_000_000;
+const DAY: u64 = 24 * 3600;
+
+fn account(id: u32, service: &str, name: &str) -> Account {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "service": service,
+        "account": name,
+        "otp_type": "totp",
+        "digits": 6,
+    }))
+    .unwrap()
+}
+
+fn used(count: u32, days_ago: u64) -> Usage {
+    Usage {
+        count,
+        last_used: NOW - days_ago * DAY,
+    }
+}
+
+fn ids(accounts: &[Account], usage: &HashMap<u32, Usage>, pinned: &[u32], query: &str) -> Vec<u32> {
+    rank(accounts, &HashMap::new(), usage, pinned, query, NOW, 10)
+        .iter()
+        .map(|a| a.id)
+        .collect()
+}
+
+#[test]
+fn word_starts_score_above_letters_inside_words() {
+    assert_eq!(fuzzy_score("GitHub", "github"), 1.0);
+    assert!(fuzzy_score("GitHub", "git") > fuzzy_score("Digital", "git"));
+    // "h" starts the camel-case hump in GitHub, but is mid-word in Lightning.
+    assert!(fuzzy_score("GitHub", "gh") > fuzzy_score("Lightning", "gh"));
+    assert!(fuzzy_score("GitHub", "gh") < 1.0);
+    assert_eq!(fuzzy_score("GitHub", "xyz"), 0.0);
+    assert_eq!(fuzzy_score("Git", "github"), 0.0);
+    assert_eq!(fuzzy_score("GitHub", "  "), 0.0);
+}
+
+#[test]
+fn a_prefix_match_ranks_above_a_subsequence() {
+    let accounts = [
+        account(1, "Lightning", "me"),
+        account(2, "Digital Ocean", "me"),
+        account(3, "GitHub", "me"),
+        account(4, "Bank", "me"),
+    ];
+    assert_eq!(ids(&accounts, &HashMap::new(), &[], "git"), [3, 2]);
+    assert_eq!(ids(&accounts, &HashMap::new(), &[], "gh"), [3, 1]);
+}
+
+#[test]
+fn recent_use_breaks_ties() {
+    let accounts = [
+        account(1, "GitHub", "personal"),
+        account(2, "GitHub", "work"),
+        account(3, "GitHub", "old"),
+    ];
+    // Equal matches fall back to the label.
+    assert_eq!(ids(&accounts, &HashMap::new(), &[], "github"), [3, 1, 2]);
+
+    let usage = HashMap::from([(2, used(5, 0)), (3, used(5, 60))]);
+    assert_eq!(ids(&accounts, &usage, &[], "github"), [2, 3, 1]);
+}
+
+#[test]
+fn use_lifts_but_does_not_outweigh_a_much_better_match() {
+    let accounts = [account(1, "GitHub", "me"), account(2, "Digital", "me")];
+    let usage = HashMap::from([(2, used(3, 0))]);
+    assert_eq!(ids(&accounts, &usage, &[], "git"), [1, 2]);
+}
+
+#[test]
+fn pinned_accounts_come_first() {
+    let accounts = [
+        account(1, "GitHub", "me"),
+        account(2, "Digital", "me"),
+        account(3, "Bank", "me"),
+    ];
+    let usage = HashMap::from([(1, used(50, 0))]);
+    assert_eq!(ids(&accounts, &usage, &[2], "git"), [2, 1]);
+    // Pinning doesn't make an account match.
+    assert_eq!(ids(&accounts, &usage, &[3], "git"), [1, 2]);
+}
+
+#[test]
+fn an_empty_query_ranks_every_account_by_use() {
+    let accounts = [
+        account(1, "GitHub", "me"),
+        account(2, "ACME", "me"),
+        account(3, "Bank", "me"),
+    ];
+    let usage = HashMap::from([(3, used(2, 0)), (1, used(2, 30))]);
+    assert_eq!(ids(&accounts, &usage, &[], ""), [3, 1, 2]);
+    assert_eq!(ids(&accounts, &usage, &[2], " "), [2, 3, 1]);
+}
+
+#[test]
+fn group_names_match_too() {
+    let mut work = account(1, "Slack", "me");
+    work.group_id = Some(7);
+    let accounts = [work, account(2, "Workday", "me")];
+    let groups = HashMap::from([(7, "Work".to_string())]);
+
+    let ranked: Vec<u32> = rank(&accounts, &groups, &HashMap::new(), &[], "work", NOW, 10)
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    // The whole group name still beats a service that only starts with it.
+    assert_eq!(ranked, [1, 2]);
+    let limited = rank(&accounts, &groups, &HashMap::new(), &[], "work", NOW, 1);
+    assert_eq!(limited.len(), 1);
+}
@@ -0,0 +1,598 @@
+//! The account and credential services against the mock server, with
+//! storage kept in memory.
+
+mod support;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use app_lib::api::client::TwoFAuthClient;
+use app_lib::api::retry::RequestPolicy;
+use app_lib::backup;
+use app_lib::models::types::*;
+use app_lib::service::{AccountService, CredentialService, Storage, CANNOT_UNDO};
+use app_lib::trash::Tombstone;
+use support::{fast_policy, Fault, MockServer, TOKEN};
+
+#[derive(Clone, Default)]
+struct MemoryStorage {
+    creds: Arc<Mutex<Option<Credentials>>>,
+    settings: Arc<Mutex<Settings>>,
+    trash: Arc<Mutex<Vec<Tombstone>>>,
+}
+
+impl MemoryStorage {
+    fn trash_ids(&self) -> Vec<u32> {
+        self.trash
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|t| t.account_id)
+            .collect()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read_credentials(&self) -> Result<Option<Credentials>, String> {
+        Ok(self.creds.lock().unwrap().clone())
+    }
+
+    fn write_credentials(&self, creds: &Credentials) -> Result<(), String> {
+        *self.creds.lock().unwrap() = Some(creds.clone());
+        Ok(())
+    }
+
+    fn clear_credentials(&self) -> Result<(), String> {
+        *self.creds.lock().unwrap() = None;
+        Ok(())
+    }
+
+    fn read_settings(&self) -> Result<Settings, String> {
+        Ok(self.settings.lock().unwrap().clone())
+    }
+
+    fn add_to_trash(&self, tombstones: Vec<Tombstone>, _now: u64) -> Result<(), String> {
+        self.trash.lock().unwrap().extend(tombstones);
+        Ok(())
+    }
+
+    fn take_from_trash(
+        &self,
+        server_url: &str,
+        account_id: u32,
+        _now: u64,
+    ) -> Result<Option<Tombstone>, String> {
+        let mut trash = self.trash.lock().unwrap();
+        let index = trash
+            .iter()
+            .position(|t| t.server_url == server_url && t.account_id == account_id);
+        Ok(index.map(|i| trash.remove(i)))
+    }
+
+    fn discard_from_trash(
+        &self,
+        server_url: &str,
+        account_ids: &[u32],
+        _now: u64,
+    ) -> Result<(), String> {
+        self.trash
+            .lock()
+            .unwrap()
+            .retain(|t| t.server_url != server_url || !account_ids.contains(&t.account_id));
+        Ok(())
+    }
+
+    fn list_trash(&self, server_url: &str, _now: u64) -> Result<Vec<TrashEntry>, String> {
+        Ok(self
+            .trash
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.server_url == server_url)
+            .map(Tombstone::entry)
+            .collect())
+    }
+
+    fn clear_trash(&self) -> Result<(), String> {
+        self.trash.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+fn services(
+    server: &MockServer,
+) -> (
+    AccountService<TwoFAuthClient<'_>, MemoryStorage>,
+    MemoryStorage,
+) {
+    let storage = MemoryStorage::default();
+    let service = AccountService::new(server.client(), storage.clone(), &server.url);
+    (service, storage)
+}
+
+#[tokio::test]
+async fn delete_keeps_the_account_in_the_trash_until_undone() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let (service, storage) = services(&server);
+
+    service.delete(id).await.unwrap();
+    assert!(server.accounts().is_empty());
+    let trash = service.trash().unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].service.as_deref(), Some("GitHub"));
+
+    let restored = service.undo_delete(id).await.unwrap();
+    assert_ne!(restored.id, id);
+    assert_eq!(restored.service.as_deref(), Some("GitHub"));
+    assert!(storage.trash_ids().is_empty());
+    assert!(service.undo_delete(id).await.is_err());
+}
+
+#[tokio::test]
+async fn a_huge_retention_keeps_deleted_accounts_for_good() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let (service, storage) = services(&server);
+    storage.settings.lock().unwrap().trash_retention_hours = u64::MAX;
+
+    service.delete(id).await.unwrap();
+    assert_eq!(storage.trash.lock().unwrap()[0].expires_at, u64::MAX);
+}
+
+#[tokio::test]
+async fn failed_delete_leaves_the_trash_empty() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    server.fail(
+        "DELETE",
+        &format!("/api/v1/twofaccounts/{id}"),
+        Fault::Status(403, "Forbidden"),
+    );
+    let (service, storage) = services(&server);
+
+    assert!(service.delete(id).await.is_err());
+    assert_eq!(server.accounts().len(), 1);
+    assert!(storage.trash_ids().is_empty());
+}
+
+#[tokio::test]
+async fn a_delete_the_server_may_have_applied_stays_in_the_trash() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    // Every attempt times out, but the server still applies each one.
+    server.fail(
+        "DELETE",
+        &format!("/api/v1/twofaccounts/{a}"),
+        Fault::Delay(Duration::from_millis(300)),
+    );
+    let policy = RequestPolicy {
+        timeout: Duration::from_millis(200),
+        max_retries: 1,
+        ..fast_policy()
+    };
+    let storage = MemoryStorage::default();
+    let service =
+        AccountService::new(server.client_with(TOKEN, policy), storage.clone(), &server.url);
+
+    assert!(service.delete(a).await.is_err());
+    assert_eq!(storage.trash_ids(), [a]);
+
+    // A delete that finds the account already gone went through.
+    server.fail(
+        "DELETE",
+        &format!("/api/v1/twofaccounts/{b}"),
+        Fault::Status(404, "Not found"),
+    );
+    service.delete(b).await.unwrap();
+    assert_eq!(storage.trash_ids(), [a, b]);
+}
+
+#[tokio::test]
+async fn delete_of_a_missing_account_deletes_nothing() {
+    let server = MockServer::start().await;
+    let (service, storage) = services(&server);
+
+    assert!(service.delete(42).await.is_err());
+    assert!(storage.trash_ids().is_empty());
+    assert_eq!(server.request_count("DELETE", "/api/v1/twofaccounts/42"), 0);
+}
+
+#[tokio::test]
+async fn delete_many_reports_each_account() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    let (service, storage) = services(&server);
+
+    let results = service.delete_many(&[a, b, a, 99]).await.unwrap();
+    let ids: Vec<u32> = results.iter().map(|r| r.account_id).collect();
+    assert_eq!(ids, [a, b, 99]);
+    assert!(results[0].error.is_none());
+    assert!(results[1].error.is_none());
+    assert!(results[2].error.is_some());
+    assert!(server.accounts().is_empty());
+    assert_eq!(server.request_count("DELETE", "/api/v1/twofaccounts"), 1);
+
+    let mut trashed = storage.trash_ids();
+    trashed.sort();
+    assert_eq!(trashed, [a, b]);
+}
+
+#[tokio::test]
+async fn delete_many_falls_back_to_single_deletes_on_old_servers() {
+    let server = MockServer::start().await;
+    server.without_batch_delete();
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    let c = server.add_account("Bank", "me");
+    server.fail(
+        "DELETE",
+        &format!("/api/v1/twofaccounts/{b}"),
+        Fault::Status(403, "Forbidden"),
+    );
+    server.fail(
+        "DELETE",
+        &format!("/api/v1/twofaccounts/{c}"),
+        Fault::Status(500, "Server Error"),
+    );
+    let (service, storage) = services(&server);
+
+    let results = service.delete_many(&[a, b, c]).await.unwrap();
+    assert!(results[0].error.is_none());
+    assert!(results[1].error.is_some());
+    assert!(results[2].error.is_some());
+    let left: Vec<u32> = server.accounts().iter().map(|s| s.id).collect();
+    assert_eq!(left, [b, c]);
+    // A server error may have come after the delete, so that one stays.
+    let mut trashed = storage.trash_ids();
+    trashed.sort();
+    assert_eq!(trashed, [a, c]);
+}
+
+#[tokio::test]
+async fn unsupported_features_fail_before_any_request() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    let (service, _) = services(&server);
+    let service = service.with_capabilities(Some(ServerCapabilities {
+        groups: false,
+        export: true,
+        preferences: false,
+    }));
+
+    let err = service.move_to_group(&[a, b], Some(1)).await.unwrap_err();
+    assert!(err.contains("server too old: groups"), "{err}");
+    assert!(service.groups().await.is_err());
+    assert!(server.requests().is_empty());
+
+    let service = service.with_capabilities(Some(ServerCapabilities::default()));
+    let err = service.export(&[a, b]).await.unwrap_err();
+    assert!(err.contains("server too old: exporting accounts"), "{err}");
+    assert!(service.set_icon(a, None).await.is_err());
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn without_export_delete_asks_before_losing_the_account() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    let (service, storage) = services(&server);
+    let service = service.with_capabilities(Some(ServerCapabilities {
+        groups: true,
+        export: false,
+        preferences: true,
+    }));
+
+    let err = service.delete(a).await.unwrap_err();
+    assert!(err.starts_with(CANNOT_UNDO), "{err}");
+    let results = service.delete_many(&[a, b]).await.unwrap();
+    assert!(results
+        .iter()
+        .all(|r| r.error.as_deref().unwrap().starts_with(CANNOT_UNDO)));
+    assert_eq!(server.accounts().len(), 2);
+    assert!(storage.trash_ids().is_empty());
+
+    service.delete_for_good(a).await.unwrap();
+    assert_eq!(server.accounts().len(), 1);
+    assert!(storage.trash_ids().is_empty());
+
+    // Found out from the server rather than the probe.
+    let (service, _) = services(&server);
+    server.fail(
+        "GET",
+        "/api/v1/twofaccounts/export",
+        Fault::Status(404, "Not found"),
+    );
+    let err = service.delete(b).await.unwrap_err();
+    assert!(err.starts_with(CANNOT_UNDO), "{err}");
+    assert_eq!(server.accounts().len(), 1);
+}
+
+#[tokio::test]
+async fn icons_are_uploaded_fetched_and_set() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let (service, _) = services(&server);
+
+    let uploaded = service
+        .upload_icon(b"\x89PNG".to_vec(), "logo.png", "image/png")
+        .await
+        .unwrap();
+    assert_eq!(uploaded, "uploaded1.png");
+    assert_eq!(
+        service.fetch_official_icon("GitHub").await.unwrap().as_deref(),
+        Some("github.png")
+    );
+    assert_eq!(service.fetch_official_icon("Nowhere").await.unwrap(), None);
+
+    let account = service.set_icon(id, Some(uploaded.clone())).await.unwrap();
+    assert_eq!(account.icon.as_deref(), Some(uploaded.as_str()));
+    assert_eq!(server.accounts()[0].secret, "JBSWY3DPEHPK3PXP");
+    let account = service.set_icon(id, None).await.unwrap();
+    assert_eq!(account.icon, None);
+}
+
+#[tokio::test]
+async fn preferences_sync_both_ways() {
+    let server = MockServer::start().await;
+    let (service, _) = services(&server);
+
+    let local = UserPreferences::default();
+    server.set_preference("showOtpAsDot", true.into());
+    server.set_preference("displayMode", "grid".into());
+    let pulled = service.pull_preferences(local).await.unwrap().unwrap();
+    assert!(pulled.show_otp_as_dot);
+    assert_eq!(
+        UserPreferences {
+            show_otp_as_dot: false,
+            ..pulled
+        },
+        local
+    );
+
+    let wanted = UserPreferences {
+        close_otp_on_copy: true,
+        ..pulled
+    };
+    service.push_preferences(&pulled, &wanted).await.unwrap();
+    assert_eq!(server.preference("closeOtpOnCopy"), Some(true.into()));
+    // Only what changed is sent.
+    let puts = server
+        .requests()
+        .iter()
+        .filter(|r| r.starts_with("PUT "))
+        .count();
+    assert_eq!(puts, 1);
+
+    // Without the endpoint the preferences stay local.
+    let service = service.with_capabilities(Some(ServerCapabilities::default()));
+    assert_eq!(service.pull_preferences(local).await.unwrap(), None);
+    service.push_preferences(&wanted, &local).await.unwrap();
+    assert_eq!(server.preference("closeOtpOnCopy"), Some(true.into()));
+}
+
+#[tokio::test]
+async fn a_timed_out_batch_delete_is_checked_against_the_server() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    server.fail(
+        "DELETE",
+        "/api/v1/twofaccounts",
+        Fault::Delay(Duration::from_millis(300)),
+    );
+    let policy = RequestPolicy {
+        timeout: Duration::from_millis(200),
+        max_retries: 1,
+        ..fast_policy()
+    };
+    let storage = MemoryStorage::default();
+    let service =
+        AccountService::new(server.client_with(TOKEN, policy), storage.clone(), &server.url);
+
+    let results = service.delete_many(&[a, b]).await.unwrap();
+    assert!(results.iter().all(|r| r.error.is_none()), "{results:?}");
+    assert!(server.accounts().is_empty());
+    let mut trashed = storage.trash_ids();
+    trashed.sort();
+    assert_eq!(trashed, [a, b]);
+}
+
+#[tokio::test]
+async fn move_to_group_and_back_out() {
+    let server = MockServer::start().await;
+    let a = server.add_account("GitHub", "me");
+    let b = server.add_account("ACME", "alice");
+    let group = server.add_group("Work");
+    let (service, _) = services(&server);
+
+    let results = service
+        .move_to_group(&[a, b, b], Some(group))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|r| r.error.is_none()));
+    assert!(server.accounts().iter().all(|s| s.group_id == Some(group)));
+
+    service.move_to_group(&[a], None).await.unwrap();
+    let groups: Vec<Option<u32>> = server.accounts().iter().map(|s| s.group_id).collect();
+    assert_eq!(groups, [None, Some(group)]);
+}
+
+#[tokio::test]
+async fn restore_skips_accounts_already_on_the_server() {
+    let server = MockServer::start().await;
+    server.add_account("GitHub", "me");
+    let id = server.add_account("ACME", "alice");
+    let (service, _) = services(&server);
+
+    let entries: Vec<_> = service
+        .export_all()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(backup::entry)
+        .collect();
+    service.delete(id).await.unwrap();
+
+    let results = service.restore(&entries).await.unwrap();
+    assert!(results[0].error.as_deref().unwrap().starts_with("skipped"));
+    assert_eq!(
+        results[1]
+            .account
+            .as_ref()
+            .and_then(|a| a.service.as_deref()),
+        Some("ACME")
+    );
+    assert_eq!(server.accounts().len(), 2);
+}
+
+#[tokio::test]
+async fn reauthenticate_needs_the_stored_token() {
+    let server = MockServer::start().await;
+    let storage = MemoryStorage::default();
+    let credentials = CredentialService::new(storage.clone());
+    let stored = Credentials {
+        server_url: server.url.clone(),
+        api_token: TOKEN.into(),
+        cert_pin: None,
+        capabilities: None,
+        email: None,
+    };
+    credentials.save(&stored).unwrap();
+    let stored = credentials.load().unwrap().unwrap();
+    let client = server.client();
+
+    credentials
+        .reauthenticate(&stored, TOKEN, &client)
+        .await
+        .unwrap();
+    let err = credentials
+        .reauthenticate(&stored, "wrong", &client)
+        .await
+        .unwrap_err();
+    assert!(err.contains("does not match"));
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 1);
+}
+
+#[tokio::test]
+async fn clear_forgets_the_trash_too() {
+    let server = MockServer::start().await;
+    let id = server.add_account("GitHub", "me");
+    let (service, storage) = services(&server);
+    let credentials = CredentialService::new(storage.clone());
+    credentials
+        .save(&Credentials {
+            server_url: server.url.clone(),
+            api_token: TOKEN.into(),
+            cert_pin: None,
+            capabilities: None,
+            email: None,
+        })
+        .unwrap();
+    service.delete(id).await.unwrap();
+
+    credentials.clear().unwrap();
+    assert!(credentials.load().unwrap().is_none());
+    assert!(storage.trash_ids().is_empty());
+}
+
+#[tokio::test]
+async fn replace_token_needs_the_same_user() {
+    let server = MockServer::start().await;
+    server.add_token("rotated-token", "test@example.com");
+    server.add_token("other-token", "someone@example.com");
+    let storage = MemoryStorage::default();
+    let credentials = CredentialService::new(storage.clone());
+    let stored = Credentials {
+        server_url: server.url.clone(),
+        api_token: TOKEN.into(),
+        cert_pin: None,
+        capabilities: None,
+        email: None,
+    };
+    credentials.save(&stored).unwrap();
+    let current = server.client();
+
+    let err = credentials
+        .replace_token(
+            &stored,
+            "other-token".into(),
+            &current,
+            &server.client_with("other-token", fast_policy()),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.contains("different user"));
+    let err = credentials
+        .replace_token(
+            &stored,
+            "revoked-token".into(),
+            &current,
+            &server.client_with("revoked-token", fast_policy()),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.starts_with("new token"), "{err}");
+    assert_eq!(credentials.load().unwrap().unwrap().api_token.expose(), TOKEN);
+
+    let replaced = credentials
+        .replace_token(
+            &stored,
+            "rotated-token".into(),
+            &current,
+            &server.client_with("rotated-token", fast_policy()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(replaced.api_token.expose(), "rotated-token");
+    assert_eq!(replaced.email.as_deref(), Some("test@example.com"));
+    assert_eq!(
+        credentials.load().unwrap().unwrap().api_token.expose(),
+        "rotated-token"
+    );
+}
+
+#[tokio::test]
+async fn replace_token_works_once_the_stored_token_is_revoked() {
+    let server = MockServer::start().await;
+    server.add_token("rotated-token", "test@example.com");
+    server.add_token("other-token", "someone@example.com");
+    server.revoke_token(TOKEN);
+    let credentials = CredentialService::new(MemoryStorage::default());
+    let stored = Credentials {
+        server_url: server.url.clone(),
+        api_token: TOKEN.into(),
+        cert_pin: None,
+        capabilities: None,
+        email: Some("test@example.com".into()),
+    };
+    let current = server.client_with(TOKEN, fast_policy());
+
+    let err = credentials
+        .replace_token(
+            &stored,
+            "other-token".into(),
+            &current,
+            &server.client_with("other-token", fast_policy()),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.contains("different user"));
+    let replaced = credentials
+        .replace_token(
+            &stored,
+            "rotated-token".into(),
+            &current,
+            &server.client_with("rotated-token", fast_policy()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(replaced.api_token.expose(), "rotated-token");
+    // The stored email is trusted; the dead token is never sent.
+    assert_eq!(server.request_count("GET", "/api/v1/user"), 2);
+}
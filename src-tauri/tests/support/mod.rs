@@ -0,0 +1,800 @@
+//! In-process stand-in for a 2FAuth server, enough of its API for the
+//! client and the flows built on it. Each test starts its own on a random
+//! port; faults can be injected per route to exercise the error paths.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use app_lib::api::client::TwoFAuthClient;
+use app_lib::api::retry::RequestPolicy;
+use app_lib::models::types::{Credentials, ExportedAccount};
+use app_lib::store;
+use app_lib::trash::Tombstone;
+
+pub const TOKEN: &str = "test-token";
+
+/// What a route does instead of its normal response.
+#[derive(Clone, Debug)]
+pub enum Fault {
+    /// Responds with this status and a 2FAuth-style `{"message": ...}` body.
+    Status(u16, &'static str),
+    /// 429 with a `Retry-After` of this many seconds.
+    RateLimited(u64),
+    /// 200 with a body that isn't JSON.
+    Malformed,
+    /// Waits this long, then responds normally.
+    Delay(Duration),
+}
+
+struct Rule {
+    method: String,
+    path: String,
+    fault: Fault,
+    /// How many more requests the fault applies to; `None` is forever.
+    remaining: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StoredAccount {
+    pub id: u32,
+    pub service: Option<String>,
+    pub account: Option<String>,
+    pub secret: String,
+    pub otp_type: String,
+    pub digits: u8,
+    pub period: Option<u32>,
+    pub counter: Option<u32>,
+    pub algorithm: String,
+    pub icon: Option<String>,
+    pub group_id: Option<u32>,
+}
+
+impl StoredAccount {
+    fn json(&self, with_otp: bool) -> Value {
+        let mut value = json!({
+            "id": self.id,
+            "service": self.service,
+            "account": self.account,
+            "icon": self.icon,
+            "otp_type": self.otp_type,
+            "digits": self.digits,
+            "period": self.period,
+            "counter": self.counter,
+            "algorithm": self.algorithm,
+            "group_id": self.group_id,
+        });
+        if with_otp {
+            value["otp"] = self.otp();
+        }
+        value
+    }
+
+    fn export(&self) -> Value {
+        json!({
+            "service": self.service,
+            "account": self.account,
+            "icon": self.icon,
+            "otp_type": self.otp_type,
+            "secret": self.secret,
+            "digits": self.digits,
+            "algorithm": self.algorithm,
+            "period": self.period,
+            "counter": self.counter,
+        })
+    }
+
+    /// Not a real code, just stable per account.
+    fn otp(&self) -> Value {
+        json!({
+            "password": format!("{:06}", (self.id as u64 * 137_731) % 1_000_000),
+            "generated_at": 1_700_000_000,
+            "period": self.period,
+            "otp_type": self.otp_type,
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    accounts: Vec<StoredAccount>,
+    groups: Vec<(u32, String)>,
+    icons: HashMap<String, Vec<u8>>,
+    next_id: u32,
+    rules: Vec<Rule>,
+    requests: Vec<String>,
+    /// What `/qrcode/decode` answers with.
+    qr_uri: String,
+    /// Servers from before batch delete answer 405 to it.
+    batch_delete: bool,
+    /// Accepted tokens and the email of the user each belongs to.
+    tokens: HashMap<String, String>,
+    /// The user's preferences, in 2FAuth's order.
+    preferences: Vec<(String, Value)>,
+}
+
+pub struct MockServer {
+    pub url: String,
+    state: Arc<Mutex<State>>,
+    http: reqwest::Client,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(State {
+            next_id: 1,
+            qr_uri: "otpauth://totp/Scanned:me@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Scanned"
+                .into(),
+            batch_delete: true,
+            tokens: HashMap::from([(TOKEN.to_string(), "test@example.com".to_string())]),
+            preferences: vec![
+                ("showOtpAsDot".into(), json!(false)),
+                ("closeOtpOnCopy".into(), json!(false)),
+                ("showAccountsIcons".into(), json!(true)),
+                ("displayMode".into(), json!("list")),
+                ("clearSearchOnCopy".into(), json!(false)),
+            ],
+            ..State::default()
+        }));
+        let shared = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, shared.clone()));
+            }
+        });
+        Self {
+            url,
+            state,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// A client for this server with fast retries, so retry tests don't
+    /// wait on real backoff.
+    pub fn client(&self) -> TwoFAuthClient<'_> {
+        TwoFAuthClient::new(&self.http, &self.url, TOKEN).with_policy(fast_policy())
+    }
+
+    pub fn client_with(&self, token: &str, policy: RequestPolicy) -> TwoFAuthClient<'_> {
+        TwoFAuthClient::new(&self.http, &self.url, token).with_policy(policy)
+    }
+
+    /// Accepts `token` as well, for the user with `email`.
+    pub fn add_token(&self, token: &str, email: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens.insert(token.into(), email.into());
+    }
+
+    /// Stops accepting `token`, as if it was revoked on the server.
+    pub fn revoke_token(&self, token: &str) {
+        self.state.lock().unwrap().tokens.remove(token);
+    }
+
+    pub fn add_account(&self, service: &str, account: &str) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.accounts.push(StoredAccount {
+            id,
+            service: Some(service.into()),
+            account: Some(account.into()),
+            secret: "JBSWY3DPEHPK3PXP".into(),
+            otp_type: "totp".into(),
+            digits: 6,
+            period: Some(30),
+            counter: None,
+            algorithm: "sha1".into(),
+            icon: None,
+            group_id: None,
+        });
+        id
+    }
+
+    pub fn add_group(&self, name: &str) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        let id = state.groups.len() as u32 + 1;
+        state.groups.push((id, name.into()));
+        id
+    }
+
+    pub fn add_icon(&self, filename: &str, bytes: &[u8]) {
+        self.state
+            .lock()
+            .unwrap()
+            .icons
+            .insert(filename.into(), bytes.to_vec());
+    }
+
+    pub fn accounts(&self) -> Vec<StoredAccount> {
+        self.state.lock().unwrap().accounts.clone()
+    }
+
+    pub fn set_qr_uri(&self, uri: &str) {
+        self.state.lock().unwrap().qr_uri = uri.into();
+    }
+
+    pub fn without_batch_delete(&self) {
+        self.state.lock().unwrap().batch_delete = false;
+    }
+
+    pub fn preference(&self, key: &str) -> Option<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .preferences
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    }
+
+    /// Changes a preference the way the web UI would.
+    pub fn set_preference(&self, key: &str, value: Value) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.preferences.iter_mut().find(|(k, _)| k == key) {
+            entry.1 = value;
+        }
+    }
+
+    /// Every request to `method path` gets `fault`. `path` is matched without
+    /// the query string and may end in `*` to match a prefix.
+    pub fn fail(&self, method: &str, path: &str, fault: Fault) {
+        self.add_rule(method, path, fault, None);
+    }
+
+    /// Like `fail`, for the next `times` matching requests only.
+    pub fn fail_times(&self, method: &str, path: &str, fault: Fault, times: usize) {
+        self.add_rule(method, path, fault, Some(times));
+    }
+
+    fn add_rule(&self, method: &str, path: &str, fault: Fault, remaining: Option<usize>) {
+        self.state.lock().unwrap().rules.push(Rule {
+            method: method.into(),
+            path: path.into(),
+            fault,
+            remaining,
+        });
+    }
+
+    /// `METHOD /path?query` of every request received, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn request_count(&self, method: &str, path: &str) -> usize {
+        let prefix = format!("{method} {path}");
+        self.requests()
+            .iter()
+            .filter(|r| **r == prefix || r.starts_with(&format!("{prefix}?")))
+            .count()
+    }
+}
+
+pub fn fast_policy() -> RequestPolicy {
+    RequestPolicy {
+        timeout: Duration::from_secs(5),
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_secs(2),
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    fn ids(&self) -> Vec<u32> {
+        self.query
+            .get("ids")
+            .map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type", "application/json".into())],
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn message(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "message": message }))
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    let Some(request) = read_request(&mut reader).await else {
+        return;
+    };
+
+    let target = match request.query.is_empty() {
+        true => format!("{} {}", request.method, request.path),
+        false => {
+            let mut query: Vec<_> = request.query.iter().map(|(k, v)| format!("{k}={v}")).collect();
+            query.sort();
+            format!("{} {}?{}", request.method, request.path, query.join("&"))
+        }
+    };
+    let fault = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(target);
+        take_fault(&mut state.rules, &request)
+    };
+
+    let response = match fault {
+        Some(Fault::Status(status, message)) => Response::message(status, message),
+        Some(Fault::RateLimited(seconds)) => {
+            let mut response = Response::message(429, "Too Many Attempts.");
+            response.headers.push(("retry-after", seconds.to_string()));
+            response
+        }
+        Some(Fault::Malformed) => Response {
+            status: 200,
+            headers: vec![("content-type", "application/json".into())],
+            body: b"{\"data\": [oops".to_vec(),
+        },
+        Some(Fault::Delay(delay)) => {
+            tokio::time::sleep(delay).await;
+            route(&request, &state)
+        }
+        None => route(&request, &state),
+    };
+
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        _ => "Mock",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {reason}\r\ncontent-length: {}\r\nconnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    // The client may have given up on a delayed response already.
+    let _ = write.write_all(head.as_bytes()).await;
+    let _ = write.write_all(&response.body).await;
+    let _ = write.shutdown().await;
+}
+
+fn take_fault(rules: &mut Vec<Rule>, request: &Request) -> Option<Fault> {
+    let index = rules.iter().position(|rule| {
+        rule.method == request.method
+            && match rule.path.strip_suffix('*') {
+                Some(prefix) => request.path.starts_with(prefix),
+                None => rule.path == request.path,
+            }
+    })?;
+    let rule = &mut rules[index];
+    let fault = rule.fault.clone();
+    if let Some(remaining) = rule.remaining.as_mut() {
+        *remaining -= 1;
+        if *remaining == 0 {
+            rules.remove(index);
+        }
+    }
+    Some(fault)
+}
+
+async fn read_request<R: tokio::io::AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = Url::parse(&format!("http://mock{}", parts.next()?)).ok()?;
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+
+    let mut body = Vec::new();
+    if let Some(len) = headers.get("content-length").and_then(|l| l.parse::<usize>().ok()) {
+        body.resize(len, 0);
+        reader.read_exact(&mut body).await.ok()?;
+    } else if headers.get("transfer-encoding").map(String::as_str) == Some("chunked") {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).await.ok()?;
+            let size = usize::from_str_radix(size.trim(), 16).ok()?;
+            let mut chunk = vec![0u8; size + 2];
+            reader.read_exact(&mut chunk).await.ok()?;
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    }
+
+    Some(Request {
+        method,
+        path: target.path().to_string(),
+        query: target.query_pairs().into_owned().collect(),
+        headers,
+        body,
+    })
+}
+
+fn route(request: &Request, state: &Mutex<State>) -> Response {
+    // Icons are public files, everything else needs the token.
+    if let Some(filename) = request.path.strip_prefix("/storage/icons/") {
+        return match state.lock().unwrap().icons.get(filename) {
+            Some(bytes) => Response {
+                status: 200,
+                headers: vec![("content-type", "image/png".into())],
+                body: bytes.clone(),
+            },
+            None => Response::message(404, "Not found"),
+        };
+    }
+    let mut state = state.lock().unwrap();
+    let email = request
+        .headers
+        .get("authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .and_then(|token| state.tokens.get(token))
+        .cloned();
+    let Some(email) = email else {
+        return Response::message(401, "Unauthenticated.");
+    };
+
+    let segments: Vec<&str> = request
+        .path
+        .trim_start_matches("/api/v1/")
+        .split('/')
+        .collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["user"]) => Response::json(
+            200,
+            json!({ "name": "Test User", "email": email }),
+        ),
+        ("GET", ["user", "preferences"]) => Response::json(
+            200,
+            state
+                .preferences
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect(),
+        ),
+        ("PUT", ["user", "preferences", key]) => {
+            let value = request.json()["value"].clone();
+            if value.is_null() {
+                return validation_error("The value field is required.");
+            }
+            let Some(entry) = state.preferences.iter_mut().find(|(k, _)| k == key) else {
+                return Response::message(400, "Unknown preference");
+            };
+            entry.1 = value.clone();
+            Response::json(200, json!({ "key": key, "value": value }))
+        }
+        ("GET", ["twofaccounts"]) => {
+            let with_otp = request.query.get("withOtp").map(String::as_str) == Some("true");
+            Response::json(
+                200,
+                state.accounts.iter().map(|a| a.json(with_otp)).collect(),
+            )
+        }
+        ("POST", ["twofaccounts"]) => match parse_account(&request.json()) {
+            Ok(mut account) => {
+                account.id = state.next_id;
+                state.next_id += 1;
+                let body = account.json(false);
+                state.accounts.push(account);
+                Response::json(201, body)
+            }
+            Err(message) => validation_error(&message),
+        },
+        ("DELETE", ["twofaccounts"]) => {
+            if !state.batch_delete {
+                return Response::message(405, "The DELETE method is not supported for this route.");
+            }
+            let ids = request.ids();
+            if ids.is_empty() {
+                return validation_error("The ids field is required.");
+            }
+            state.accounts.retain(|a| !ids.contains(&a.id));
+            Response::empty(204)
+        }
+        ("POST", ["twofaccounts", "preview"]) => {
+            let uri = request.json()["uri"].as_str().unwrap_or_default().to_string();
+            match parse_uri(&uri) {
+                Ok(account) => Response::json(
+                    200,
+                    json!({
+                        "service": account.service,
+                        "account": account.account,
+                        "otp_type": account.otp_type,
+                        "digits": account.digits,
+                        "period": account.period,
+                        "algorithm": account.algorithm,
+                        "icon": null,
+                    }),
+                ),
+                Err(message) => validation_error(&message),
+            }
+        }
+        ("POST", ["twofaccounts", "reorder"]) => {
+            let ids: Vec<u32> = serde_json::from_value(request.json()["orderedIds"].clone())
+                .unwrap_or_default();
+            if ids.is_empty() {
+                return validation_error("The ordered ids field is required.");
+            }
+            state.accounts.sort_by_key(|a| {
+                ids.iter().position(|id| *id == a.id).unwrap_or(usize::MAX)
+            });
+            Response::json(200, json!({ "message": "order saved", "orderedIds": ids }))
+        }
+        ("GET", ["twofaccounts", "export"]) => {
+            let ids = request.ids();
+            let data: Vec<Value> = state
+                .accounts
+                .iter()
+                .filter(|a| ids.contains(&a.id))
+                .map(StoredAccount::export)
+                .collect();
+            Response::json(200, json!({ "app": "2fauth_mock", "data": data }))
+        }
+        ("PATCH", ["twofaccounts", "withdraw"]) => {
+            let ids = request.ids();
+            for account in state.accounts.iter_mut().filter(|a| ids.contains(&a.id)) {
+                account.group_id = None;
+            }
+            Response::json(200, json!({ "message": "accounts withdrawn" }))
+        }
+        ("GET", ["twofaccounts", id, "otp"]) => match find(&state.accounts, id) {
+            Some(account) => Response::json(200, account.otp()),
+            None => Response::message(404, "Not found"),
+        },
+        ("PUT", ["twofaccounts", id]) => {
+            let Some(index) = id
+                .parse::<u32>()
+                .ok()
+                .and_then(|id| state.accounts.iter().position(|a| a.id == id))
+            else {
+                return Response::message(404, "Not found");
+            };
+            match parse_account(&request.json()) {
+                Ok(mut account) => {
+                    let current = &state.accounts[index];
+                    account.id = current.id;
+                    account.group_id = current.group_id;
+                    let body = account.json(false);
+                    state.accounts[index] = account;
+                    Response::json(200, body)
+                }
+                Err(message) => validation_error(&message),
+            }
+        }
+        ("DELETE", ["twofaccounts", id]) => match find(&state.accounts, id).map(|a| a.id) {
+            Some(id) => {
+                state.accounts.retain(|a| a.id != id);
+                Response::empty(204)
+            }
+            None => Response::message(404, "Not found"),
+        },
+        ("GET", ["groups"]) => Response::json(
+            200,
+            state
+                .groups
+                .iter()
+                .map(|(id, name)| json!({ "id": id, "name": name, "twofaccounts_count": 0 }))
+                .collect(),
+        ),
+        ("POST", ["groups", id, "assign"]) => {
+            let Some(group_id) = id
+                .parse::<u32>()
+                .ok()
+                .filter(|id| state.groups.iter().any(|(g, _)| g == id))
+            else {
+                return Response::message(404, "Not found");
+            };
+            let ids: Vec<u32> =
+                serde_json::from_value(request.json()["ids"].clone()).unwrap_or_default();
+            for account in state.accounts.iter_mut().filter(|a| ids.contains(&a.id)) {
+                account.group_id = Some(group_id);
+            }
+            Response::json(200, json!({ "id": group_id }))
+        }
+        ("POST", ["qrcode", "decode"]) => {
+            if !multipart_has(request, "qrcode") {
+                return validation_error("The qrcode field is required.");
+            }
+            Response::json(200, json!({ "data": state.qr_uri }))
+        }
+        ("POST", ["icons"]) => {
+            if !multipart_has(request, "icon") {
+                return validation_error("The icon field is required.");
+            }
+            let filename = format!("uploaded{}.png", state.icons.len() + 1);
+            state.icons.insert(filename.clone(), request.body.clone());
+            Response::json(201, json!({ "filename": filename }))
+        }
+        ("POST", ["icons", "default"]) => {
+            let service = request.json()["service"].as_str().unwrap_or_default().to_lowercase();
+            if service != "github" {
+                return Response::message(404, "No icon found");
+            }
+            state.icons.insert("github.png".into(), b"\x89PNG".to_vec());
+            Response::json(201, json!({ "filename": "github.png" }))
+        }
+        _ => Response::message(404, "Not found"),
+    }
+}
+
+fn find<'a>(accounts: &'a [StoredAccount], id: &str) -> Option<&'a StoredAccount> {
+    let id: u32 = id.parse().ok()?;
+    accounts.iter().find(|a| a.id == id)
+}
+
+fn validation_error(message: &str) -> Response {
+    Response::json(422, json!({ "message": message, "errors": {} }))
+}
+
+fn multipart_has(request: &Request, field: &str) -> bool {
+    request
+        .headers
+        .get("content-type")
+        .is_some_and(|t| t.starts_with("multipart/form-data"))
+        && String::from_utf8_lossy(&request.body).contains(&format!("name=\"{field}\""))
+}
+
+fn parse_account(body: &Value) -> Result<StoredAccount, String> {
+    if let Some(uri) = body["uri"].as_str() {
+        return parse_uri(uri);
+    }
+    let secret = body["secret"]
+        .as_str()
+        .filter(|s| !s.is_empty())
+        .ok_or("The secret field is required.")?;
+    let otp_type = body["otp_type"].as_str().unwrap_or("totp").to_lowercase();
+    if !["totp", "hotp", "steamtotp"].contains(&otp_type.as_str()) {
+        return Err("The selected otp type is invalid.".into());
+    }
+    Ok(StoredAccount {
+        id: 0,
+        service: body["service"].as_str().map(String::from),
+        account: body["account"].as_str().map(String::from),
+        secret: secret.to_string(),
+        digits: body["digits"].as_u64().unwrap_or(6) as u8,
+        period: (otp_type != "hotp").then(|| body["period"].as_u64().unwrap_or(30) as u32),
+        counter: (otp_type == "hotp").then(|| body["counter"].as_u64().unwrap_or(0) as u32),
+        algorithm: body["algorithm"].as_str().unwrap_or("sha1").to_string(),
+        icon: body["icon"].as_str().map(String::from),
+        group_id: None,
+        otp_type,
+    })
+}
+
+fn parse_uri(uri: &str) -> Result<StoredAccount, String> {
+    let invalid = || "The uri is not a valid otpauth URI.".to_string();
+    let url = Url::parse(uri).map_err(|_| invalid())?;
+    if url.scheme() != "otpauth" {
+        return Err(invalid());
+    }
+    let otp_type = url.host_str().ok_or_else(invalid)?.to_lowercase();
+    let label = percent_decode(url.path().trim_start_matches('/'));
+    let (issuer, account) = match label.split_once(':') {
+        Some((issuer, account)) => (Some(issuer.to_string()), account.to_string()),
+        None => (None, label),
+    };
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let mut body = json!({
+        "service": query.get("issuer").cloned().or(issuer),
+        "account": account,
+        "secret": query.get("secret"),
+        "otp_type": otp_type,
+        "algorithm": query.get("algorithm").map(|a| a.to_lowercase()),
+    });
+    for key in ["digits", "period", "counter"] {
+        if let Some(value) = query.get(key).and_then(|v| v.parse::<u64>().ok()) {
+            body[key] = json!(value);
+        }
+    }
+    parse_account(&body)
+}
+
+fn percent_decode(s: &str) -> String {
+    Url::parse(&format!("http://x/?v={s}"))
+        .ok()
+        .and_then(|u| u.query_pairs().next().map(|(_, v)| v.into_owned()))
+        .unwrap_or_else(|| s.to_string())
+}
+
+/// A scratch data directory, removed when dropped.
+pub struct TempDir(pub std::path::PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "2fa-{}-{name}-{}",
+            env!("CARGO_CRATE_NAME"),
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        store::create_private_dir(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn creds(token: &str) -> Credentials {
+    Credentials {
+        server_url: "https://2fauth.example.com".into(),
+        api_token: token.into(),
+        cert_pin: None,
+        capabilities: None,
+        email: None,
+    }
+}
+
+/// A deleted account on the `creds` server that never expires.
+pub fn tombstone() -> Tombstone {
+    Tombstone {
+        account_id: 7,
+        server_url: creds("").server_url,
+        deleted_at: 100,
+        expires_at: u64::MAX,
+        account: ExportedAccount {
+            service: Some("GitHub".into()),
+            account: Some("me".into()),
+            icon: None,
+            otp_type: "totp".into(),
+            secret: Some("JBSWY3DPEHPK3PXP".into()),
+            digits: Some(6),
+            algorithm: Some("sha1".into()),
+            period: Some(30),
+            counter: None,
+        },
+    }
+}
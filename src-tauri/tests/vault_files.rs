@@ -0,0 +1,207 @@
+//! How vault files are written: atomically, owner-only, and with a backup
+//! that takes over when the file itself stops decrypting; and how the
+//! secrets in them are kept out of logs and the webview.
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+
+use app_lib::models::types::*;
+use app_lib::store;
+use app_lib::trash::{self, Tombstone};
+use support::{creds, tombstone, TempDir};
+
+fn corrupt(path: &std::path::Path) {
+    let mut data = fs::read(path).unwrap();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    fs::write(path, data).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn vault_files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new("private");
+    store::write_credentials(&dir.0, &creds("old-token")).unwrap();
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+
+    let mode = |path: PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(dir.0.clone()), 0o700);
+    for path in [
+        store::credentials_path(&dir.0),
+        store::backup_path(&store::credentials_path(&dir.0)),
+        store::key_path(&dir.0),
+        trash::trash_path(&dir.0),
+    ] {
+        assert_eq!(mode(path.clone()), 0o600, "{}", path.display());
+    }
+
+    // Files left readable by an older version are tightened.
+    let key = store::key_path(&dir.0);
+    fs::set_permissions(&key, fs::Permissions::from_mode(0o644)).unwrap();
+    store::restrict_permissions(&dir.0).unwrap();
+    assert_eq!(mode(key), 0o600);
+}
+
+#[test]
+fn writes_leave_no_temp_files_behind() {
+    let dir = TempDir::new("temp");
+    store::write_credentials(&dir.0, &creds("one")).unwrap();
+    store::write_credentials(&dir.0, &creds("two")).unwrap();
+    store::write_settings(&dir.0, &Settings::default()).unwrap();
+
+    let mut names: Vec<String> = fs::read_dir(&dir.0)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "credentials.enc",
+            "credentials.enc.bak",
+            "keyfile.key",
+            "settings.json"
+        ]
+    );
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "two"
+    );
+}
+
+#[test]
+fn a_corrupt_vault_is_restored_from_its_backup() {
+    let dir = TempDir::new("restore");
+    store::write_credentials(&dir.0, &creds("old-token")).unwrap();
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+    trash::add_all(&dir.0, vec![Tombstone { account_id: 8, ..tombstone() }], 0).unwrap();
+    let path = store::credentials_path(&dir.0);
+    let good = fs::read(store::backup_path(&path)).unwrap();
+
+    // The backup is the copy before the last write.
+    corrupt(&path);
+    corrupt(&trash::trash_path(&dir.0));
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "old-token"
+    );
+    assert_eq!(fs::read(&path).unwrap(), good);
+    assert_eq!(
+        trash::list(&dir.0, &creds("").server_url, 0).unwrap().len(),
+        1
+    );
+
+    // With the backup gone too there is nothing to fall back on.
+    corrupt(&path);
+    corrupt(&store::backup_path(&path));
+    let err = store::read_credentials(&dir.0).unwrap_err();
+    assert!(err.contains("corrupt vault file"), "{err}");
+}
+
+#[test]
+fn a_file_that_no_longer_decrypts_never_becomes_the_backup() {
+    let dir = TempDir::new("bad-backup");
+    store::write_credentials(&dir.0, &creds("old-token")).unwrap();
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    let path = store::credentials_path(&dir.0);
+
+    // Writing over a corrupt file keeps the good backup instead of it.
+    corrupt(&path);
+    store::write_credentials(&dir.0, &creds("new-token")).unwrap();
+    corrupt(&path);
+    assert_eq!(
+        store::read_credentials(&dir.0)
+            .unwrap()
+            .unwrap()
+            .api_token
+            .expose(),
+        "old-token"
+    );
+}
+
+#[test]
+fn a_leftover_temp_file_does_not_block_writes() {
+    let dir = TempDir::new("stale-temp");
+    let path = store::settings_path(&dir.0);
+    fs::write(dir.0.join(".settings.json.tmp"), "stale").unwrap();
+    store::write_atomic(&path, b"{}").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"{}");
+    assert_eq!(fs::read(dir.0.join(".settings.json.tmp")).unwrap(), b"stale");
+}
+
+#[test]
+fn a_damaged_settings_file_does_not_lock_the_vault() {
+    let dir = TempDir::new("bad-settings");
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    fs::write(store::settings_path(&dir.0), "{ not json").unwrap();
+
+    let read = |dir: &TempDir| store::read_credentials(&dir.0).unwrap().unwrap();
+    assert_eq!(read(&dir).api_token.expose(), "secret-token");
+    store::write_credentials(&dir.0, &creds("new-token")).unwrap();
+    assert_eq!(read(&dir).api_token.expose(), "new-token");
+    assert_eq!(
+        store::read_settings(&dir.0).unwrap().trash_retention_hours,
+        Settings::default().trash_retention_hours
+    );
+}
+
+#[test]
+fn pins_and_url_patterns_stay_with_their_server() {
+    // Written before the server was tracked.
+    let mut settings: Settings = serde_json::from_value(serde_json::json!({
+        "pinned": [3],
+        "url_patterns": [{ "account_id": 3, "pattern": "*.example.com" }],
+    }))
+    .unwrap();
+
+    assert!(settings.claim_for("https://a.example"));
+    assert_eq!(settings.pinned, [3]);
+    assert!(!settings.claim_for("https://a.example"));
+
+    assert!(settings.claim_for("https://b.example"));
+    assert!(settings.pinned.is_empty());
+    assert!(settings.url_patterns.is_empty());
+    assert_eq!(settings.server_url.as_deref(), Some("https://b.example"));
+}
+
+#[test]
+fn clearing_removes_the_backups() {
+    let dir = TempDir::new("clear");
+    store::write_credentials(&dir.0, &creds("old-token")).unwrap();
+    store::write_credentials(&dir.0, &creds("secret-token")).unwrap();
+    trash::add_all(&dir.0, vec![tombstone()], 0).unwrap();
+    trash::add_all(&dir.0, vec![Tombstone { account_id: 8, ..tombstone() }], 0).unwrap();
+    assert!(store::backup_path(&trash::trash_path(&dir.0)).exists());
+
+    trash::clear(&dir.0).unwrap();
+    store::clear_credentials(&dir.0).unwrap();
+    assert!(!store::backup_path(&store::credentials_path(&dir.0)).exists());
+    assert!(!store::backup_path(&trash::trash_path(&dir.0)).exists());
+}
+
+#[test]
+fn the_token_stays_out_of_debug_output_and_views() {
+    let stored = creds("abcdefghijklmnop-1234");
+    assert!(!format!("{stored:?}").contains("abcdefgh"));
+
+    let view = CredentialsView::from(&stored);
+    assert_eq!(view.token_hint, "••••••••1234");
+    assert_eq!(
+        CredentialsView::from(&creds("short")).token_hint,
+        "••••••••"
+    );
+}
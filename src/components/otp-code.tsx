@@ -1,6 +1,7 @@
 import { cn } from "@/lib/utils";
 import { useClipboard } from "@/hooks/use-clipboard";
 import { useUiStore } from "@/stores/ui-store";
+import { useAccountsStore } from "@/stores/accounts-store";
 
 interface OtpCodeProps {
   code: string;
@@ -17,6 +18,7 @@ export function OtpCode({ code, accountId, className }: OtpCodeProps) {
   const copy = useClipboard();
   const copiedId = useUiStore((s) => s.copiedId);
   const isCopied = copiedId === accountId;
+  const masked = useAccountsStore((s) => s.preferences.show_otp_as_dot);
 
   return (
     <button
@@ -29,7 +31,7 @@ export function OtpCode({ code, accountId, className }: OtpCodeProps) {
       )}
       title="Click to copy"
     >
-      <span>{formatCode(code)}</span>
+      <span>{formatCode(masked ? "•".repeat(code.length) : code)}</span>
     </button>
   );
 }
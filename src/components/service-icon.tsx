@@ -1,3 +1,4 @@
+import { convertFileSrc } from "@tauri-apps/api/core";
 import { cn } from "@/lib/utils";
 
 interface ServiceIconProps {
@@ -19,7 +20,7 @@ export function ServiceIcon({
   if (icon) {
     return (
       <img
-        src={icon}
+        src={convertFileSrc(icon, "icon")}
         alt={service ?? ""}
         width={size}
         height={size}
@@ -0,0 +1,23 @@
+import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { useConnectionStore } from "../stores/connection-store";
+
+/**
+ * Whether the server rejected the token, kept current in every window by
+ * `auth-required` and `auth-restored`.
+ */
+export function useAuthRequired() {
+  const authRequired = useConnectionStore((s) => s.authRequired);
+  const setAuthRequired = useConnectionStore((s) => s.setAuthRequired);
+
+  useEffect(() => {
+    const unlistenRequired = listen("auth-required", () => setAuthRequired(true));
+    const unlistenRestored = listen("auth-restored", () => setAuthRequired(false));
+    return () => {
+      unlistenRequired.then((un) => un());
+      unlistenRestored.then((un) => un());
+    };
+  }, [setAuthRequired]);
+
+  return authRequired;
+}
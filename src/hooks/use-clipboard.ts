@@ -1,19 +1,31 @@
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 import { useUiStore } from "../stores/ui-store";
+import { useAccountsStore } from "../stores/accounts-store";
+import { commands } from "../lib/tauri-commands";
+import { useWindowLabel } from "./use-window-label";
 import { useCallback, useRef } from "react";
 
 export function useClipboard() {
   const setCopiedId = useUiStore((s) => s.setCopiedId);
+  const source = useWindowLabel() === "tray-popup" ? "tray" : "app";
   const timerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
 
   const copy = useCallback(
     async (text: string, id: number) => {
       await writeText(text);
+      commands.recordUsage(id).catch(() => {});
       setCopiedId(id);
       if (timerRef.current) clearTimeout(timerRef.current);
       timerRef.current = setTimeout(() => setCopiedId(null), 2000);
+
+      const { preferences, setSearchQuery } = useAccountsStore.getState();
+      if (preferences.clear_search_on_copy) setSearchQuery("");
+      if (preferences.close_otp_on_copy && source === "tray") {
+        getCurrentWindow().hide().catch(() => {});
+      }
     },
-    [setCopiedId]
+    [setCopiedId, source]
   );
 
   return copy;
@@ -1,2 +1,4 @@
 export const DEFAULT_PERIOD = 30;
 export const OTP_REFRESH_INTERVAL = 1000;
+export const PIN_MISMATCH = "certificate pin mismatch";
+export const CANNOT_UNDO = "this server can't export accounts";
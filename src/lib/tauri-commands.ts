@@ -3,24 +3,64 @@ import type {
   Account,
   OtpValue,
   UserInfo,
-  Credentials,
+  CredentialsView,
   CreateAccountPayload,
   AccountPreview,
+  CertificatePinStatus,
+  Settings,
+  ImportPreview,
+  ImportResult,
+  ExportFormat,
+  ExportSummary,
+  AutofillRequest,
+  AuditEntry,
+  AuditFilter,
+  AuditVerification,
+  TrashEntry,
+  BatchResult,
+  KeySource,
 } from "../types";
 
 export const commands = {
-  saveCredentials: (serverUrl: string, apiToken: string) =>
-    invoke<void>("save_credentials", { serverUrl, apiToken }),
+  saveCredentials: (serverUrl: string, apiToken: string, pinCertificate = true) =>
+    invoke<void>("save_credentials", { serverUrl, apiToken, pinCertificate }),
 
   loadCredentials: () => invoke<boolean>("load_credentials"),
 
   clearCredentials: () => invoke<void>("clear_credentials"),
 
-  getCredentials: () => invoke<Credentials>("get_credentials"),
+  getCredentials: () => invoke<CredentialsView>("get_credentials"),
 
   verifyConnection: (serverUrl: string, apiToken: string) =>
     invoke<UserInfo>("verify_connection", { serverUrl, apiToken }),
 
+  reauthenticate: (apiToken: string) =>
+    invoke<void>("reauthenticate", { apiToken }),
+
+  isAuthRequired: () => invoke<boolean>("is_auth_required"),
+
+  replaceApiToken: (apiToken: string) =>
+    invoke<void>("replace_api_token", { apiToken }),
+
+  getCertificatePin: () =>
+    invoke<CertificatePinStatus>("get_certificate_pin"),
+
+  approveCertificatePin: (fingerprint: string) =>
+    invoke<void>("approve_certificate_pin", { fingerprint }),
+
+  clearCertificatePin: () => invoke<void>("clear_certificate_pin"),
+
+  isVaultLocked: () => invoke<boolean>("is_vault_locked"),
+
+  unlockVault: (password: string) =>
+    invoke<boolean>("unlock_vault", { password }),
+
+  migrateKeyStore: (source: KeySource, password?: string) =>
+    invoke<void>("migrate_key_store", { source, password }),
+
+  rotateVaultKey: (password?: string) =>
+    invoke<void>("rotate_vault_key", { password }),
+
   fetchAccounts: () => invoke<Account[]>("fetch_accounts"),
 
   fetchOtp: (accountId: number) =>
@@ -29,12 +69,53 @@ export const commands = {
   createAccount: (payload: CreateAccountPayload) =>
     invoke<Account>("create_account", { payload }),
 
-  deleteAccount: (accountId: number) =>
-    invoke<void>("delete_account", { accountId }),
+  previewImport: (data: number[], password?: string) =>
+    invoke<ImportPreview>("preview_import", { data, password }),
+
+  importAccounts: (indices: number[]) =>
+    invoke<ImportResult[]>("import_accounts", { indices }),
+
+  exportBackup: (password: string) =>
+    invoke<string | null>("export_backup", { password }),
+
+  restoreBackup: (data: number[], password: string) =>
+    invoke<ImportResult[]>("restore_backup", { data, password }),
+
+  exportAccounts: (accountIds: number[], format: ExportFormat) =>
+    invoke<ExportSummary | null>("export_accounts", { accountIds, format }),
+
+  deleteAccount: (accountId: number, forGood = false) =>
+    invoke<void>("delete_account", { accountId, forGood }),
+
+  deleteAccounts: (accountIds: number[]) =>
+    invoke<BatchResult[]>("delete_accounts", { accountIds }),
+
+  moveAccountsToGroup: (accountIds: number[], groupId: number | null) =>
+    invoke<BatchResult[]>("move_accounts_to_group", { accountIds, groupId }),
+
+  listTrash: () => invoke<TrashEntry[]>("list_trash"),
+
+  undoDelete: (accountId: number) =>
+    invoke<Account>("undo_delete", { accountId }),
+
+  reorderAccounts: (accountIds: number[]) =>
+    invoke<void>("reorder_accounts", { accountIds }),
+
+  setPinned: (accountId: number, pinned: boolean) =>
+    invoke<number[]>("set_pinned", { accountId, pinned }),
 
   previewAccount: (uri: string) =>
     invoke<AccountPreview>("preview_account", { uri }),
 
+  uploadIcon: (data: number[], fileName: string) =>
+    invoke<string>("upload_icon", { data, fileName }),
+
+  fetchOfficialIcon: (service: string) =>
+    invoke<string | null>("fetch_official_icon", { service }),
+
+  setAccountIcon: (accountId: number, icon: string | null) =>
+    invoke<Account>("set_account_icon", { accountId, icon }),
+
   decodeQr: (imageData: number[]) =>
     invoke<string>("decode_qr", { imageData }),
 
@@ -49,4 +130,31 @@ export const commands = {
 
   scanScreenForQr: (x: number, y: number, width: number, height: number) =>
     invoke<string>("scan_screen_for_qr", { x, y, width, height }),
+
+  getSettings: () => invoke<Settings>("get_settings"),
+
+  updateSettings: (settings: Settings) =>
+    invoke<void>("update_settings", { settings }),
+
+  syncPreferences: () => invoke<Settings>("sync_preferences"),
+
+  revokeIpcClient: (client: string) =>
+    invoke<string[]>("revoke_ipc_client", { client }),
+
+  searchAccounts: (query: string, limit?: number) =>
+    invoke<Account[]>("search_accounts", { query, limit }),
+
+  recordUsage: (accountId: number) =>
+    invoke<void>("record_usage", { accountId }),
+
+  queryAuditLog: (filter?: AuditFilter) =>
+    invoke<AuditEntry[]>("query_audit_log", { filter }),
+
+  verifyAuditLog: () => invoke<AuditVerification>("verify_audit_log"),
+
+  pendingAutofillRequests: () =>
+    invoke<AutofillRequest[]>("pending_autofill_requests"),
+
+  respondAutofill: (requestId: number, accountId: number | null) =>
+    invoke<void>("respond_autofill", { requestId, accountId }),
 };
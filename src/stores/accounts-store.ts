@@ -1,14 +1,21 @@
 import { create } from "zustand";
+import { toast } from "sonner";
 import { commands } from "../lib/tauri-commands";
-import type { Account } from "../types";
+import { CANNOT_UNDO } from "../lib/constants";
+import type { Account, UserPreferences } from "../types";
 
 interface AccountsStore {
   accounts: Account[];
   isLoading: boolean;
   error: string | null;
   searchQuery: string;
+  rankedIds: number[] | null;
+  pinned: number[];
+  preferences: UserPreferences;
   fetchAccounts: () => Promise<void>;
   deleteAccount: (accountId: number) => Promise<void>;
+  togglePin: (accountId: number) => Promise<void>;
+  moveAccount: (accountId: number, beforeId: number) => Promise<void>;
   setSearchQuery: (q: string) => void;
   filteredAccounts: () => Account[];
 }
@@ -18,27 +25,121 @@ export const useAccountsStore = create<AccountsStore>((set, get) => ({
   isLoading: false,
   error: null,
   searchQuery: "",
+  rankedIds: null,
+  pinned: [],
+  preferences: {
+    show_otp_as_dot: false,
+    show_accounts_icons: true,
+    close_otp_on_copy: false,
+    clear_search_on_copy: false,
+  },
 
   fetchAccounts: async () => {
     set({ isLoading: true, error: null });
     try {
-      const accounts = await commands.fetchAccounts();
-      set({ accounts, isLoading: false });
+      const [accounts, settings] = await Promise.all([
+        commands.fetchAccounts(),
+        commands.syncPreferences(),
+      ]);
+      set({
+        accounts,
+        pinned: settings.pinned,
+        preferences: settings.preferences,
+        isLoading: false,
+      });
     } catch (e) {
       set({ error: String(e), isLoading: false });
     }
   },
 
   deleteAccount: async (accountId) => {
-    await commands.deleteAccount(accountId);
+    const deleted = get().accounts.find((a) => a.id === accountId);
+    const name = deleted?.service ?? "account";
+    try {
+      await commands.deleteAccount(accountId);
+    } catch (e) {
+      if (!String(e).startsWith(CANNOT_UNDO)) throw e;
+      // Without an export there is nothing to restore from, so only delete
+      // once the user accepts that.
+      toast.error(`${name} can't be restored once deleted on this server`, {
+        action: {
+          label: "Delete Anyway",
+          onClick: async () => {
+            try {
+              await commands.deleteAccount(accountId, true);
+              await get().fetchAccounts();
+            } catch (e) {
+              toast.error(String(e));
+            }
+          },
+        },
+      });
+      return;
+    }
     await get().fetchAccounts();
+    toast(`Deleted ${name}`, {
+      action: {
+        label: "Undo",
+        onClick: async () => {
+          try {
+            await commands.undoDelete(accountId);
+            await get().fetchAccounts();
+          } catch (e) {
+            toast.error(String(e));
+          }
+        },
+      },
+    });
   },
 
-  setSearchQuery: (searchQuery) => set({ searchQuery }),
+  togglePin: async (accountId) => {
+    const pinned = await commands.setPinned(
+      accountId,
+      !get().pinned.includes(accountId)
+    );
+    set({ pinned });
+  },
+
+  moveAccount: async (accountId, beforeId) => {
+    const previous = get().accounts;
+    const moved = previous.find((a) => a.id === accountId);
+    if (!moved || accountId === beforeId) return;
+    const rest = previous.filter((a) => a.id !== accountId);
+    const at = rest.findIndex((a) => a.id === beforeId);
+    const accounts = [...rest.slice(0, at), moved, ...rest.slice(at)];
+    set({ accounts });
+    try {
+      await commands.reorderAccounts(accounts.map((a) => a.id));
+    } catch (e) {
+      set({ accounts: previous, error: String(e) });
+    }
+  },
+
+  setSearchQuery: (searchQuery) => {
+    set({ searchQuery, rankedIds: null });
+    if (!searchQuery) return;
+    commands
+      .searchAccounts(searchQuery)
+      .then((ranked) => {
+        if (get().searchQuery === searchQuery) {
+          set({ rankedIds: ranked.map((a) => a.id) });
+        }
+      })
+      .catch(() => {});
+  },
 
   filteredAccounts: () => {
-    const { accounts, searchQuery } = get();
-    if (!searchQuery) return accounts;
+    const { accounts, searchQuery, rankedIds, pinned } = get();
+    if (!searchQuery) {
+      const byId = new Map(accounts.map((a) => [a.id, a]));
+      const first = pinned.flatMap((id) => byId.get(id) ?? []);
+      return [...first, ...accounts.filter((a) => !pinned.includes(a.id))];
+    }
+    if (rankedIds) {
+      // Ranked by the backend; codes come from the live list.
+      const byId = new Map(accounts.map((a) => [a.id, a]));
+      return rankedIds.flatMap((id) => byId.get(id) ?? []);
+    }
     const q = searchQuery.toLowerCase();
     return accounts.filter(
       (a) =>
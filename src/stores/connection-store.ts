@@ -3,24 +3,43 @@ import { commands } from "../lib/tauri-commands";
 
 interface ConnectionStore {
   isConfigured: boolean;
+  isLocked: boolean;
+  /** The server rejected the stored token; cached data stays visible. */
+  authRequired: boolean;
   isLoading: boolean;
   checkConfiguration: () => Promise<void>;
+  unlock: (password: string) => Promise<void>;
   setConfigured: (v: boolean) => void;
+  setAuthRequired: (v: boolean) => void;
 }
 
 export const useConnectionStore = create<ConnectionStore>((set) => ({
   isConfigured: false,
+  isLocked: false,
+  authRequired: false,
   isLoading: true,
 
   checkConfiguration: async () => {
     set({ isLoading: true });
     try {
+      if (await commands.isVaultLocked()) {
+        set({ isLocked: true, isConfigured: false, isLoading: false });
+        return;
+      }
       const configured = await commands.loadCredentials();
-      set({ isConfigured: configured, isLoading: false });
+      const authRequired = configured && (await commands.isAuthRequired());
+      set({ isConfigured: configured, isLocked: false, authRequired, isLoading: false });
     } catch {
       set({ isConfigured: false, isLoading: false });
     }
   },
 
+  unlock: async (password) => {
+    const configured = await commands.unlockVault(password);
+    set({ isConfigured: configured, isLocked: false });
+  },
+
   setConfigured: (isConfigured) => set({ isConfigured }),
+
+  setAuthRequired: (authRequired) => set({ authRequired }),
 }));
@@ -9,6 +9,7 @@ export interface Account {
   counter: number | null;
   algorithm: string | null;
   otp: OtpValue | null;
+  group_id: number | null;
 }
 
 export interface OtpValue {
@@ -35,9 +36,24 @@ export interface CreateAccountPayload {
   algorithm?: string;
 }
 
-export interface Credentials {
+/** The stored credentials; the token itself never reaches the webview. */
+export interface CredentialsView {
   server_url: string;
-  api_token: string;
+  token_hint: string;
+  cert_pin: string | null;
+  /** Unknown until the connection is verified with this app version. */
+  capabilities: ServerCapabilities | null;
+}
+
+export interface ServerCapabilities {
+  groups: boolean;
+  export: boolean;
+  preferences: boolean;
+}
+
+export interface CertificatePinStatus {
+  pinned: string | null;
+  presented: string | null;
 }
 
 export interface AccountPreview {
@@ -49,3 +65,139 @@ export interface AccountPreview {
   algorithm: string | null;
   icon: string | null;
 }
+
+export interface Settings {
+  request_timeout_secs: number;
+  max_retries: number;
+  ipc_enabled: boolean;
+  ipc_allowlist: string[];
+  url_patterns: UrlPattern[];
+  pinned: number[];
+  server_url: string | null;
+  trash_retention_hours: number;
+  key_source: KeySource;
+  preferences: UserPreferences;
+}
+
+/** The user's 2FAuth preferences, kept in step with the server. */
+export interface UserPreferences {
+  show_otp_as_dot: boolean;
+  show_accounts_icons: boolean;
+  close_otp_on_copy: boolean;
+  clear_search_on_copy: boolean;
+}
+
+export type KeySource = "keyfile" | "keyring" | "password";
+
+export interface BatchResult {
+  account_id: number;
+  error: string | null;
+}
+
+export interface TrashEntry {
+  account_id: number;
+  service: string | null;
+  account: string | null;
+  deleted_at: number;
+  expires_at: number;
+}
+
+export type ImportStatus = "ready" | "duplicate" | "unsupported";
+
+export interface ImportCandidate {
+  index: number;
+  service: string | null;
+  account: string | null;
+  otp_type: string;
+  status: ImportStatus;
+  reason: string | null;
+}
+
+export interface ImportPreview {
+  format: string;
+  candidates: ImportCandidate[];
+}
+
+export interface ImportResult {
+  index: number;
+  account: Account | null;
+  error: string | null;
+}
+
+export type ExportFormat = "uri_list" | "qr_codes" | "migration";
+
+export interface ExportSkip {
+  account: string;
+  reason: string;
+}
+
+export interface ExportSummary {
+  directory: string;
+  files: string[];
+  skipped: ExportSkip[];
+}
+
+export interface UrlPattern {
+  account_id: number;
+  pattern: string;
+}
+
+export interface AutofillCandidate {
+  id: number;
+  service: string | null;
+  account: string | null;
+}
+
+export interface AutofillRequest {
+  id: number;
+  origin: string | null;
+  host: string;
+  candidates: AutofillCandidate[];
+}
+
+export type AuditAction =
+  | "code_copied"
+  | "code_requested"
+  | "account_created"
+  | "account_deleted"
+  | "account_updated"
+  | "accounts_imported"
+  | "accounts_exported"
+  | "backup_exported"
+  | "backup_restored"
+  | "credentials_saved"
+  | "credentials_cleared"
+  | "certificate_pin_changed"
+  | "reauthenticated"
+  | "ipc_client_approved"
+  | "key_store_changed"
+  | "vault_key_rotated"
+  | "api_token_replaced";
+
+export type AuditSource = "app" | "tray" | "hotkey" | "ipc" | "browser" | "cli";
+
+export interface AuditEntry {
+  seq: number;
+  timestamp: number;
+  action: AuditAction;
+  account_id: number | null;
+  profile: string | null;
+  source: AuditSource;
+  detail: string | null;
+  prev_hash: string;
+  hash: string;
+}
+
+export interface AuditFilter {
+  action?: AuditAction;
+  account_id?: number;
+  source?: AuditSource;
+  since?: number;
+  until?: number;
+  limit?: number;
+}
+
+export interface AuditVerification {
+  entries: number;
+  broken_at: number | null;
+}
@@ -1,4 +1,5 @@
-import { useState, useEffect, useCallback } from "react";
+import { useState, useEffect, useCallback, useRef } from "react";
+import { toast } from "sonner";
 import { ServiceIcon } from "@/components/service-icon";
 import { OtpCode } from "@/components/otp-code";
 import { CountdownRing } from "@/components/countdown-ring";
@@ -9,6 +10,7 @@ import { useUiStore } from "@/stores/ui-store";
 import { commands } from "@/lib/tauri-commands";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import type { Account } from "@/types";
+import { Pin } from "lucide-react";
 
 interface AccountItemProps {
   account: Account;
@@ -19,6 +21,25 @@ interface AccountItemProps {
 export function AccountItem({ account, remaining, isFocused }: AccountItemProps) {
   const period = account.period ?? DEFAULT_PERIOD;
   const deleteAccount = useAccountsStore((s) => s.deleteAccount);
+  const togglePin = useAccountsStore((s) => s.togglePin);
+  const fetchAccounts = useAccountsStore((s) => s.fetchAccounts);
+  const fileRef = useRef<HTMLInputElement>(null);
+
+  const setIcon = async (icon: Promise<string | null>) => {
+    try {
+      const filename = await icon;
+      if (!filename) {
+        toast.error("No official icon for this service");
+        return;
+      }
+      await commands.setAccountIcon(account.id, filename);
+      await fetchAccounts();
+    } catch (e) {
+      toast.error(String(e));
+    }
+  };
+  const isPinned = useAccountsStore((s) => s.pinned.includes(account.id));
+  const showIcon = useAccountsStore((s) => s.preferences.show_accounts_icons);
   const copy = useClipboard();
   const copiedId = useUiStore((s) => s.copiedId);
   const isCopied = copiedId === account.id;
@@ -49,9 +70,10 @@ export function AccountItem({ account, remaining, isFocused }: AccountItemProps)
         setConfirming(false);
       }}
     >
-      <ServiceIcon icon={account.icon} service={account.service} />
+      {showIcon && <ServiceIcon icon={account.icon} service={account.service} />}
       <div className="flex-1 min-w-0">
-        <p className="text-sm font-medium truncate text-[#F8FAFC]">
+        <p className="flex items-center gap-1 text-sm font-medium truncate text-[#F8FAFC]">
+          {isPinned && <Pin className="size-3 shrink-0 text-[#F97316]" />}
           {account.service ?? "Unknown"}
         </p>
         {account.account && (
@@ -72,6 +94,21 @@ export function AccountItem({ account, remaining, isFocused }: AccountItemProps)
           )}
         </div>
       )}
+      <input
+        ref={fileRef}
+        type="file"
+        accept="image/*"
+        className="hidden"
+        onClick={(e) => e.stopPropagation()}
+        onChange={async (e) => {
+          const file = e.target.files?.[0];
+          e.target.value = "";
+          close();
+          if (!file) return;
+          const data = Array.from(new Uint8Array(await file.arrayBuffer()));
+          setIcon(commands.uploadIcon(data, file.name));
+        }}
+      />
       {menu && (
         <div
           className="fixed z-50 min-w-[140px] rounded-none border border-[#2D2D44] bg-[#1E1E32] p-1 shadow-md"
@@ -86,7 +123,7 @@ export function AccountItem({ account, remaining, isFocused }: AccountItemProps)
                   try {
                     const creds = await commands.getCredentials();
                     const url = `${creds.server_url}/api/v1/twofaccounts/${account.id}/otp`;
-                    const curl = `curl -s -H "Authorization: Bearer ${creds.api_token}" "${url}"`;
+                    const curl = `curl -s -H "Authorization: Bearer $TWOFAUTH_API_TOKEN" "${url}"`;
                     await writeText(curl);
                   } catch {}
                   close();
@@ -94,6 +131,32 @@ export function AccountItem({ account, remaining, isFocused }: AccountItemProps)
               >
                 Copy cURL
               </button>
+              <button
+                className="w-full rounded-none px-2 py-1.5 text-sm text-[#F8FAFC] hover:bg-[#1A1A2E] text-left"
+                onClick={() => {
+                  togglePin(account.id).catch(() => {});
+                  close();
+                }}
+              >
+                {isPinned ? "Unpin" : "Pin"}
+              </button>
+              {account.service && (
+                <button
+                  className="w-full rounded-none px-2 py-1.5 text-sm text-[#F8FAFC] hover:bg-[#1A1A2E] text-left"
+                  onClick={() => {
+                    setIcon(commands.fetchOfficialIcon(account.service!));
+                    close();
+                  }}
+                >
+                  Use Official Icon
+                </button>
+              )}
+              <button
+                className="w-full rounded-none px-2 py-1.5 text-sm text-[#F8FAFC] hover:bg-[#1A1A2E] text-left"
+                onClick={() => fileRef.current?.click()}
+              >
+                Upload Icon…
+              </button>
               <button
                 className="w-full rounded-none px-2 py-1.5 text-sm text-red-500 hover:bg-[#1A1A2E] text-left"
                 onClick={() => setConfirming(true)}
@@ -6,6 +6,7 @@ import { Separator } from "@/components/ui/separator";
 import { useAccountsStore } from "@/stores/accounts-store";
 import { useUiStore } from "@/stores/ui-store";
 import { useOtpTimer } from "@/hooks/use-otp-timer";
+import { useAuthRequired } from "@/hooks/use-auth-required";
 import { useClipboard } from "@/hooks/use-clipboard";
 import { AccountItem } from "./account-item";
 import { AddAccountDialog } from "./add-account";
@@ -21,17 +22,26 @@ export function AccountsScreen() {
     fetchAccounts,
     setSearchQuery,
     filteredAccounts,
+    moveAccount,
   } = useAccountsStore();
   const { addDialogOpen, setAddDialogOpen } = useUiStore();
   const copy = useClipboard();
   const searchRef = useRef<HTMLInputElement>(null);
   const [focusedIndex, setFocusedIndex] = useState(-1);
+  const [draggedId, setDraggedId] = useState<number | null>(null);
+
+  const authRequired = useAuthRequired();
 
   const handleRefresh = useCallback(() => {
     fetchAccounts();
   }, [fetchAccounts]);
 
-  const remaining = useOtpTimer(accounts, handleRefresh);
+  // Codes rolling over would only hit the server with a rejected token.
+  const handleExpired = useCallback(() => {
+    if (!authRequired) fetchAccounts();
+  }, [authRequired, fetchAccounts]);
+
+  const remaining = useOtpTimer(accounts, handleExpired);
 
   useEffect(() => {
     fetchAccounts();
@@ -116,6 +126,16 @@ export function AccountsScreen() {
           onChange={(e) => setSearchQuery(e.target.value)}
         />
       </div>
+      {authRequired && (
+        <div className="mx-3 mb-2 flex items-center justify-between gap-2 border border-[#F97316] px-3 py-2">
+          <p className="text-xs text-[#F8FAFC]">
+            The server rejected the API token. Codes shown may be out of date.
+          </p>
+          <Button size="sm" variant="outline" onClick={() => setScreen("setup")}>
+            Sign in again
+          </Button>
+        </div>
+      )}
       <Separator className="bg-[#2D2D44]" />
       <ScrollArea className="flex-1">
         <div className="p-2">
@@ -140,12 +160,27 @@ export function AccountsScreen() {
             </div>
           )}
           {filtered.map((account, index) => (
-            <AccountItem
+            <div
               key={account.id}
-              account={account}
-              remaining={remaining[account.id] ?? 0}
-              isFocused={index === focusedIndex}
-            />
+              draggable={!searchQuery}
+              onDragStart={() => setDraggedId(account.id)}
+              onDragEnd={() => setDraggedId(null)}
+              onDragOver={(e) => {
+                if (draggedId !== null) e.preventDefault();
+              }}
+              onDrop={(e) => {
+                e.preventDefault();
+                if (draggedId !== null) moveAccount(draggedId, account.id);
+                setDraggedId(null);
+              }}
+              className={draggedId === account.id ? "opacity-50" : undefined}
+            >
+              <AccountItem
+                account={account}
+                remaining={remaining[account.id] ?? 0}
+                isFocused={index === focusedIndex}
+              />
+            </div>
           ))}
         </div>
       </ScrollArea>
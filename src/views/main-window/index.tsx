@@ -3,13 +3,14 @@ import { useConnectionStore } from "@/stores/connection-store";
 import { useUiStore } from "@/stores/ui-store";
 import { WelcomeScreen } from "./welcome-screen";
 import { SetupScreen } from "./setup-screen";
+import { UnlockScreen } from "./unlock-screen";
 import { AccountsScreen } from "./accounts-screen";
 import { Toaster } from "sonner";
 import { UpdateChecker } from "@/components/update-checker";
 import { Loader2 } from "lucide-react";
 
 export function MainWindowView() {
-  const { isConfigured, isLoading, checkConfiguration } = useConnectionStore();
+  const { isConfigured, isLocked, isLoading, checkConfiguration } = useConnectionStore();
   const { screen, setScreen } = useUiStore();
 
   useEffect(() => {
@@ -28,6 +29,15 @@ export function MainWindowView() {
     );
   }
 
+  if (isLocked) {
+    return (
+      <>
+        <UnlockScreen onUnlocked={() => setScreen("accounts")} />
+        <Toaster position="bottom-center" />
+      </>
+    );
+  }
+
   if (isConfigured && screen !== "setup") {
     return (
       <>
@@ -11,6 +11,8 @@ import {
 } from "@/components/ui/card";
 import { Badge } from "@/components/ui/badge";
 import { commands } from "@/lib/tauri-commands";
+import type { CertificatePinStatus } from "@/types";
+import { PIN_MISMATCH } from "@/lib/constants";
 import { useConnectionStore } from "@/stores/connection-store";
 import { useUiStore } from "@/stores/ui-store";
 import { ArrowLeft, Loader2, Shield } from "lucide-react";
@@ -19,20 +21,25 @@ export function SetupScreen() {
   const isConfigured = useConnectionStore((s) => s.isConfigured);
   const [serverUrl, setServerUrl] = useState("");
   const [apiToken, setApiToken] = useState("");
+  const [tokenHint, setTokenHint] = useState<string | null>(null);
   const [testing, setTesting] = useState(false);
   const [saving, setSaving] = useState(false);
   const [testResult, setTestResult] = useState<{
     ok: boolean;
     message: string;
   } | null>(null);
+  const [certificate, setCertificate] = useState<CertificatePinStatus | null>(
+    null,
+  );
   const setConfigured = useConnectionStore((s) => s.setConfigured);
+  const setAuthRequired = useConnectionStore((s) => s.setAuthRequired);
   const setScreen = useUiStore((s) => s.setScreen);
 
   useEffect(() => {
     if (isConfigured) {
       commands.getCredentials().then((creds) => {
         setServerUrl(creds.server_url);
-        setApiToken(creds.api_token);
+        setTokenHint(creds.token_hint);
       }).catch(() => {});
     }
   }, [isConfigured]);
@@ -53,11 +60,40 @@ export function SetupScreen() {
     }
   };
 
+  const handleTrustCertificate = async () => {
+    try {
+      const status = await commands.getCertificatePin();
+      if (!status.presented) {
+        setTestResult({
+          ok: false,
+          message: "The server did not present a certificate this device trusts",
+        });
+        return;
+      }
+      setCertificate(status);
+    } catch (e) {
+      setTestResult({ ok: false, message: String(e) });
+    }
+  };
+
+  const handleConfirmCertificate = async () => {
+    if (!certificate?.presented) return;
+    try {
+      await commands.approveCertificatePin(certificate.presented);
+      setCertificate(null);
+      await handleTest();
+    } catch (e) {
+      setCertificate(null);
+      setTestResult({ ok: false, message: String(e) });
+    }
+  };
+
   const handleSave = async () => {
     setSaving(true);
     try {
       await commands.saveCredentials(serverUrl, apiToken);
       setConfigured(true);
+      setAuthRequired(false);
       setScreen("accounts");
     } catch (e) {
       setTestResult({ ok: false, message: String(e) });
@@ -106,7 +142,11 @@ export function SetupScreen() {
             <Input
               id="api-token"
               type="password"
-              placeholder="Your Personal Access Token"
+              placeholder={
+                tokenHint
+                  ? `Re-enter the token (${tokenHint})`
+                  : "Your Personal Access Token"
+              }
               value={apiToken}
               onChange={(e) => setApiToken(e.target.value)}
               className="text-xs"
@@ -118,6 +158,52 @@ export function SetupScreen() {
               {testResult.message}
             </Badge>
           )}
+          {certificate?.presented && (
+            <div className="space-y-2 rounded-md border border-[#2D2D44] p-2 text-[11px] text-[#94A3B8]">
+              <p>
+                Only trust this certificate if it matches the one your server
+                administrator expects.
+              </p>
+              <p>
+                Pinned:{" "}
+                <span className="font-mono break-all text-[#F8FAFC]">
+                  {certificate.pinned ?? "none"}
+                </span>
+              </p>
+              <p>
+                Presented:{" "}
+                <span className="font-mono break-all text-[#F8FAFC]">
+                  {certificate.presented}
+                </span>
+              </p>
+              <div className="flex gap-2">
+                <Button
+                  variant="outline"
+                  className="flex-1 text-xs"
+                  onClick={() => setCertificate(null)}
+                >
+                  Cancel
+                </Button>
+                <Button
+                  className="flex-1 text-xs"
+                  onClick={handleConfirmCertificate}
+                >
+                  Trust
+                </Button>
+              </div>
+            </div>
+          )}
+          {isConfigured &&
+            !certificate &&
+            testResult?.message.startsWith(PIN_MISMATCH) && (
+              <Button
+                variant="outline"
+                className="w-full text-xs"
+                onClick={handleTrustCertificate}
+              >
+                Trust New Certificate
+              </Button>
+            )}
 
           <div className="flex flex-col gap-2">
             <Button
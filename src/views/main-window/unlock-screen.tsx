@@ -0,0 +1,81 @@
+import { useState } from "react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Badge } from "@/components/ui/badge";
+import { useConnectionStore } from "@/stores/connection-store";
+import { Loader2, Lock } from "lucide-react";
+
+interface UnlockScreenProps {
+  onUnlocked: () => void;
+}
+
+export function UnlockScreen({ onUnlocked }: UnlockScreenProps) {
+  const unlock = useConnectionStore((s) => s.unlock);
+  const [password, setPassword] = useState("");
+  const [unlocking, setUnlocking] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const handleUnlock = async () => {
+    setUnlocking(true);
+    setError(null);
+    try {
+      await unlock(password);
+      onUnlocked();
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setUnlocking(false);
+    }
+  };
+
+  return (
+    <div className="flex items-center justify-center min-h-screen p-3 bg-[#0F0F1A]">
+      <Card className="w-full max-w-sm border-l-[1px] border-l-[#2D2D44]">
+        <CardHeader className="text-center">
+          <Lock className="size-8 text-[#F97316] mx-auto mb-1" />
+          <CardTitle className="text-lg">2FA Auth</CardTitle>
+          <CardDescription className="text-[#94A3B8] text-xs">
+            Enter your master password to unlock
+          </CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-3">
+          <div className="space-y-1">
+            <Label htmlFor="master-password" className="text-[#94A3B8] uppercase text-[11px] tracking-widest">Master Password</Label>
+            <Input
+              id="master-password"
+              type="password"
+              autoFocus
+              value={password}
+              onChange={(e) => setPassword(e.target.value)}
+              onKeyDown={(e) => e.key === "Enter" && password && handleUnlock()}
+              className="text-xs"
+            />
+          </div>
+
+          {error && (
+            <Badge variant="destructive" className="w-full justify-center py-1">
+              {error}
+            </Badge>
+          )}
+
+          <Button
+            className="w-full text-xs"
+            onClick={handleUnlock}
+            disabled={!password || unlocking}
+          >
+            {unlocking && <Loader2 className="animate-spin" />}
+            Unlock
+          </Button>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
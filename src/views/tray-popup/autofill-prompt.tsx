@@ -0,0 +1,66 @@
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { commands } from "@/lib/tauri-commands";
+import type { AutofillRequest } from "@/types";
+
+export function AutofillPrompt() {
+  const [requests, setRequests] = useState<AutofillRequest[]>([]);
+
+  useEffect(() => {
+    commands.pendingAutofillRequests().then(setRequests).catch(() => {});
+    const added = listen<AutofillRequest>("autofill-request", (e) =>
+      setRequests((rs) => [...rs.filter((r) => r.id !== e.payload.id), e.payload])
+    );
+    const resolved = listen<number>("autofill-resolved", (e) =>
+      setRequests((rs) => rs.filter((r) => r.id !== e.payload))
+    );
+    return () => {
+      added.then((un) => un());
+      resolved.then((un) => un());
+    };
+  }, []);
+
+  const request = requests[0];
+  if (!request) return null;
+
+  const answer = (accountId: number | null) => {
+    setRequests((rs) => rs.filter((r) => r.id !== request.id));
+    commands.respondAutofill(request.id, accountId).catch(() => {});
+  };
+
+  return (
+    <div className="absolute inset-0 z-50 flex flex-col gap-2 bg-[#0F0F1A] p-3">
+      <p className="text-xs font-semibold uppercase tracking-wider text-[#F97316]">
+        Autofill request
+      </p>
+      <p className="text-xs text-[#F8FAFC]">
+        Your browser wants a code for <span className="font-mono">{request.host}</span>
+      </p>
+      {request.origin && (
+        <p className="text-[10px] text-[#94A3B8] truncate">{request.origin}</p>
+      )}
+      <div className="flex-1 overflow-y-auto">
+        {request.candidates.map((c) => (
+          <button
+            key={c.id}
+            className="w-full rounded-none px-2 py-1.5 text-left hover:bg-[#1A1A2E]"
+            onClick={() => answer(c.id)}
+          >
+            <p className="text-xs font-medium text-[#F8FAFC] truncate">
+              {c.service ?? "Unknown"}
+            </p>
+            {c.account && (
+              <p className="text-[10px] text-[#94A3B8] truncate">{c.account}</p>
+            )}
+          </button>
+        ))}
+      </div>
+      <button
+        className="rounded-none border border-[#2D2D44] px-2 py-1.5 text-xs text-red-500 hover:bg-[#1A1A2E]"
+        onClick={() => answer(null)}
+      >
+        Deny
+      </button>
+    </div>
+  );
+}
@@ -1,15 +1,18 @@
 import { useEffect, useCallback } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { Input } from "@/components/ui/input";
 import { ScrollArea } from "@/components/ui/scroll-area";
 import { useAccountsStore } from "@/stores/accounts-store";
 import { useConnectionStore } from "@/stores/connection-store";
 import { useOtpTimer } from "@/hooks/use-otp-timer";
+import { useAuthRequired } from "@/hooks/use-auth-required";
 import { TrayAccountItem } from "./tray-account-item";
 import { TrayFooter } from "./tray-footer";
+import { AutofillPrompt } from "./autofill-prompt";
 import { Toaster } from "sonner";
 
 export function TrayPopupView() {
-  const { isConfigured, checkConfiguration } = useConnectionStore();
+  const { isConfigured, isLocked, checkConfiguration } = useConnectionStore();
   const {
     accounts,
     isLoading,
@@ -27,9 +30,24 @@ export function TrayPopupView() {
     });
   }, [checkConfiguration, fetchAccounts]);
 
+  // Codes were hidden while the token was rejected; fetch them again as soon
+  // as the main window has new credentials.
+  useEffect(() => {
+    const unlisten = listen("auth-restored", () => {
+      checkConfiguration().then(() => {
+        if (useConnectionStore.getState().isConfigured) fetchAccounts();
+      });
+    });
+    return () => {
+      unlisten.then((un) => un());
+    };
+  }, [checkConfiguration, fetchAccounts]);
+
+  const authRequired = useAuthRequired();
+
   const handleRefresh = useCallback(() => {
-    fetchAccounts();
-  }, [fetchAccounts]);
+    if (!authRequired) fetchAccounts();
+  }, [authRequired, fetchAccounts]);
 
   const remaining = useOtpTimer(accounts, handleRefresh);
   const filtered = filteredAccounts();
@@ -38,14 +56,22 @@ export function TrayPopupView() {
     return (
       <div className="flex items-center justify-center h-screen bg-[#0F0F1A] rounded-none p-4">
         <p className="text-xs text-[#94A3B8] text-center">
-          Not configured. Open Settings to connect.
+          {isLocked
+            ? "Locked. Open the app to enter your master password."
+            : "Not configured. Open Settings to connect."}
         </p>
       </div>
     );
   }
 
   return (
-    <div className="flex flex-col h-screen bg-[#0F0F1A] rounded-none overflow-hidden">
+    <div className="relative flex flex-col h-screen bg-[#0F0F1A] rounded-none overflow-hidden">
+      <AutofillPrompt />
+      {authRequired && (
+        <p className="px-2 pt-2 text-[10px] text-[#F97316]">
+          Token rejected. Open the app to sign in again.
+        </p>
+      )}
       <div className="px-2 pt-2 pb-1">
         <Input
           placeholder="Search..."
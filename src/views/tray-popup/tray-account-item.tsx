@@ -18,6 +18,9 @@ interface TrayAccountItemProps {
 export function TrayAccountItem({ account, remaining }: TrayAccountItemProps) {
   const period = account.period ?? DEFAULT_PERIOD;
   const deleteAccount = useAccountsStore((s) => s.deleteAccount);
+  const togglePin = useAccountsStore((s) => s.togglePin);
+  const isPinned = useAccountsStore((s) => s.pinned.includes(account.id));
+  const showIcon = useAccountsStore((s) => s.preferences.show_accounts_icons);
   const copy = useClipboard();
   const copiedId = useUiStore((s) => s.copiedId);
   const isCopied = copiedId === account.id;
@@ -48,7 +51,7 @@ export function TrayAccountItem({ account, remaining }: TrayAccountItemProps) {
         setConfirming(false);
       }}
     >
-      <ServiceIcon icon={account.icon} service={account.service} size={24} />
+      {showIcon && <ServiceIcon icon={account.icon} service={account.service} size={24} />}
       <div className="flex-1 min-w-0">
         <p className="text-xs font-medium truncate text-[#F8FAFC]">
           {account.service ?? "Unknown"}
@@ -84,7 +87,7 @@ export function TrayAccountItem({ account, remaining }: TrayAccountItemProps) {
                   try {
                     const creds = await commands.getCredentials();
                     const url = `${creds.server_url}/api/v1/twofaccounts/${account.id}/otp`;
-                    const curl = `curl -s -H "Authorization: Bearer ${creds.api_token}" "${url}"`;
+                    const curl = `curl -s -H "Authorization: Bearer $TWOFAUTH_API_TOKEN" "${url}"`;
                     await writeText(curl);
                   } catch {}
                   close();
@@ -92,6 +95,15 @@ export function TrayAccountItem({ account, remaining }: TrayAccountItemProps) {
               >
                 Copy cURL
               </button>
+              <button
+                className="w-full rounded-none px-2 py-1.5 text-sm text-[#F8FAFC] hover:bg-[#1A1A2E] text-left"
+                onClick={() => {
+                  togglePin(account.id).catch(() => {});
+                  close();
+                }}
+              >
+                {isPinned ? "Unpin" : "Pin"}
+              </button>
               <button
                 className="w-full rounded-none px-2 py-1.5 text-sm text-red-500 hover:bg-[#1A1A2E] text-left"
                 onClick={() => setConfirming(true)}